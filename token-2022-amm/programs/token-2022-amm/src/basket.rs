@@ -0,0 +1,578 @@
+//! Multi-asset pools ("baskets") holding three or more Token-2022 mints.
+//! Any two members can be swapped against each other under a weighted or
+//! stable invariant. Liquidity goes in and out in proportion to every
+//! balance. Each member's mint, vault and user token account come in
+//! `remaining_accounts`, followed by that mint's transfer-hook accounts, so
+//! hooks are resolved one mint at a time.
+//!
+//! A basket's PDA is seeded by the hash of its sorted member mints, so each
+//! set of mints has one basket, and its LP mint is a PDA of the basket.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token_2022::{self, Token2022};
+use anchor_spl::token_interface::{Mint, TokenAccount};
+use spl_token_2022::onchain::invoke_transfer_checked;
+
+use crate::curve::stable::{self, amp_in_range};
+use crate::curve::weighted::{MIN_WEIGHT, WEIGHT_ONE};
+use crate::curve::{mul_div, SwapCurve, WeightedCurve};
+use crate::{Amm, AmmError, LP_DECIMALS};
+
+pub const MIN_BASKET_MEMBERS: usize = 3;
+pub const MAX_BASKET_MEMBERS: usize = 8;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum BasketCurve {
+    /// Balancer-style prod(x_i^w_i) with per-member weights
+    Weighted,
+    /// StableSwap over all members with a fixed amplification
+    Stable { amp: u64 },
+}
+
+impl BasketCurve {
+    /// `weights` must sum to `WEIGHT_ONE` for weighted baskets and be all
+    /// zero for stable ones
+    fn validate(&self, weights: &[u64]) -> Result<()> {
+        let valid = match self {
+            BasketCurve::Weighted => {
+                weights.iter().all(|w| *w >= MIN_WEIGHT)
+                    && weights.iter().try_fold(0u64, |sum, w| sum.checked_add(*w)) == Some(WEIGHT_ONE)
+            }
+            BasketCurve::Stable { amp } => amp_in_range(*amp) && weights.iter().all(|w| *w == 0),
+        };
+        require!(valid, AmmError::InvalidCurveParameters);
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct BasketMember {
+    pub mint: Pubkey,
+    pub vault: Pubkey,
+    pub balance: u64,
+    pub weight: u64,           // Scaled by WEIGHT_ONE; zero in stable baskets
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct BasketPool {
+    pub lp_mint: Pubkey,
+    pub curve: BasketCurve,
+    #[max_len(MAX_BASKET_MEMBERS)]
+    pub members: Vec<BasketMember>, // Sorted by mint
+    pub lp_supply: u64,
+    pub locked: bool,
+    pub bump: u8,
+}
+
+impl BasketPool {
+    fn balances(&self) -> Vec<u64> {
+        self.members.iter().map(|m| m.balance).collect()
+    }
+
+    /// Output for `amount_in` (after fees) of member `i` paid out in member `j`
+    pub fn swap_exact_in(&self, i: usize, j: usize, amount_in: u64) -> Result<u64> {
+        require!(
+            i != j && i < self.members.len() && j < self.members.len(),
+            AmmError::InvalidTokenPair
+        );
+        let (member_in, member_out) = (&self.members[i], &self.members[j]);
+        match self.curve {
+            BasketCurve::Weighted => WeightedCurve::for_pair(member_in.weight, member_out.weight)?
+                .swap_exact_in(amount_in, member_in.balance, member_out.balance, true),
+            BasketCurve::Stable { amp } => stable::swap_exact_in(amp, &self.balances(), i, j, amount_in),
+        }
+    }
+}
+
+/// One member's slice of `remaining_accounts`
+struct MemberAccounts<'a, 'info> {
+    mint: &'a AccountInfo<'info>,
+    vault: &'a AccountInfo<'info>,
+    user: &'a AccountInfo<'info>,
+    hook: &'a [AccountInfo<'info>],
+}
+
+impl<'a, 'info> MemberAccounts<'a, 'info> {
+    /// Split `remaining_accounts` into `[mint, vault, user token account,
+    /// hook accounts...]` groups, sized by `hook_account_counts`
+    fn split(remaining: &'a [AccountInfo<'info>], hook_account_counts: &[u8]) -> Result<Vec<Self>> {
+        let mut rest = remaining;
+        let mut groups = Vec::with_capacity(hook_account_counts.len());
+        for count in hook_account_counts {
+            let len = 3 + *count as usize;
+            require!(rest.len() >= len, ErrorCode::AccountNotEnoughKeys);
+            let (group, tail) = rest.split_at(len);
+            groups.push(Self {
+                mint: &group[0],
+                vault: &group[1],
+                user: &group[2],
+                hook: &group[3..],
+            });
+            rest = tail;
+        }
+        Ok(groups)
+    }
+
+    /// Check the accounts against `member` and return the mint's decimals
+    fn verify(&self, member: &BasketMember) -> Result<u8> {
+        require_keys_eq!(self.mint.key(), member.mint, AmmError::InvalidTokenPair);
+        require_keys_eq!(self.vault.key(), member.vault, AmmError::InvalidTokenPair);
+        Ok(load::<Mint>(self.mint)?.decimals)
+    }
+}
+
+fn load<T: AccountDeserialize>(info: &AccountInfo) -> Result<T> {
+    require_keys_eq!(*info.owner, token_2022::ID, AmmError::InvalidTokenPair);
+    T::try_deserialize(&mut &info.try_borrow_data()?[..])
+}
+
+/// Hook-aware transfer resolving only the given mint's extra accounts
+#[allow(clippy::too_many_arguments)]
+fn transfer<'info>(
+    token_program: &Program<'info, Token2022>,
+    from: &AccountInfo<'info>,
+    accounts: &MemberAccounts<'_, 'info>,
+    to: &AccountInfo<'info>,
+    authority: AccountInfo<'info>,
+    amount: u64,
+    decimals: u8,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+    invoke_transfer_checked(
+        &token_program.key(),
+        from.clone(),
+        accounts.mint.clone(),
+        to.clone(),
+        authority,
+        accounts.hook,
+        amount,
+        decimals,
+        signer_seeds,
+    )?;
+    Ok(())
+}
+
+pub(crate) fn create_basket<'info>(
+    ctx: Context<'_, '_, '_, 'info, CreateBasket<'info>>,
+    mints_hash: [u8; 32],
+    curve: BasketCurve,
+    weights: Vec<u64>,
+    initial_amounts: Vec<u64>,
+    hook_account_counts: Vec<u8>,
+) -> Result<()> {
+    let n = initial_amounts.len();
+    require!(
+        (MIN_BASKET_MEMBERS..=MAX_BASKET_MEMBERS).contains(&n)
+            && weights.len() == n
+            && hook_account_counts.len() == n
+            && initial_amounts.iter().all(|a| *a > 0),
+        AmmError::InvalidCurveParameters
+    );
+    curve.validate(&weights)?;
+
+    let groups = MemberAccounts::split(ctx.remaining_accounts, &hook_account_counts)?;
+    let amm_key = ctx.accounts.amm.key();
+    let mut members: Vec<BasketMember> = Vec::with_capacity(n);
+    let mut decimals = Vec::with_capacity(n);
+    for (i, accounts) in groups.iter().enumerate() {
+        let mint = load::<Mint>(accounts.mint)?;
+        let vault = load::<TokenAccount>(accounts.vault)?;
+        require!(
+            vault.mint == accounts.mint.key() && vault.owner == amm_key,
+            AmmError::InvalidTokenPair
+        );
+        // Sorted mints make members distinct and their order canonical
+        if let Some(previous) = members.last() {
+            require!(previous.mint < accounts.mint.key(), AmmError::InvalidTokenPair);
+        }
+        members.push(BasketMember {
+            mint: accounts.mint.key(),
+            vault: accounts.vault.key(),
+            balance: initial_amounts[i],
+            weight: weights[i],
+        });
+        decimals.push(mint.decimals);
+    }
+    let mints: Vec<&[u8]> = members.iter().map(|m| m.mint.as_ref()).collect();
+    require!(hashv(&mints).to_bytes() == mints_hash, AmmError::InvalidTokenPair);
+    if matches!(curve, BasketCurve::Stable { .. }) {
+        require!(
+            decimals.iter().all(|d| *d == decimals[0]),
+            AmmError::MismatchedDecimals
+        );
+    }
+
+    // Deposits and withdrawals are proportional, so the opening supply only
+    // sets the LP unit
+    let lp_supply = initial_amounts
+        .iter()
+        .try_fold(0u64, |sum, a| sum.checked_add(*a))
+        .ok_or(AmmError::InvalidLiquidityCalculation)?;
+
+    let pool = &mut ctx.accounts.pool;
+    pool.lp_mint = ctx.accounts.lp_mint.key();
+    pool.curve = curve;
+    pool.members = members;
+    pool.lp_supply = lp_supply;
+    pool.bump = ctx.bumps.pool;
+    pool.locked = true;
+    pool.exit(&crate::ID)?;
+
+    for (i, accounts) in groups.iter().enumerate() {
+        transfer(
+            &ctx.accounts.token_program,
+            accounts.user,
+            accounts,
+            accounts.vault,
+            ctx.accounts.creator.to_account_info(),
+            initial_amounts[i],
+            decimals[i],
+            &[],
+        )?;
+    }
+    mint_lp(&ctx.accounts.token_program, &ctx.accounts.lp_mint, &ctx.accounts.creator_lp_token, &ctx.accounts.amm, lp_supply)?;
+    ctx.accounts.pool.locked = false;
+
+    emit_cpi!(BasketCreated {
+        pool: ctx.accounts.pool.key(),
+        creator: ctx.accounts.creator.key(),
+        lp_mint: ctx.accounts.lp_mint.key(),
+        curve,
+        mints: ctx.accounts.pool.members.iter().map(|m| m.mint).collect(),
+        amounts: initial_amounts,
+        lp_supply,
+    });
+    Ok(())
+}
+
+pub(crate) fn basket_swap<'info>(
+    ctx: Context<'_, '_, '_, 'info, BasketSwap<'info>>,
+    in_index: u8,
+    out_index: u8,
+    amount_in: u64,
+    minimum_amount_out: u64,
+    hook_account_counts: [u8; 2],
+) -> Result<()> {
+    let (i, j) = (in_index as usize, out_index as usize);
+    let groups = MemberAccounts::split(ctx.remaining_accounts, &hook_account_counts)?;
+    let (accounts_in, accounts_out) = (&groups[0], &groups[1]);
+
+    let pool = &mut ctx.accounts.pool;
+    let fee = ctx.accounts.amm.fee_on(amount_in)?;
+    let amount_out = pool.swap_exact_in(i, j, amount_in - fee)?;
+    require!(
+        amount_out >= minimum_amount_out && amount_out > 0,
+        AmmError::InsufficientOutputAmount
+    );
+    let decimals_in = accounts_in.verify(&pool.members[i])?;
+    let decimals_out = accounts_out.verify(&pool.members[j])?;
+
+    // The fee stays in the pool
+    pool.members[i].balance = pool.members[i]
+        .balance
+        .checked_add(amount_in)
+        .ok_or(AmmError::InvalidSwapCalculation)?;
+    pool.members[j].balance -= amount_out;
+    pool.locked = true;
+    pool.exit(&crate::ID)?;
+
+    transfer(
+        &ctx.accounts.token_program,
+        accounts_in.user,
+        accounts_in,
+        accounts_in.vault,
+        ctx.accounts.user.to_account_info(),
+        amount_in,
+        decimals_in,
+        &[],
+    )?;
+    let amm_seeds = [b"amm".as_ref(), std::slice::from_ref(&ctx.accounts.amm.bump)];
+    transfer(
+        &ctx.accounts.token_program,
+        accounts_out.vault,
+        accounts_out,
+        accounts_out.user,
+        ctx.accounts.amm.to_account_info(),
+        amount_out,
+        decimals_out,
+        &[&amm_seeds],
+    )?;
+    ctx.accounts.pool.locked = false;
+
+    emit_cpi!(BasketSwapped {
+        pool: ctx.accounts.pool.key(),
+        user: ctx.accounts.user.key(),
+        mint_in: accounts_in.mint.key(),
+        mint_out: accounts_out.mint.key(),
+        amount_in,
+        amount_out,
+        fee,
+    });
+    Ok(())
+}
+
+pub(crate) fn add_basket_liquidity<'info>(
+    ctx: Context<'_, '_, '_, 'info, BasketLiquidity<'info>>,
+    lp_amount: u64,
+    maximum_amounts: Vec<u64>,
+    hook_account_counts: Vec<u8>,
+) -> Result<()> {
+    let pool = &mut ctx.accounts.pool;
+    let n = pool.members.len();
+    require!(
+        maximum_amounts.len() == n && hook_account_counts.len() == n && lp_amount > 0,
+        AmmError::InvalidLiquidityCalculation
+    );
+    let groups = MemberAccounts::split(ctx.remaining_accounts, &hook_account_counts)?;
+
+    // Round deposits up so existing holders never lose value
+    let lp_supply = pool.lp_supply;
+    let mut amounts = Vec::with_capacity(n);
+    let mut decimals = Vec::with_capacity(n);
+    for ((member, accounts), maximum) in pool.members.iter_mut().zip(&groups).zip(&maximum_amounts) {
+        decimals.push(accounts.verify(member)?);
+        let amount = u64::try_from((member.balance as u128 * lp_amount as u128).div_ceil(lp_supply as u128))
+            .map_err(|_| AmmError::InvalidLiquidityCalculation)?;
+        require!(amount <= *maximum, AmmError::ExcessiveInputAmount);
+        member.balance = member
+            .balance
+            .checked_add(amount)
+            .ok_or(AmmError::InvalidLiquidityCalculation)?;
+        amounts.push(amount);
+    }
+    pool.lp_supply = pool
+        .lp_supply
+        .checked_add(lp_amount)
+        .ok_or(AmmError::InvalidLiquidityCalculation)?;
+    pool.locked = true;
+    pool.exit(&crate::ID)?;
+
+    for ((accounts, amount), decimals) in groups.iter().zip(&amounts).zip(decimals) {
+        transfer(
+            &ctx.accounts.token_program,
+            accounts.user,
+            accounts,
+            accounts.vault,
+            ctx.accounts.user.to_account_info(),
+            *amount,
+            decimals,
+            &[],
+        )?;
+    }
+    mint_lp(&ctx.accounts.token_program, &ctx.accounts.lp_mint, &ctx.accounts.user_lp_token, &ctx.accounts.amm, lp_amount)?;
+    ctx.accounts.pool.locked = false;
+
+    emit_cpi!(BasketLiquidityChanged {
+        pool: ctx.accounts.pool.key(),
+        user: ctx.accounts.user.key(),
+        deposit: true,
+        amounts,
+        lp_amount,
+        lp_supply: ctx.accounts.pool.lp_supply,
+    });
+    Ok(())
+}
+
+pub(crate) fn remove_basket_liquidity<'info>(
+    ctx: Context<'_, '_, '_, 'info, BasketLiquidity<'info>>,
+    lp_amount: u64,
+    minimum_amounts: Vec<u64>,
+    hook_account_counts: Vec<u8>,
+) -> Result<()> {
+    let pool = &mut ctx.accounts.pool;
+    let n = pool.members.len();
+    require!(
+        minimum_amounts.len() == n
+            && hook_account_counts.len() == n
+            && lp_amount > 0
+            && lp_amount < pool.lp_supply,
+        AmmError::InvalidLiquidityCalculation
+    );
+    let groups = MemberAccounts::split(ctx.remaining_accounts, &hook_account_counts)?;
+
+    let lp_supply = pool.lp_supply;
+    let mut amounts = Vec::with_capacity(n);
+    let mut decimals = Vec::with_capacity(n);
+    for ((member, accounts), minimum) in pool.members.iter_mut().zip(&groups).zip(&minimum_amounts) {
+        decimals.push(accounts.verify(member)?);
+        let amount = mul_div(member.balance, lp_amount, lp_supply)?;
+        require!(amount >= *minimum, AmmError::InsufficientOutputAmount);
+        member.balance -= amount;
+        amounts.push(amount);
+    }
+    pool.lp_supply -= lp_amount;
+    pool.locked = true;
+    pool.exit(&crate::ID)?;
+
+    token_2022::burn(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            token_2022::Burn {
+                mint: ctx.accounts.lp_mint.to_account_info(),
+                from: ctx.accounts.user_lp_token.to_account_info(),
+                authority: ctx.accounts.user.to_account_info(),
+            },
+        ),
+        lp_amount,
+    )?;
+    let amm_seeds = [b"amm".as_ref(), std::slice::from_ref(&ctx.accounts.amm.bump)];
+    for ((accounts, amount), decimals) in groups.iter().zip(&amounts).zip(decimals) {
+        transfer(
+            &ctx.accounts.token_program,
+            accounts.vault,
+            accounts,
+            accounts.user,
+            ctx.accounts.amm.to_account_info(),
+            *amount,
+            decimals,
+            &[&amm_seeds],
+        )?;
+    }
+    ctx.accounts.pool.locked = false;
+
+    emit_cpi!(BasketLiquidityChanged {
+        pool: ctx.accounts.pool.key(),
+        user: ctx.accounts.user.key(),
+        deposit: false,
+        amounts,
+        lp_amount,
+        lp_supply: ctx.accounts.pool.lp_supply,
+    });
+    Ok(())
+}
+
+fn mint_lp<'info>(
+    token_program: &Program<'info, Token2022>,
+    lp_mint: &InterfaceAccount<'info, Mint>,
+    to: &InterfaceAccount<'info, TokenAccount>,
+    amm: &Account<'info, Amm>,
+    amount: u64,
+) -> Result<()> {
+    let amm_seeds = [b"amm".as_ref(), std::slice::from_ref(&amm.bump)];
+    token_2022::mint_to(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            token_2022::MintTo {
+                mint: lp_mint.to_account_info(),
+                to: to.to_account_info(),
+                authority: amm.to_account_info(),
+            },
+            &[&amm_seeds],
+        ),
+        amount,
+    )
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(mints_hash: [u8; 32])]
+pub struct CreateBasket<'info> {
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + BasketPool::INIT_SPACE,
+        seeds = [b"basket", mints_hash.as_ref()],
+        bump
+    )]
+    pub pool: Box<Account<'info, BasketPool>>,
+    #[account(seeds = [b"amm"], bump = amm.bump)]
+    pub amm: Account<'info, Amm>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    #[account(
+        init,
+        payer = creator,
+        seeds = [b"basket-lp-mint", pool.key().as_ref()],
+        bump,
+        mint::decimals = LP_DECIMALS,
+        mint::authority = amm
+    )]
+    pub lp_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        init,
+        payer = creator,
+        associated_token::mint = lp_mint,
+        associated_token::authority = creator,
+        associated_token::token_program = token_program
+    )]
+    pub creator_lp_token: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token2022>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct BasketSwap<'info> {
+    #[account(
+        mut,
+        constraint = !pool.locked @ AmmError::PoolLocked
+    )]
+    pub pool: Box<Account<'info, BasketPool>>,
+    #[account(seeds = [b"amm"], bump = amm.bump)]
+    pub amm: Account<'info, Amm>,
+    pub user: Signer<'info>,
+    pub token_program: Program<'info, Token2022>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct BasketLiquidity<'info> {
+    #[account(
+        mut,
+        has_one = lp_mint,
+        constraint = !pool.locked @ AmmError::PoolLocked
+    )]
+    pub pool: Box<Account<'info, BasketPool>>,
+    #[account(seeds = [b"amm"], bump = amm.bump)]
+    pub amm: Account<'info, Amm>,
+    pub user: Signer<'info>,
+
+    #[account(mut)]
+    pub lp_mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, token::mint = lp_mint)]
+    pub user_lp_token: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token2022>,
+}
+
+#[event]
+pub struct BasketCreated {
+    pub pool: Pubkey,
+    pub creator: Pubkey,
+    pub lp_mint: Pubkey,
+    pub curve: BasketCurve,
+    pub mints: Vec<Pubkey>,
+    pub amounts: Vec<u64>,
+    pub lp_supply: u64,
+}
+
+#[event]
+pub struct BasketSwapped {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub mint_in: Pubkey,
+    pub mint_out: Pubkey,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee: u64,
+}
+
+#[event]
+pub struct BasketLiquidityChanged {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub deposit: bool,         // False for withdrawals
+    pub amounts: Vec<u64>,     // Per member, in pool order
+    pub lp_amount: u64,
+    pub lp_supply: u64,
+}
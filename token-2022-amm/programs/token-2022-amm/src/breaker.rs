@@ -0,0 +1,123 @@
+//! Per-pool circuit breaker. Bounds how far a single swap may move the
+//! execution price from spot and how far the spot price may drift within a
+//! window of slots, so one large trade cannot drain a thin pool.
+
+use anchor_lang::prelude::*;
+
+use crate::fee::price_move_bps;
+use crate::AmmError;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct CircuitBreaker {
+    pub max_price_impact_bps: u16, // Zero leaves single swaps unbounded
+    pub max_window_move_bps: u16,  // Zero leaves the window unbounded
+    pub window_slots: u64,
+    pub window_start_slot: u64,
+    pub window_start_price: u128,  // Q64.64 spot price of A in B when the window opened
+}
+
+impl CircuitBreaker {
+    pub fn configure(
+        &mut self,
+        max_price_impact_bps: u16,
+        max_window_move_bps: u16,
+        window_slots: u64,
+    ) -> Result<()> {
+        require!(
+            max_window_move_bps == 0 || window_slots > 0,
+            AmmError::InvalidCurveParameters
+        );
+        self.max_price_impact_bps = max_price_impact_bps;
+        self.max_window_move_bps = max_window_move_bps;
+        self.window_slots = window_slots;
+        // Start a fresh window under the new bounds
+        self.window_start_price = 0;
+        Ok(())
+    }
+
+    /// Reject a swap whose execution price is too far from spot
+    pub fn check_impact(&self, price_impact_bps: u64) -> Result<()> {
+        require!(
+            self.max_price_impact_bps == 0 || price_impact_bps <= self.max_price_impact_bps as u64,
+            AmmError::PriceImpactTooHigh
+        );
+        Ok(())
+    }
+
+    /// Reject a swap that takes the spot price too far from where it stood
+    /// when the current window opened. A swap after the window has elapsed
+    /// opens a new one at its pre-trade price.
+    pub fn check_window(&mut self, slot: u64, price_before: u128, price_after: u128) -> Result<()> {
+        if self.max_window_move_bps == 0 {
+            return Ok(());
+        }
+        if self.window_start_price == 0
+            || slot >= self.window_start_slot.saturating_add(self.window_slots)
+        {
+            self.window_start_slot = slot;
+            self.window_start_price = price_before;
+        }
+        require!(
+            price_move_bps(self.window_start_price, price_after) <= self.max_window_move_bps as u64,
+            AmmError::PriceMoveTooHigh
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ONE: u128 = 1 << 64;
+
+    /// 2% per swap, 5% per 100-slot window
+    fn breaker() -> CircuitBreaker {
+        let mut breaker = CircuitBreaker::default();
+        breaker.configure(200, 500, 100).unwrap();
+        breaker
+    }
+
+    #[test]
+    fn impact_is_bounded_unless_disabled() {
+        let breaker = breaker();
+        assert!(breaker.check_impact(200).is_ok());
+        assert_eq!(breaker.check_impact(201).unwrap_err(), AmmError::PriceImpactTooHigh.into());
+        assert!(CircuitBreaker::default().check_impact(u64::MAX).is_ok());
+    }
+
+    #[test]
+    fn moves_add_up_within_a_window() {
+        let mut breaker = breaker();
+        breaker.check_window(10, 100 * ONE, 103 * ONE).unwrap();
+        assert_eq!((breaker.window_start_slot, breaker.window_start_price), (10, 100 * ONE));
+        // 3% then 2% more from the window's opening price is fine, 5.5% is not
+        breaker.check_window(50, 103 * ONE, 105 * ONE).unwrap();
+        let err = breaker.check_window(109, 105 * ONE, 105 * ONE + ONE / 2).unwrap_err();
+        assert_eq!(err, AmmError::PriceMoveTooHigh.into());
+        assert_eq!(breaker.window_start_price, 100 * ONE);
+    }
+
+    #[test]
+    fn window_resets_at_the_pre_trade_price() {
+        let mut breaker = breaker();
+        breaker.check_window(10, 100 * ONE, 105 * ONE).unwrap();
+        // One window later the 5.5% is measured from 105 instead
+        breaker.check_window(110, 105 * ONE, 105 * ONE + ONE / 2).unwrap();
+        assert_eq!((breaker.window_start_slot, breaker.window_start_price), (110, 105 * ONE));
+    }
+
+    #[test]
+    fn configure_opens_a_fresh_window() {
+        let mut breaker = breaker();
+        breaker.check_window(10, 100 * ONE, 104 * ONE).unwrap();
+        breaker.configure(200, 500, 100).unwrap();
+        breaker.check_window(20, 104 * ONE, 108 * ONE).unwrap();
+        assert_eq!(breaker.window_start_price, 104 * ONE);
+
+        let err = breaker.configure(0, 500, 0).unwrap_err();
+        assert_eq!(err, AmmError::InvalidCurveParameters.into());
+        let mut open = CircuitBreaker::default();
+        assert!(open.check_window(0, ONE, u128::MAX).is_ok());
+    }
+}
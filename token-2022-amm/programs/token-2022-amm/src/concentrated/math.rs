@@ -0,0 +1,197 @@
+//! Tick and sqrt-price math for concentrated liquidity. Prices are
+//! sqrt(token B per token A) in Q64.64; tick `t` sits at sqrt(1.0001^t).
+
+use anchor_lang::prelude::*;
+
+use crate::curve::fixed_point;
+use crate::curve::U256;
+use crate::AmmError;
+
+pub const MIN_TICK: i32 = -443_636;
+pub const MAX_TICK: i32 = 443_636;
+/// `sqrt_price_at_tick(MIN_TICK)` and `sqrt_price_at_tick(MAX_TICK)`
+pub const MIN_SQRT_PRICE_X64: u128 = 4_295_048_016;
+pub const MAX_SQRT_PRICE_X64: u128 = 79_226_673_521_066_979_257_578_248_091;
+
+/// round(2^64 / sqrt(1.0001)^(2^i)) for i in 0..20
+const TICK_FACTORS: [u128; 20] = [
+    18_445_821_805_675_392_312,
+    18_444_899_583_751_176_498,
+    18_443_055_278_223_354_163,
+    18_439_367_220_385_604_838,
+    18_431_993_317_065_449_818,
+    18_417_254_355_718_160_513,
+    18_387_811_781_193_591_352,
+    18_329_067_761_203_520_168,
+    18_212_142_134_806_087_855,
+    17_980_523_815_641_551_639,
+    17_526_086_738_831_147_014,
+    16_651_378_430_235_024_244,
+    15_030_750_278_693_429_945,
+    12_247_334_978_882_834_400,
+    8_131_365_268_884_726_201,
+    3_584_323_654_723_342_298,
+    696_457_651_847_595_234,
+    26_294_789_957_452_057,
+    37_481_735_321_082,
+    76_158_724,
+];
+
+/// log2(sqrt(1.0001)) in Q64.64
+const LOG2_SQRT_TICK_BASE: i128 = 1_330_584_781_654_115;
+
+pub fn sqrt_price_at_tick(tick: i32) -> Result<u128> {
+    require!(
+        (MIN_TICK..=MAX_TICK).contains(&tick),
+        AmmError::InvalidTickRange
+    );
+    let abs_tick = tick.unsigned_abs();
+    let mut ratio: u128 = 1 << 64;
+    for (i, factor) in TICK_FACTORS.iter().enumerate() {
+        if abs_tick & (1 << i) != 0 {
+            ratio = (ratio * factor) >> 64;
+        }
+    }
+    // The factors step downwards; positive ticks take the reciprocal
+    Ok(if tick > 0 { u128::MAX / ratio } else { ratio })
+}
+
+/// Greatest tick whose sqrt price does not exceed `sqrt_price_x64`
+pub fn tick_at_sqrt_price(sqrt_price_x64: u128) -> Result<i32> {
+    require!(
+        (MIN_SQRT_PRICE_X64..=MAX_SQRT_PRICE_X64).contains(&sqrt_price_x64),
+        AmmError::InvalidSqrtPrice
+    );
+    let log = fixed_point::log2(sqrt_price_x64)?;
+    let estimate = log.div_euclid(LOG2_SQRT_TICK_BASE).clamp(MIN_TICK as i128, MAX_TICK as i128) as i32;
+
+    // log2 rounds down, so the estimate is off by at most one either way
+    let mut tick = estimate;
+    if tick < MAX_TICK && sqrt_price_at_tick(tick + 1)? <= sqrt_price_x64 {
+        tick += 1;
+    } else if sqrt_price_at_tick(tick)? > sqrt_price_x64 {
+        tick -= 1;
+    }
+    Ok(tick)
+}
+
+/// Token A between two sqrt prices: L * (sb - sa) / (sa * sb)
+pub fn amount_a_delta(sqrt_price_a: u128, sqrt_price_b: u128, liquidity: u128, round_up: bool) -> Result<u64> {
+    let (lower, upper) = ordered(sqrt_price_a, sqrt_price_b);
+    require!(lower > 0, AmmError::InvalidSqrtPrice);
+    let numerator = (U256::from(liquidity) << 64)
+        .checked_mul(U256::from(upper - lower))
+        .ok_or(AmmError::InvalidLiquidityCalculation)?;
+    let denominator = U256::from(lower) * U256::from(upper);
+    to_u64(div(numerator, denominator, round_up))
+}
+
+/// Token B between two sqrt prices: L * (sb - sa)
+pub fn amount_b_delta(sqrt_price_a: u128, sqrt_price_b: u128, liquidity: u128, round_up: bool) -> Result<u64> {
+    let (lower, upper) = ordered(sqrt_price_a, sqrt_price_b);
+    let product = U256::from(liquidity) * U256::from(upper - lower);
+    to_u64(div(product, U256::one() << 64, round_up))
+}
+
+/// Sqrt price after adding `amount_in` of the input token, rounded so the
+/// pool never gives out more than the input pays for
+pub fn next_sqrt_price_from_input(sqrt_price_x64: u128, liquidity: u128, amount_in: u64, a_to_b: bool) -> Result<u128> {
+    require!(liquidity > 0, AmmError::InvalidSwapCalculation);
+    let next = if a_to_b {
+        // L * sp / (L + in * sp), rounded up
+        let liquidity_x64 = U256::from(liquidity) << 64;
+        let numerator = liquidity_x64
+            .checked_mul(U256::from(sqrt_price_x64))
+            .ok_or(AmmError::InvalidSwapCalculation)?;
+        let denominator = liquidity_x64 + U256::from(amount_in) * U256::from(sqrt_price_x64);
+        div(numerator, denominator, true)
+    } else {
+        // sp + in / L, rounded down
+        U256::from(sqrt_price_x64) + (U256::from(amount_in) << 64) / U256::from(liquidity)
+    };
+    u128::try_from(next).map_err(|_| AmmError::InvalidSqrtPrice.into())
+}
+
+fn ordered(a: u128, b: u128) -> (u128, u128) {
+    if a < b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+fn div(numerator: U256, denominator: U256, round_up: bool) -> U256 {
+    let quotient = numerator / denominator;
+    if round_up && !(numerator % denominator).is_zero() {
+        quotient + U256::one()
+    } else {
+        quotient
+    }
+}
+
+fn to_u64(value: U256) -> Result<u64> {
+    u64::try_from(value).map_err(|_| AmmError::InvalidLiquidityCalculation.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIQUIDITY: u128 = 1_000_000_000_000;
+
+    #[test]
+    fn price_bounds_match_the_tick_bounds() {
+        assert_eq!(sqrt_price_at_tick(MIN_TICK).unwrap(), MIN_SQRT_PRICE_X64);
+        assert_eq!(sqrt_price_at_tick(MAX_TICK).unwrap(), MAX_SQRT_PRICE_X64);
+        assert_eq!(sqrt_price_at_tick(0).unwrap(), 1 << 64);
+        assert_eq!(sqrt_price_at_tick(MAX_TICK + 1).unwrap_err(), AmmError::InvalidTickRange.into());
+        assert_eq!(tick_at_sqrt_price(MIN_SQRT_PRICE_X64 - 1).unwrap_err(), AmmError::InvalidSqrtPrice.into());
+    }
+
+    #[test]
+    fn tick_round_trips_through_its_sqrt_price() {
+        for tick in [MIN_TICK, MIN_TICK + 1, -100_000, -1, 0, 1, 100_000, MAX_TICK - 1, MAX_TICK] {
+            let sqrt_price = sqrt_price_at_tick(tick).unwrap();
+            assert_eq!(tick_at_sqrt_price(sqrt_price).unwrap(), tick);
+            if tick > MIN_TICK {
+                // Just below a tick's price is the tick before it
+                assert_eq!(tick_at_sqrt_price(sqrt_price - 1).unwrap(), tick - 1);
+            }
+        }
+    }
+
+    #[test]
+    fn amount_deltas_round_up_by_at_most_one() {
+        let (lower, upper) = (sqrt_price_at_tick(-1_234).unwrap(), sqrt_price_at_tick(5_678).unwrap());
+        for delta in [amount_a_delta, amount_b_delta] {
+            let up = delta(lower, upper, LIQUIDITY, true).unwrap();
+            let down = delta(upper, lower, LIQUIDITY, false).unwrap();
+            assert_eq!(up, down + 1);
+        }
+        // Exact results are not rounded at all
+        assert_eq!(amount_b_delta(1 << 64, 3 << 64, 5, true).unwrap(), 10);
+        assert_eq!(amount_a_delta(1 << 64, 2 << 64, 4, true).unwrap(), 2);
+    }
+
+    #[test]
+    fn next_sqrt_price_never_overpays_the_input() {
+        let sqrt_price = sqrt_price_at_tick(1_000).unwrap();
+        for amount_in in [1, 1_000, 1_000_000_007] {
+            // Token A in: the price falls no further than `amount_in` pays for
+            let next = next_sqrt_price_from_input(sqrt_price, LIQUIDITY, amount_in, true).unwrap();
+            assert!(amount_a_delta(next, sqrt_price, LIQUIDITY, true).unwrap() <= amount_in);
+            assert!(amount_a_delta(next - 1, sqrt_price, LIQUIDITY, false).unwrap() >= amount_in);
+
+            // Token B in: the price rises no further than `amount_in` pays for
+            let next = next_sqrt_price_from_input(sqrt_price, LIQUIDITY, amount_in, false).unwrap();
+            assert!(amount_b_delta(sqrt_price, next, LIQUIDITY, true).unwrap() <= amount_in);
+            assert!(amount_b_delta(sqrt_price, next + 1, LIQUIDITY, false).unwrap() >= amount_in);
+        }
+    }
+
+    #[test]
+    fn amount_a_delta_rejects_overflowing_liquidity() {
+        let err = amount_a_delta(MIN_SQRT_PRICE_X64, MAX_SQRT_PRICE_X64, u128::MAX, true).unwrap_err();
+        assert_eq!(err, AmmError::InvalidLiquidityCalculation.into());
+    }
+}
@@ -0,0 +1,996 @@
+//! Concentrated liquidity. Each pool keeps its sqrt price in Q64.64 and the
+//! liquidity active at the current tick. Positions add liquidity between two
+//! ticks, and ticks live in `TickArray` accounts. Fees accrue per unit of
+//! liquidity through global and per-tick "outside" growth counters.
+
+pub mod math;
+
+use anchor_lang::prelude::*;
+use anchor_spl::token_2022::Token2022;
+use anchor_spl::token_interface::{Mint, TokenAccount};
+
+use crate::curve::U256;
+use crate::{transfer_with_hooks, Amm, AmmError};
+use math::{MAX_SQRT_PRICE_X64, MAX_TICK, MIN_SQRT_PRICE_X64, MIN_TICK};
+
+pub const TICK_ARRAY_SIZE: usize = 32;
+
+#[account]
+#[derive(InitSpace)]
+pub struct ClPool {
+    pub token_a_mint: Pubkey,  // Always the smaller mint key
+    pub token_b_mint: Pubkey,
+    pub token_a_vault: Pubkey,
+    pub token_b_vault: Pubkey,
+    pub tick_spacing: u16,
+    pub sqrt_price_x64: u128,  // sqrt(B per A) in Q64.64
+    pub tick_current: i32,     // Greatest tick at or below the current price
+    pub liquidity: u128,       // Active at `tick_current`
+    pub fee_growth_global_a_x64: u128, // Fees per unit of liquidity, Q64.64, wrapping
+    pub fee_growth_global_b_x64: u128,
+    pub locked: bool,
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct Tick {
+    pub liquidity_net: i128,   // Added to pool liquidity when crossed upwards
+    pub liquidity_gross: u128, // Total position liquidity referencing this tick
+    pub fee_growth_outside_a_x64: u128, // Growth on the side away from the current tick
+    pub fee_growth_outside_b_x64: u128,
+}
+
+impl Tick {
+    pub fn initialized(&self) -> bool {
+        self.liquidity_gross > 0
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct TickArray {
+    pub pool: Pubkey,
+    pub start_tick_index: i32,
+    pub ticks: [Tick; TICK_ARRAY_SIZE],
+}
+
+impl TickArray {
+    /// Number of tick indices one array covers
+    pub fn span(tick_spacing: u16) -> i32 {
+        TICK_ARRAY_SIZE as i32 * tick_spacing as i32
+    }
+
+    pub fn start_index_for(tick: i32, tick_spacing: u16) -> i32 {
+        let span = Self::span(tick_spacing);
+        tick.div_euclid(span) * span
+    }
+
+    pub fn contains(&self, tick: i32, tick_spacing: u16) -> bool {
+        tick >= self.start_tick_index && tick < self.start_tick_index + Self::span(tick_spacing)
+    }
+
+    fn offset(&self, tick: i32, tick_spacing: u16) -> Result<usize> {
+        require!(
+            self.contains(tick, tick_spacing) && tick % tick_spacing as i32 == 0,
+            AmmError::InvalidTickArray
+        );
+        Ok(((tick - self.start_tick_index) / tick_spacing as i32) as usize)
+    }
+
+    pub fn tick(&self, tick: i32, tick_spacing: u16) -> Result<&Tick> {
+        Ok(&self.ticks[self.offset(tick, tick_spacing)?])
+    }
+
+    pub fn tick_mut(&mut self, tick: i32, tick_spacing: u16) -> Result<&mut Tick> {
+        let offset = self.offset(tick, tick_spacing)?;
+        Ok(&mut self.ticks[offset])
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Position {
+    pub pool: Pubkey,
+    pub owner: Pubkey,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub liquidity: u128,
+    pub fee_growth_inside_a_last_x64: u128,
+    pub fee_growth_inside_b_last_x64: u128,
+    pub tokens_owed_a: u64,    // Collected fees not yet paid out
+    pub tokens_owed_b: u64,
+    pub bump: u8,
+}
+
+impl Position {
+    /// Credit fees earned since the last update, then apply `liquidity_delta`
+    fn update(&mut self, inside_a: u128, inside_b: u128, liquidity_delta: i128) -> Result<()> {
+        let earned = |inside: u128, last: u128| -> Result<u64> {
+            let growth = U256::from(inside.wrapping_sub(last)) * U256::from(self.liquidity);
+            u64::try_from(growth >> 64).map_err(|_| AmmError::InvalidLiquidityCalculation.into())
+        };
+        let owed_a = earned(inside_a, self.fee_growth_inside_a_last_x64)?;
+        let owed_b = earned(inside_b, self.fee_growth_inside_b_last_x64)?;
+        self.tokens_owed_a = self.tokens_owed_a.saturating_add(owed_a);
+        self.tokens_owed_b = self.tokens_owed_b.saturating_add(owed_b);
+        self.fee_growth_inside_a_last_x64 = inside_a;
+        self.fee_growth_inside_b_last_x64 = inside_b;
+        self.liquidity = add_delta(self.liquidity, liquidity_delta)?;
+        Ok(())
+    }
+}
+
+/// Fee growth per unit of liquidity between two ticks
+fn fee_growth_inside(pool: &ClPool, lower: (i32, &Tick), upper: (i32, &Tick)) -> (u128, u128) {
+    let side = |global: u128, outside: u128, below_current: bool| {
+        if below_current {
+            outside
+        } else {
+            global.wrapping_sub(outside)
+        }
+    };
+    let (global_a, global_b) = (pool.fee_growth_global_a_x64, pool.fee_growth_global_b_x64);
+    let lower_below = pool.tick_current >= lower.0;
+    let upper_above = pool.tick_current < upper.0;
+    let below_a = side(global_a, lower.1.fee_growth_outside_a_x64, lower_below);
+    let below_b = side(global_b, lower.1.fee_growth_outside_b_x64, lower_below);
+    let above_a = side(global_a, upper.1.fee_growth_outside_a_x64, upper_above);
+    let above_b = side(global_b, upper.1.fee_growth_outside_b_x64, upper_above);
+    (
+        global_a.wrapping_sub(below_a).wrapping_sub(above_a),
+        global_b.wrapping_sub(below_b).wrapping_sub(above_b),
+    )
+}
+
+/// Add position liquidity to a boundary tick
+fn update_tick(tick: &mut Tick, tick_index: i32, pool: &ClPool, liquidity_delta: i128, upper: bool) -> Result<()> {
+    let was_initialized = tick.initialized();
+    tick.liquidity_gross = add_delta(tick.liquidity_gross, liquidity_delta)?;
+    if !was_initialized && tick.initialized() && tick_index <= pool.tick_current {
+        // By convention all growth so far happened below the tick
+        tick.fee_growth_outside_a_x64 = pool.fee_growth_global_a_x64;
+        tick.fee_growth_outside_b_x64 = pool.fee_growth_global_b_x64;
+    }
+    let net = if upper { liquidity_delta.checked_neg() } else { Some(liquidity_delta) };
+    tick.liquidity_net = net
+        .and_then(|n| tick.liquidity_net.checked_add(n))
+        .ok_or(AmmError::InvalidLiquidityCalculation)?;
+    Ok(())
+}
+
+fn add_delta(liquidity: u128, delta: i128) -> Result<u128> {
+    if delta >= 0 {
+        liquidity.checked_add(delta as u128)
+    } else {
+        liquidity.checked_sub(delta.unsigned_abs())
+    }
+    .ok_or(AmmError::InvalidLiquidityCalculation.into())
+}
+
+pub(crate) fn create_cl_pool(
+    ctx: Context<CreateClPool>,
+    tick_spacing: u16,
+    initial_sqrt_price_x64: u128,
+) -> Result<()> {
+    require!(tick_spacing > 0, AmmError::InvalidTickRange);
+    let pool = &mut ctx.accounts.pool;
+    pool.token_a_mint = ctx.accounts.token_a_mint.key();
+    pool.token_b_mint = ctx.accounts.token_b_mint.key();
+    pool.token_a_vault = ctx.accounts.token_a_vault.key();
+    pool.token_b_vault = ctx.accounts.token_b_vault.key();
+    pool.tick_spacing = tick_spacing;
+    pool.sqrt_price_x64 = initial_sqrt_price_x64;
+    pool.tick_current = math::tick_at_sqrt_price(initial_sqrt_price_x64)?;
+    pool.bump = ctx.bumps.pool;
+
+    emit_cpi!(ClPoolCreated {
+        pool: pool.key(),
+        token_a_mint: pool.token_a_mint,
+        token_b_mint: pool.token_b_mint,
+        tick_spacing,
+        sqrt_price_x64: initial_sqrt_price_x64,
+        tick_current: pool.tick_current,
+    });
+    Ok(())
+}
+
+pub(crate) fn initialize_tick_array(ctx: Context<InitializeTickArray>, start_tick_index: i32) -> Result<()> {
+    let tick_spacing = ctx.accounts.pool.tick_spacing;
+    // Aligned to a whole array and overlapping the valid tick range
+    require!(
+        start_tick_index == TickArray::start_index_for(start_tick_index, tick_spacing)
+            && start_tick_index + TickArray::span(tick_spacing) > MIN_TICK
+            && start_tick_index <= MAX_TICK,
+        AmmError::InvalidTickArray
+    );
+    let tick_array = &mut ctx.accounts.tick_array;
+    tick_array.pool = ctx.accounts.pool.key();
+    tick_array.start_tick_index = start_tick_index;
+    Ok(())
+}
+
+pub(crate) fn open_position(ctx: Context<OpenPosition>, tick_lower: i32, tick_upper: i32) -> Result<()> {
+    let spacing = ctx.accounts.pool.tick_spacing as i32;
+    require!(
+        tick_lower < tick_upper
+            && tick_lower >= MIN_TICK
+            && tick_upper <= MAX_TICK
+            && tick_lower % spacing == 0
+            && tick_upper % spacing == 0,
+        AmmError::InvalidTickRange
+    );
+    let position = &mut ctx.accounts.position;
+    position.pool = ctx.accounts.pool.key();
+    position.owner = ctx.accounts.owner.key();
+    position.tick_lower = tick_lower;
+    position.tick_upper = tick_upper;
+    position.bump = ctx.bumps.position;
+    Ok(())
+}
+
+pub(crate) fn increase_liquidity<'info>(
+    ctx: Context<'_, '_, '_, 'info, ModifyClLiquidity<'info>>,
+    liquidity: u128,
+    maximum_token_a: u64,
+    maximum_token_b: u64,
+) -> Result<()> {
+    let delta = i128::try_from(liquidity).map_err(|_| AmmError::InvalidLiquidityCalculation)?;
+    require!(delta > 0, AmmError::InvalidLiquidityCalculation);
+    let (token_a_amount, token_b_amount) = modify_liquidity(ctx.accounts, delta)?;
+    require!(
+        token_a_amount <= maximum_token_a && token_b_amount <= maximum_token_b,
+        AmmError::ExcessiveInputAmount
+    );
+
+    let all_accounts = liquidity_accounts(&ctx);
+    lock(&mut ctx.accounts.pool)?;
+    for (from, mint, to, amount) in [
+        (&ctx.accounts.owner_token_a, &ctx.accounts.token_a_mint, &ctx.accounts.token_a_vault, token_a_amount),
+        (&ctx.accounts.owner_token_b, &ctx.accounts.token_b_mint, &ctx.accounts.token_b_vault, token_b_amount),
+    ] {
+        transfer_with_hooks(
+            &ctx.accounts.token_program,
+            from,
+            mint,
+            to,
+            ctx.accounts.owner.to_account_info(),
+            &all_accounts,
+            amount,
+            &[],
+        )?;
+    }
+    ctx.accounts.pool.locked = false;
+
+    emit_cpi!(ClLiquidityChanged {
+        pool: ctx.accounts.pool.key(),
+        position: ctx.accounts.position.key(),
+        owner: ctx.accounts.owner.key(),
+        liquidity_delta: delta,
+        token_a_amount,
+        token_b_amount,
+    });
+    Ok(())
+}
+
+pub(crate) fn decrease_liquidity<'info>(
+    mut ctx: Context<'_, '_, '_, 'info, ModifyClLiquidity<'info>>,
+    liquidity: u128,
+    minimum_token_a: u64,
+    minimum_token_b: u64,
+) -> Result<()> {
+    let delta = i128::try_from(liquidity)
+        .map_err(|_| AmmError::InvalidLiquidityCalculation)?
+        .checked_neg()
+        .filter(|d| *d < 0)
+        .ok_or(AmmError::InvalidLiquidityCalculation)?;
+    let (token_a_amount, token_b_amount) = modify_liquidity(ctx.accounts, delta)?;
+    require!(
+        token_a_amount >= minimum_token_a && token_b_amount >= minimum_token_b,
+        AmmError::InsufficientOutputAmount
+    );
+
+    pay_out(&mut ctx, token_a_amount, token_b_amount)?;
+    emit_cpi!(ClLiquidityChanged {
+        pool: ctx.accounts.pool.key(),
+        position: ctx.accounts.position.key(),
+        owner: ctx.accounts.owner.key(),
+        liquidity_delta: delta,
+        token_a_amount,
+        token_b_amount,
+    });
+    Ok(())
+}
+
+pub(crate) fn collect_fees<'info>(mut ctx: Context<'_, '_, '_, 'info, ModifyClLiquidity<'info>>) -> Result<()> {
+    modify_liquidity(ctx.accounts, 0)?;
+    let position = &mut ctx.accounts.position;
+    let (token_a_amount, token_b_amount) = (position.tokens_owed_a, position.tokens_owed_b);
+    position.tokens_owed_a = 0;
+    position.tokens_owed_b = 0;
+
+    pay_out(&mut ctx, token_a_amount, token_b_amount)?;
+    emit_cpi!(ClFeesCollected {
+        pool: ctx.accounts.pool.key(),
+        position: ctx.accounts.position.key(),
+        owner: ctx.accounts.owner.key(),
+        token_a_amount,
+        token_b_amount,
+    });
+    Ok(())
+}
+
+pub(crate) fn cl_swap<'info>(
+    ctx: Context<'_, '_, '_, 'info, ClSwap<'info>>,
+    amount_in: u64,
+    minimum_amount_out: u64,
+    a_to_b: bool,
+    sqrt_price_limit_x64: u128,
+) -> Result<()> {
+    let accounts = &mut *ctx.accounts;
+    let spacing = accounts.pool.tick_spacing;
+    let span = TickArray::span(spacing);
+    let step = if a_to_b { -span } else { span };
+    let start = TickArray::start_index_for(accounts.pool.tick_current, spacing);
+    // The third array only counts after the second
+    require!(
+        accounts.tick_array_1.is_some() || accounts.tick_array_2.is_none(),
+        AmmError::InvalidTickArray
+    );
+
+    let result = {
+        let mut arrays: Vec<&mut TickArray> = vec![&mut accounts.tick_array_0];
+        arrays.extend(
+            [accounts.tick_array_1.as_mut(), accounts.tick_array_2.as_mut()]
+                .into_iter()
+                .flatten()
+                .map(|array| &mut ***array),
+        );
+        for (i, array) in arrays.iter().enumerate() {
+            require!(
+                array.start_tick_index == start + i as i32 * step,
+                AmmError::InvalidTickArray
+            );
+        }
+        compute_swap(&mut accounts.pool, &mut arrays, &accounts.amm, amount_in, a_to_b, sqrt_price_limit_x64)?
+    };
+    require!(
+        result.amount_out >= minimum_amount_out,
+        AmmError::InsufficientOutputAmount
+    );
+
+    let mut all_accounts = vec![
+        accounts.pool.to_account_info(),
+        accounts.amm.to_account_info(),
+        accounts.user.to_account_info(),
+        accounts.user_token_a.to_account_info(),
+        accounts.user_token_b.to_account_info(),
+        accounts.token_a_mint.to_account_info(),
+        accounts.token_b_mint.to_account_info(),
+        accounts.token_a_vault.to_account_info(),
+        accounts.token_b_vault.to_account_info(),
+        accounts.token_program.to_account_info(),
+    ];
+    all_accounts.extend(ctx.remaining_accounts.iter().cloned());
+
+    let (user_in, mint_in, vault_in, vault_out, mint_out, user_out) = if a_to_b {
+        (&accounts.user_token_a, &accounts.token_a_mint, &accounts.token_a_vault, &accounts.token_b_vault, &accounts.token_b_mint, &accounts.user_token_b)
+    } else {
+        (&accounts.user_token_b, &accounts.token_b_mint, &accounts.token_b_vault, &accounts.token_a_vault, &accounts.token_a_mint, &accounts.user_token_a)
+    };
+
+    lock(&mut accounts.pool)?;
+    transfer_with_hooks(
+        &accounts.token_program,
+        user_in,
+        mint_in,
+        vault_in,
+        accounts.user.to_account_info(),
+        &all_accounts,
+        result.amount_in,
+        &[],
+    )?;
+    let amm_seeds = [b"amm".as_ref(), std::slice::from_ref(&accounts.amm.bump)];
+    transfer_with_hooks(
+        &accounts.token_program,
+        vault_out,
+        mint_out,
+        user_out,
+        accounts.amm.to_account_info(),
+        &all_accounts,
+        result.amount_out,
+        &[&amm_seeds],
+    )?;
+    accounts.pool.locked = false;
+
+    let event = ClSwapped {
+        pool: accounts.pool.key(),
+        user: accounts.user.key(),
+        a_to_b,
+        amount_in: result.amount_in,
+        amount_out: result.amount_out,
+        fee: result.fee,
+        sqrt_price_x64: accounts.pool.sqrt_price_x64,
+        tick_current: accounts.pool.tick_current,
+        liquidity: accounts.pool.liquidity,
+    };
+    emit_cpi!(event);
+    Ok(())
+}
+
+struct SwapResult {
+    amount_in: u64,
+    amount_out: u64,
+    fee: u64,
+}
+
+/// Walk the price from tick to tick until the input is used up, the limit
+/// is hit or the tick arrays supplied run out. Only the input consumed is
+/// charged, so a swap can fill partially.
+fn compute_swap(
+    pool: &mut ClPool,
+    arrays: &mut [&mut TickArray],
+    amm: &Amm,
+    amount_in: u64,
+    a_to_b: bool,
+    sqrt_price_limit_x64: u128,
+) -> Result<SwapResult> {
+    let limit = match sqrt_price_limit_x64 {
+        0 if a_to_b => MIN_SQRT_PRICE_X64,
+        0 => MAX_SQRT_PRICE_X64,
+        limit => limit,
+    };
+    require!(
+        if a_to_b {
+            limit < pool.sqrt_price_x64 && limit >= MIN_SQRT_PRICE_X64
+        } else {
+            limit > pool.sqrt_price_x64 && limit <= MAX_SQRT_PRICE_X64
+        },
+        AmmError::InvalidSqrtPrice
+    );
+
+    let spacing = pool.tick_spacing;
+    let mut remaining = amount_in;
+    let mut amount_out: u64 = 0;
+    let mut total_fee: u64 = 0;
+
+    while remaining > 0 && pool.sqrt_price_x64 != limit {
+        let Some((next_tick, initialized)) = next_initialized_tick(arrays, pool.tick_current, spacing, a_to_b) else {
+            break;
+        };
+        let next_sqrt_price = math::sqrt_price_at_tick(next_tick)?;
+        let target = if a_to_b {
+            next_sqrt_price.max(limit)
+        } else {
+            next_sqrt_price.min(limit)
+        };
+
+        let (new_sqrt_price, step_in, step_fee) = if pool.liquidity == 0 {
+            (target, 0, 0)
+        } else {
+            let available = remaining - amm.fee_on(remaining)?;
+            let to_target = if a_to_b {
+                math::amount_a_delta(target, pool.sqrt_price_x64, pool.liquidity, true)?
+            } else {
+                math::amount_b_delta(pool.sqrt_price_x64, target, pool.liquidity, true)?
+            };
+            if available >= to_target {
+                (target, to_target, amm.gross_up(to_target)? - to_target)
+            } else {
+                let next = math::next_sqrt_price_from_input(pool.sqrt_price_x64, pool.liquidity, available, a_to_b)?;
+                (next, available, remaining - available)
+            }
+        };
+
+        if pool.liquidity > 0 {
+            let step_out = if a_to_b {
+                math::amount_b_delta(new_sqrt_price, pool.sqrt_price_x64, pool.liquidity, false)?
+            } else {
+                math::amount_a_delta(pool.sqrt_price_x64, new_sqrt_price, pool.liquidity, false)?
+            };
+            amount_out = amount_out
+                .checked_add(step_out)
+                .ok_or(AmmError::InvalidSwapCalculation)?;
+            let growth = ((step_fee as u128) << 64) / pool.liquidity;
+            if a_to_b {
+                pool.fee_growth_global_a_x64 = pool.fee_growth_global_a_x64.wrapping_add(growth);
+            } else {
+                pool.fee_growth_global_b_x64 = pool.fee_growth_global_b_x64.wrapping_add(growth);
+            }
+        }
+        remaining -= step_in + step_fee;
+        total_fee += step_fee;
+
+        let previous_sqrt_price = pool.sqrt_price_x64;
+        pool.sqrt_price_x64 = new_sqrt_price;
+        if new_sqrt_price == next_sqrt_price {
+            if initialized {
+                cross_tick(pool, arrays, next_tick, a_to_b)?;
+            }
+            pool.tick_current = if a_to_b { next_tick - 1 } else { next_tick };
+        } else if new_sqrt_price != previous_sqrt_price {
+            pool.tick_current = math::tick_at_sqrt_price(new_sqrt_price)?;
+        }
+    }
+
+    Ok(SwapResult {
+        amount_in: amount_in - remaining,
+        amount_out,
+        fee: total_fee,
+    })
+}
+
+/// Next initialized tick in the swap direction within the loaded arrays, or
+/// the edge of the last one supplied if none is. `None` once past every
+/// array.
+fn next_initialized_tick(
+    arrays: &[&mut TickArray],
+    tick_current: i32,
+    spacing: u16,
+    a_to_b: bool,
+) -> Option<(i32, bool)> {
+    let spacing_i32 = spacing as i32;
+    let last = arrays.last()?;
+    let (mut tick, edge) = if a_to_b {
+        (tick_current.div_euclid(spacing_i32) * spacing_i32, last.start_tick_index)
+    } else {
+        (
+            (tick_current.div_euclid(spacing_i32) + 1) * spacing_i32,
+            last.start_tick_index + TickArray::span(spacing),
+        )
+    };
+    let in_range = |tick: i32| if a_to_b { tick >= edge } else { tick < edge };
+    if !in_range(tick) && tick != edge {
+        return None;
+    }
+
+    while in_range(tick) {
+        let array = arrays.iter().find(|a| a.contains(tick, spacing))?;
+        if array.tick(tick, spacing).ok()?.initialized() {
+            return Some((tick, true));
+        }
+        tick += if a_to_b { -spacing_i32 } else { spacing_i32 };
+    }
+    Some((edge.clamp(MIN_TICK, MAX_TICK), false))
+}
+
+/// Flip a tick's outside growth and apply its net liquidity as the price
+/// moves across it
+fn cross_tick(pool: &mut ClPool, arrays: &mut [&mut TickArray], tick_index: i32, a_to_b: bool) -> Result<()> {
+    let spacing = pool.tick_spacing;
+    let array = arrays
+        .iter_mut()
+        .find(|a| a.contains(tick_index, spacing))
+        .ok_or(AmmError::InvalidTickArray)?;
+    let tick = array.tick_mut(tick_index, spacing)?;
+    tick.fee_growth_outside_a_x64 = pool.fee_growth_global_a_x64.wrapping_sub(tick.fee_growth_outside_a_x64);
+    tick.fee_growth_outside_b_x64 = pool.fee_growth_global_b_x64.wrapping_sub(tick.fee_growth_outside_b_x64);
+    let net = if a_to_b { -tick.liquidity_net } else { tick.liquidity_net };
+    pool.liquidity = add_delta(pool.liquidity, net)?;
+    Ok(())
+}
+
+/// Apply `delta` to a position and its boundary ticks and return the token
+/// amounts it moves, rounded in the pool's favour
+fn modify_liquidity(accounts: &mut ModifyClLiquidity, delta: i128) -> Result<(u64, u64)> {
+    let pool = &mut accounts.pool;
+    let spacing = pool.tick_spacing;
+    let (tick_lower, tick_upper) = (accounts.position.tick_lower, accounts.position.tick_upper);
+    let same_array = accounts.tick_array_lower.key() == accounts.tick_array_upper.key();
+
+    let mut lower = *accounts.tick_array_lower.tick(tick_lower, spacing)?;
+    let mut upper = if same_array {
+        *accounts.tick_array_lower.tick(tick_upper, spacing)?
+    } else {
+        *accounts.tick_array_upper.tick(tick_upper, spacing)?
+    };
+    if delta != 0 {
+        update_tick(&mut lower, tick_lower, pool, delta, false)?;
+        update_tick(&mut upper, tick_upper, pool, delta, true)?;
+    }
+    let (inside_a, inside_b) = fee_growth_inside(pool, (tick_lower, &lower), (tick_upper, &upper));
+    accounts.position.update(inside_a, inside_b, delta)?;
+
+    // Ticks no position references any more go back to their blank state
+    for tick in [&mut lower, &mut upper] {
+        if !tick.initialized() {
+            *tick = Tick::default();
+        }
+    }
+    *accounts.tick_array_lower.tick_mut(tick_lower, spacing)? = lower;
+    if same_array {
+        // Both fields hold the same account and are written back in turn
+        *accounts.tick_array_lower.tick_mut(tick_upper, spacing)? = upper;
+        accounts.tick_array_upper.ticks = accounts.tick_array_lower.ticks;
+    } else {
+        *accounts.tick_array_upper.tick_mut(tick_upper, spacing)? = upper;
+    }
+
+    let round_up = delta > 0;
+    let liquidity = delta.unsigned_abs();
+    let sqrt_lower = math::sqrt_price_at_tick(tick_lower)?;
+    let sqrt_upper = math::sqrt_price_at_tick(tick_upper)?;
+    let amounts = if pool.tick_current < tick_lower {
+        (math::amount_a_delta(sqrt_lower, sqrt_upper, liquidity, round_up)?, 0)
+    } else if pool.tick_current >= tick_upper {
+        (0, math::amount_b_delta(sqrt_lower, sqrt_upper, liquidity, round_up)?)
+    } else {
+        pool.liquidity = add_delta(pool.liquidity, delta)?;
+        (
+            math::amount_a_delta(pool.sqrt_price_x64, sqrt_upper, liquidity, round_up)?,
+            math::amount_b_delta(sqrt_lower, pool.sqrt_price_x64, liquidity, round_up)?,
+        )
+    };
+    Ok(amounts)
+}
+
+fn lock(pool: &mut Account<ClPool>) -> Result<()> {
+    pool.locked = true;
+    pool.exit(&crate::ID)
+}
+
+fn liquidity_accounts<'info>(ctx: &Context<'_, '_, '_, 'info, ModifyClLiquidity<'info>>) -> Vec<AccountInfo<'info>> {
+    let mut all_accounts = vec![
+        ctx.accounts.pool.to_account_info(),
+        ctx.accounts.amm.to_account_info(),
+        ctx.accounts.owner.to_account_info(),
+        ctx.accounts.owner_token_a.to_account_info(),
+        ctx.accounts.owner_token_b.to_account_info(),
+        ctx.accounts.token_a_mint.to_account_info(),
+        ctx.accounts.token_b_mint.to_account_info(),
+        ctx.accounts.token_a_vault.to_account_info(),
+        ctx.accounts.token_b_vault.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+    ];
+    all_accounts.extend(ctx.remaining_accounts.iter().cloned());
+    all_accounts
+}
+
+/// Send tokens from the pool vaults to the position owner
+fn pay_out<'info>(
+    ctx: &mut Context<'_, '_, '_, 'info, ModifyClLiquidity<'info>>,
+    token_a_amount: u64,
+    token_b_amount: u64,
+) -> Result<()> {
+    let all_accounts = liquidity_accounts(ctx);
+    let amm_seeds = [b"amm".as_ref(), std::slice::from_ref(&ctx.accounts.amm.bump)];
+    lock(&mut ctx.accounts.pool)?;
+    for (from, mint, to, amount) in [
+        (&ctx.accounts.token_a_vault, &ctx.accounts.token_a_mint, &ctx.accounts.owner_token_a, token_a_amount),
+        (&ctx.accounts.token_b_vault, &ctx.accounts.token_b_mint, &ctx.accounts.owner_token_b, token_b_amount),
+    ] {
+        transfer_with_hooks(
+            &ctx.accounts.token_program,
+            from,
+            mint,
+            to,
+            ctx.accounts.amm.to_account_info(),
+            &all_accounts,
+            amount,
+            &[&amm_seeds],
+        )?;
+    }
+    ctx.accounts.pool.locked = false;
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(tick_spacing: u16)]
+pub struct CreateClPool<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + ClPool::INIT_SPACE,
+        seeds = [
+            b"cl-pool",
+            token_a_mint.key().as_ref(),
+            token_b_mint.key().as_ref(),
+            &tick_spacing.to_le_bytes()
+        ],
+        bump
+    )]
+    pub pool: Box<Account<'info, ClPool>>,
+    #[account(seeds = [b"amm"], bump = amm.bump)]
+    pub amm: Account<'info, Amm>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(constraint = token_a_mint.key() < token_b_mint.key() @ AmmError::InvalidTokenPair)]
+    pub token_a_mint: InterfaceAccount<'info, Mint>,
+    pub token_b_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        token::mint = token_a_mint,
+        token::authority = amm
+    )]
+    pub token_a_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        token::mint = token_b_mint,
+        token::authority = amm
+    )]
+    pub token_b_vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(start_tick_index: i32)]
+pub struct InitializeTickArray<'info> {
+    pub pool: Box<Account<'info, ClPool>>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + TickArray::INIT_SPACE,
+        seeds = [b"tick-array", pool.key().as_ref(), &start_tick_index.to_le_bytes()],
+        bump
+    )]
+    pub tick_array: Box<Account<'info, TickArray>>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(tick_lower: i32, tick_upper: i32)]
+pub struct OpenPosition<'info> {
+    pub pool: Box<Account<'info, ClPool>>,
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + Position::INIT_SPACE,
+        seeds = [
+            b"position",
+            pool.key().as_ref(),
+            owner.key().as_ref(),
+            &tick_lower.to_le_bytes(),
+            &tick_upper.to_le_bytes()
+        ],
+        bump
+    )]
+    pub position: Box<Account<'info, Position>>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ModifyClLiquidity<'info> {
+    #[account(
+        mut,
+        constraint = !pool.locked @ AmmError::PoolLocked
+    )]
+    pub pool: Box<Account<'info, ClPool>>,
+    #[account(seeds = [b"amm"], bump = amm.bump)]
+    pub amm: Box<Account<'info, Amm>>,
+    #[account(
+        mut,
+        has_one = pool,
+        has_one = owner
+    )]
+    pub position: Box<Account<'info, Position>>,
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = tick_array_lower.pool == pool.key() @ AmmError::InvalidTickArray
+    )]
+    pub tick_array_lower: Box<Account<'info, TickArray>>,
+    #[account(
+        mut,
+        constraint = tick_array_upper.pool == pool.key() @ AmmError::InvalidTickArray
+    )]
+    pub tick_array_upper: Box<Account<'info, TickArray>>,
+
+    #[account(mut)]
+    pub owner_token_a: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut)]
+    pub owner_token_b: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(address = pool.token_a_mint @ AmmError::InvalidTokenPair)]
+    pub token_a_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(address = pool.token_b_mint @ AmmError::InvalidTokenPair)]
+    pub token_b_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(mut, address = pool.token_a_vault @ AmmError::InvalidTokenPair)]
+    pub token_a_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut, address = pool.token_b_vault @ AmmError::InvalidTokenPair)]
+    pub token_b_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token2022>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ClSwap<'info> {
+    #[account(
+        mut,
+        constraint = !pool.locked @ AmmError::PoolLocked
+    )]
+    pub pool: Box<Account<'info, ClPool>>,
+    #[account(seeds = [b"amm"], bump = amm.bump)]
+    pub amm: Box<Account<'info, Amm>>,
+    pub user: Signer<'info>,
+
+    #[account(mut)]
+    pub user_token_a: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut)]
+    pub user_token_b: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(address = pool.token_a_mint @ AmmError::InvalidTokenPair)]
+    pub token_a_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(address = pool.token_b_mint @ AmmError::InvalidTokenPair)]
+    pub token_b_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(mut, address = pool.token_a_vault @ AmmError::InvalidTokenPair)]
+    pub token_a_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut, address = pool.token_b_vault @ AmmError::InvalidTokenPair)]
+    pub token_b_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Array holding the current tick, then up to two more in the swap
+    /// direction; the swap stops at the end of the last one supplied
+    #[account(
+        mut,
+        constraint = tick_array_0.pool == pool.key() @ AmmError::InvalidTickArray
+    )]
+    pub tick_array_0: Box<Account<'info, TickArray>>,
+    #[account(
+        mut,
+        constraint = tick_array_1.pool == pool.key() @ AmmError::InvalidTickArray
+    )]
+    pub tick_array_1: Option<Box<Account<'info, TickArray>>>,
+    #[account(
+        mut,
+        constraint = tick_array_2.pool == pool.key() @ AmmError::InvalidTickArray
+    )]
+    pub tick_array_2: Option<Box<Account<'info, TickArray>>>,
+
+    pub token_program: Program<'info, Token2022>,
+}
+
+#[event]
+pub struct ClPoolCreated {
+    pub pool: Pubkey,
+    pub token_a_mint: Pubkey,
+    pub token_b_mint: Pubkey,
+    pub tick_spacing: u16,
+    pub sqrt_price_x64: u128,
+    pub tick_current: i32,
+}
+
+#[event]
+pub struct ClLiquidityChanged {
+    pub pool: Pubkey,
+    pub position: Pubkey,
+    pub owner: Pubkey,
+    pub liquidity_delta: i128,
+    pub token_a_amount: u64,
+    pub token_b_amount: u64,
+}
+
+#[event]
+pub struct ClSwapped {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub a_to_b: bool,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee: u64,
+    pub sqrt_price_x64: u128,
+    pub tick_current: i32,
+    pub liquidity: u128,
+}
+
+#[event]
+pub struct ClFeesCollected {
+    pub pool: Pubkey,
+    pub position: Pubkey,
+    pub owner: Pubkey,
+    pub token_a_amount: u64,
+    pub token_b_amount: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPACING: u16 = 10;
+    /// Liquidity of a wide position over [-100, 100) and a narrow one over
+    /// [-20, 20)
+    const WIDE: u128 = 1_000_000_000_000;
+    const NARROW: u128 = 3_000_000_000_000;
+
+    fn amm() -> Amm {
+        Amm {
+            authority: Pubkey::default(),
+            pool_fee: 25,
+            pool_fee_denominator: 10_000,
+            token_a_mint: Pubkey::default(),
+            token_b_mint: Pubkey::default(),
+            token_a_vault: Pubkey::default(),
+            token_b_vault: Pubkey::default(),
+            lp_mint: Pubkey::default(),
+            bump: 0,
+        }
+    }
+
+    /// Pool at tick 0 inside both positions, and the arrays starting at
+    /// `starts` with the positions' ticks filled in
+    fn setup(starts: &[i32]) -> (ClPool, Vec<TickArray>) {
+        let pool = ClPool {
+            token_a_mint: Pubkey::default(),
+            token_b_mint: Pubkey::default(),
+            token_a_vault: Pubkey::default(),
+            token_b_vault: Pubkey::default(),
+            tick_spacing: SPACING,
+            sqrt_price_x64: math::sqrt_price_at_tick(0).unwrap(),
+            tick_current: 0,
+            liquidity: WIDE + NARROW,
+            fee_growth_global_a_x64: 0,
+            fee_growth_global_b_x64: 0,
+            locked: false,
+            bump: 0,
+        };
+        let mut arrays: Vec<TickArray> = starts
+            .iter()
+            .map(|start| TickArray {
+                pool: Pubkey::default(),
+                start_tick_index: *start,
+                ticks: [Tick::default(); TICK_ARRAY_SIZE],
+            })
+            .collect();
+        for (tick, liquidity_net) in [(-100, WIDE as i128), (100, -(WIDE as i128)), (-20, NARROW as i128), (20, -(NARROW as i128))] {
+            if let Some(array) = arrays.iter_mut().find(|a| a.contains(tick, SPACING)) {
+                *array.tick_mut(tick, SPACING).unwrap() = Tick {
+                    liquidity_net,
+                    liquidity_gross: liquidity_net.unsigned_abs(),
+                    ..Tick::default()
+                };
+            }
+        }
+        (pool, arrays)
+    }
+
+    #[test]
+    fn swap_crosses_an_initialized_tick() {
+        let span = TickArray::span(SPACING);
+        let (mut pool, mut arrays) = setup(&[0, -span, -2 * span]);
+        let mut arrays: Vec<&mut TickArray> = arrays.iter_mut().collect();
+        let limit = math::sqrt_price_at_tick(-50).unwrap();
+        let result = compute_swap(&mut pool, &mut arrays, &amm(), 1_000_000_000_000, true, limit).unwrap();
+
+        // The narrow position drops out at -20 and the swap stops at the limit
+        assert_eq!(pool.liquidity, WIDE);
+        assert_eq!(pool.sqrt_price_x64, limit);
+        assert_eq!(pool.tick_current, -50);
+        assert!(result.amount_in < 1_000_000_000_000);
+
+        let (p0, p20) = (math::sqrt_price_at_tick(0).unwrap(), math::sqrt_price_at_tick(-20).unwrap());
+        let out = math::amount_b_delta(p20, p0, WIDE + NARROW, false).unwrap()
+            + math::amount_b_delta(limit, p20, WIDE, false).unwrap();
+        let amm = amm();
+        let step_in = |a, b, liquidity| amm.gross_up(math::amount_a_delta(a, b, liquidity, true).unwrap()).unwrap();
+        assert_eq!(result.amount_out, out);
+        assert_eq!(result.amount_in, step_in(p20, p0, WIDE + NARROW) + step_in(limit, p20, WIDE));
+
+        // The crossed tick keeps the growth accrued above it, before the cross
+        let first_in = math::amount_a_delta(p20, p0, WIDE + NARROW, true).unwrap();
+        let first_fee = amm.gross_up(first_in).unwrap() - first_in;
+        let crossed = arrays[1].tick(-20, SPACING).unwrap();
+        assert_eq!(crossed.fee_growth_outside_a_x64, ((first_fee as u128) << 64) / (WIDE + NARROW));
+        assert!(pool.fee_growth_global_a_x64 > crossed.fee_growth_outside_a_x64);
+    }
+
+    #[test]
+    fn swap_stops_at_the_last_array_supplied() {
+        // One array covers [0, 320); buying token A runs out of it well
+        // before the input does
+        let (mut pool, mut arrays) = setup(&[0]);
+        let mut arrays: Vec<&mut TickArray> = arrays.iter_mut().collect();
+        let result = compute_swap(&mut pool, &mut arrays, &amm(), u64::MAX / 2, false, 0).unwrap();
+
+        let edge = TickArray::span(SPACING);
+        assert_eq!(pool.sqrt_price_x64, math::sqrt_price_at_tick(edge).unwrap());
+        assert_eq!(pool.tick_current, edge);
+        assert_eq!(pool.liquidity, 0);
+        assert!(result.amount_in < u64::MAX / 2);
+        assert!(result.amount_out > 0);
+    }
+}
@@ -0,0 +1,49 @@
+//! x * y = k
+
+use anchor_lang::prelude::*;
+
+use super::{proportional_deposit, SwapCurve};
+use crate::AmmError;
+
+pub struct ConstantProductCurve;
+
+impl SwapCurve for ConstantProductCurve {
+    fn swap_exact_in(&self, amount_in: u64, reserve_in: u64, reserve_out: u64, _a_to_b: bool) -> Result<u64> {
+        // Use u128 to prevent overflow in intermediate calculations
+        let amount_in = amount_in as u128;
+        let reserve_in = reserve_in as u128;
+        let reserve_out = reserve_out as u128;
+
+        let numerator = amount_in.checked_mul(reserve_out)
+            .ok_or(AmmError::InvalidSwapCalculation)?;
+        let denominator = reserve_in.checked_add(amount_in)
+            .ok_or(AmmError::InvalidSwapCalculation)?;
+
+        let amount_out = numerator.checked_div(denominator)
+            .ok_or(AmmError::InvalidSwapCalculation)?;
+
+        // Convert back to u64, checking for overflow
+        u64::try_from(amount_out).map_err(|_| AmmError::InvalidSwapCalculation.into())
+    }
+
+    fn swap_exact_out(&self, amount_out: u64, reserve_in: u64, reserve_out: u64, _a_to_b: bool) -> Result<u64> {
+        require!(amount_out < reserve_out, AmmError::InvalidSwapCalculation);
+
+        // out >= amount_out  <=>  in * (reserve_out - amount_out) >= amount_out * reserve_in
+        let numerator = (reserve_in as u128)
+            .checked_mul(amount_out as u128)
+            .ok_or(AmmError::InvalidSwapCalculation)?;
+        let denominator = (reserve_out - amount_out) as u128;
+
+        u64::try_from(numerator.div_ceil(denominator)).map_err(|_| AmmError::InvalidSwapCalculation.into())
+    }
+
+    fn spot_price(&self, reserve_in: u64, reserve_out: u64, _a_to_b: bool) -> Result<u128> {
+        require!(reserve_in > 0, AmmError::InvalidSwapCalculation);
+        Ok(((reserve_out as u128) << 64) / reserve_in as u128)
+    }
+
+    fn deposit(&self, amount_a: u64, amount_b: u64, reserve_a: u64, reserve_b: u64, lp_supply: u64) -> Result<u64> {
+        proportional_deposit(amount_a, amount_b, reserve_a, reserve_b, lp_supply)
+    }
+}
@@ -0,0 +1,184 @@
+//! Q64.64 fixed-point `log2`, `exp2` and `pow`, using only u128 shifts and
+//! multiplies so they stay cheap on BPF.
+
+use anchor_lang::prelude::*;
+
+use super::U256;
+use crate::AmmError;
+
+pub const ONE: u128 = 1 << 64;
+
+/// Upper bound on the relative error of `pow`, in Q64.64. Callers widen
+/// results by this much in the pool's favour.
+pub const MAX_POW_RELATIVE_ERROR: u128 = 18_446_744; // ~1e-12
+
+/// Fraction bits kept while iterating. One bit below 64 so that squaring a
+/// value in [1, 2) fits in u128.
+const PRECISION: u32 = 63;
+
+/// round(2^(2^-i) * 2^63) for i in 1..=63
+const EXP2_FRACTION_BITS: [u64; 63] = [
+    13043817825332782212, 10968499650544839023, 10058158527438640871,
+    9631725603661576981, 9425337585438007767, 9323807973325342579,
+    9273454034941487980, 9248379135337035441, 9235867122425417553,
+    9229617465154500136, 9226494222562136017, 9224932997620185949,
+    9224152484218207415, 9223762252282022473, 9223567142504825508,
+    9223469589163912706, 9223420812880372951, 9223396424835331638,
+    9223384230836993049, 9223378133843869261, 9223375085348818743,
+    9223373561101671328, 9223372798978192081, 9223372417916476073,
+    9223372227385623972, 9223372132120199398, 9223372084487487480,
+    9223372060671131613, 9223372048762953703, 9223372042808864754,
+    9223372039831820280, 9223372038343298044, 9223372037599036926,
+    9223372037226906367, 9223372037040841087, 9223372036947808448,
+    9223372036901292128, 9223372036878033968, 9223372036866404888,
+    9223372036860590348, 9223372036857683078, 9223372036856229443,
+    9223372036855502625, 9223372036855139217, 9223372036854957512,
+    9223372036854866660, 9223372036854821234, 9223372036854798521,
+    9223372036854787165, 9223372036854781486, 9223372036854778647,
+    9223372036854777228, 9223372036854776518, 9223372036854776163,
+    9223372036854775985, 9223372036854775897, 9223372036854775852,
+    9223372036854775830, 9223372036854775819, 9223372036854775814,
+    9223372036854775811, 9223372036854775809, 9223372036854775809,
+];
+
+/// log2 of a positive Q64.64 value, rounded down
+pub fn log2(x: u128) -> Result<i128> {
+    require!(x > 0, AmmError::InvalidSwapCalculation);
+    let msb = 127 - x.leading_zeros() as i32;
+    let mut result = ((msb - 64) as i128) << 64;
+
+    // Normalise into [1, 2) with 63 fraction bits, then read off one bit of
+    // the fraction per squaring
+    let mut y = if msb >= PRECISION as i32 {
+        x >> (msb - PRECISION as i32)
+    } else {
+        x << (PRECISION as i32 - msb)
+    };
+    for bit in 1..=PRECISION {
+        y = (y * y) >> PRECISION;
+        if y >= 2 << PRECISION {
+            y >>= 1;
+            result += 1 << (64 - bit);
+        }
+    }
+    Ok(result)
+}
+
+/// 2^x for a Q64.64 exponent, rounded down
+pub fn exp2(x: i128) -> Result<u128> {
+    let integer = x >> 64;
+    let fraction = (x - (integer << 64)) as u64;
+
+    let mut result: u128 = 1 << PRECISION;
+    for (i, factor) in EXP2_FRACTION_BITS.iter().enumerate() {
+        if fraction & (1 << (63 - i)) != 0 {
+            result = (result * *factor as u128) >> PRECISION;
+        }
+    }
+
+    // From 63 to 64 fraction bits, then scale by 2^integer
+    let shift = integer + 1;
+    if shift < 0 {
+        Ok(result.checked_shr((-shift) as u32).unwrap_or(0))
+    } else {
+        require!(
+            shift < result.leading_zeros() as i128,
+            AmmError::InvalidSwapCalculation
+        );
+        Ok(result << shift)
+    }
+}
+
+/// base^exponent for Q64.64 operands
+pub fn pow(base: u128, exponent: u128) -> Result<u128> {
+    if base == 0 {
+        return Ok(0);
+    }
+    let log = log2(base)?;
+    let product = (U256::from(log.unsigned_abs()) * U256::from(exponent)) >> 64;
+    let product = i128::try_from(product.as_u128()).map_err(|_| AmmError::InvalidSwapCalculation)?;
+    exp2(if log < 0 { -product } else { product })
+}
+
+/// `pow` widened by its error bound, for results the pool must not undershoot
+pub fn pow_up(base: u128, exponent: u128) -> Result<u128> {
+    let result = pow(base, exponent)?;
+    Ok(result + mul(result, MAX_POW_RELATIVE_ERROR)? + 1)
+}
+
+/// Product of two Q64.64 values, rounded down
+pub fn mul(a: u128, b: u128) -> Result<u128> {
+    u128::try_from((U256::from(a) * U256::from(b)) >> 64)
+        .map_err(|_| AmmError::InvalidSwapCalculation.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reference values, rounded down, computed to 80 digits with Python's
+    /// `decimal`
+    const LOG2: [(u128, i128); 3] = [
+        (3 * ONE, 29237397617229858719),
+        (ONE / 10, -61278757397652712450),
+        (7 * ONE / 4, 14893069623846345523),
+    ];
+    const EXP2: [(i128, u128); 3] = [
+        (ONE as i128 / 2, 26087635650665564424),
+        (-3 * ONE as i128 / 2, 6521908912666391106),
+        (41 * ONE as i128 / 4, 22463487284315830319669),
+    ];
+    /// (base, exponent, base^exponent)
+    const POW: [(u128, u128, u128); 3] = [
+        (3 * ONE / 2, ONE / 4, 20414678143713417215),
+        (4 * ONE / 5, 4 * ONE, 7555786372591432340),
+        (ONE / 3, 5 * ONE / 2, 1183359184069815933),
+    ];
+
+    #[test]
+    fn exact_powers_of_two() {
+        assert_eq!(log2(ONE).unwrap(), 0);
+        assert_eq!(log2(2 * ONE).unwrap(), ONE as i128);
+        assert_eq!(log2(ONE / 2).unwrap(), -(ONE as i128));
+        assert_eq!(log2(1).unwrap(), -64 * ONE as i128);
+        assert_eq!(exp2(0).unwrap(), ONE);
+        assert_eq!(exp2(3 * ONE as i128).unwrap(), 8 * ONE);
+        assert_eq!(exp2(-(ONE as i128)).unwrap(), ONE / 2);
+    }
+
+    #[test]
+    fn log2_rounds_down_to_within_a_few_units() {
+        for (x, want) in LOG2 {
+            let got = log2(x).unwrap();
+            assert!(got <= want && want - got <= 4, "log2({x}): got {got}, want {want}");
+        }
+    }
+
+    #[test]
+    fn exp2_rounds_down_to_within_one_part_in_2_60() {
+        for (x, want) in EXP2 {
+            let got = exp2(x).unwrap();
+            assert!(got <= want && want - got <= want >> 60, "exp2({x}): got {got}, want {want}");
+        }
+    }
+
+    #[test]
+    fn pow_stays_within_its_error_bound() {
+        for (base, exponent, want) in POW {
+            let got = pow(base, exponent).unwrap();
+            let bound = mul(want, MAX_POW_RELATIVE_ERROR).unwrap();
+            assert!(got.abs_diff(want) <= bound, "pow({base}, {exponent}): got {got}, want {want}");
+            assert!(pow_up(base, exponent).unwrap() >= want);
+        }
+        assert_eq!(pow(0, ONE / 2).unwrap(), 0);
+        assert_eq!(pow(3 * ONE, 0).unwrap(), ONE);
+    }
+
+    #[test]
+    fn rejects_zero_and_overflow() {
+        assert_eq!(log2(0).unwrap_err(), AmmError::InvalidSwapCalculation.into());
+        assert_eq!(exp2(64 * ONE as i128).unwrap_err(), AmmError::InvalidSwapCalculation.into());
+        assert!(exp2(62 * ONE as i128).is_ok());
+        assert_eq!(exp2(-200 * ONE as i128).unwrap(), 0);
+    }
+}
@@ -0,0 +1,217 @@
+//! Swap curves. Instruction handlers only talk to `SwapCurve`; the pool's
+//! `CurveParams` decides which implementation they get.
+
+use anchor_lang::prelude::*;
+
+use crate::AmmError;
+
+pub mod constant_product;
+pub mod fixed_point;
+pub mod stable;
+pub mod weighted;
+
+pub use constant_product::ConstantProductCurve;
+pub use stable::StableCurve;
+pub use weighted::WeightedCurve;
+
+// Kept out of this module's scope: the macro expands against std's `Result`
+#[allow(clippy::assign_op_pattern, clippy::manual_div_ceil)]
+mod wide {
+    uint::construct_uint! {
+        /// 256-bit integer for invariant math whose intermediates overflow u128
+        pub struct U256(4);
+    }
+}
+
+pub use wide::U256;
+
+/// Pricing and LP accounting for one pool type. Amounts passed to the swap
+/// methods are net of the AMM fee, which handlers charge separately.
+pub trait SwapCurve {
+    /// Output paid for `amount_in` of the input token
+    fn swap_exact_in(&self, amount_in: u64, reserve_in: u64, reserve_out: u64, a_to_b: bool) -> Result<u64>;
+
+    /// Smallest input for which `swap_exact_in` pays at least `amount_out`
+    fn swap_exact_out(&self, amount_out: u64, reserve_in: u64, reserve_out: u64, a_to_b: bool) -> Result<u64>;
+
+    /// Q64.64 marginal price of the input token in output tokens
+    fn spot_price(&self, reserve_in: u64, reserve_out: u64, a_to_b: bool) -> Result<u128>;
+
+    /// LP tokens minted for depositing `amount_a` and `amount_b`
+    fn deposit(&self, amount_a: u64, amount_b: u64, reserve_a: u64, reserve_b: u64, lp_supply: u64) -> Result<u64>;
+
+    /// Tokens `(a, b)` paid out for burning `lp_amount`
+    fn withdraw(&self, lp_amount: u64, reserve_a: u64, reserve_b: u64, lp_supply: u64) -> Result<(u64, u64)> {
+        proportional_withdraw(lp_amount, reserve_a, reserve_b, lp_supply)
+    }
+}
+
+/// Curve selection and parameters stored on `Pool`. The Borsh variant tag is
+/// the curve-type discriminator.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, InitSpace)]
+pub enum CurveParams {
+    ConstantProduct,
+    /// StableSwap with an amplification coefficient that moves linearly from
+    /// `initial_amp` to `target_amp` between the two timestamps
+    Stable {
+        initial_amp: u64,
+        target_amp: u64,
+        ramp_start_ts: i64,
+        ramp_stop_ts: i64,
+    },
+    /// Weighted product with weights scaled by `weighted::WEIGHT_ONE`
+    Weighted { weight_a: u64, weight_b: u64 },
+    /// Liquidity bootstrapping: a weighted pool whose token A weight moves
+    /// linearly from `start_weight_a` to `end_weight_a` over the sale
+    Lbp {
+        start_weight_a: u64,
+        end_weight_a: u64,
+        start_ts: i64,
+        end_ts: i64,
+    },
+}
+
+impl CurveParams {
+    /// Reject parameters no curve can be built from
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            CurveParams::ConstantProduct => Ok(()),
+            CurveParams::Stable {
+                initial_amp,
+                target_amp,
+                ramp_start_ts,
+                ramp_stop_ts,
+            } => {
+                require!(
+                    stable::amp_in_range(*initial_amp)
+                        && stable::amp_in_range(*target_amp)
+                        && ramp_start_ts <= ramp_stop_ts,
+                    AmmError::InvalidCurveParameters
+                );
+                Ok(())
+            }
+            CurveParams::Weighted { weight_a, weight_b } => {
+                require!(
+                    weighted::weights_valid(*weight_a, *weight_b),
+                    AmmError::InvalidCurveParameters
+                );
+                Ok(())
+            }
+            CurveParams::Lbp {
+                start_weight_a,
+                end_weight_a,
+                start_ts,
+                end_ts,
+            } => {
+                let valid = |weight_a: u64| {
+                    weight_a < weighted::WEIGHT_ONE
+                        && weighted::weights_valid(weight_a, weighted::WEIGHT_ONE - weight_a)
+                };
+                require!(
+                    valid(*start_weight_a) && valid(*end_weight_a) && start_ts < end_ts,
+                    AmmError::InvalidCurveParameters
+                );
+                Ok(())
+            }
+        }
+    }
+
+    /// The curve as priced at unix time `now`
+    pub fn swap_curve(&self, now: i64) -> Box<dyn SwapCurve> {
+        match self {
+            CurveParams::ConstantProduct => Box::new(ConstantProductCurve),
+            CurveParams::Stable { .. } => Box::new(StableCurve {
+                amp: self.current_amp(now).unwrap_or_default(),
+            }),
+            CurveParams::Weighted { weight_a, weight_b } => Box::new(WeightedCurve {
+                weight_a: *weight_a,
+                weight_b: *weight_b,
+            }),
+            CurveParams::Lbp {
+                start_weight_a,
+                end_weight_a,
+                start_ts,
+                end_ts,
+            } => {
+                let weight_a = interpolate(*start_weight_a, *end_weight_a, *start_ts, *end_ts, now);
+                Box::new(WeightedCurve {
+                    weight_a,
+                    weight_b: weighted::WEIGHT_ONE.saturating_sub(weight_a),
+                })
+            }
+        }
+    }
+
+    /// Whether an LBP sale has not yet ended at unix time `now`
+    pub fn sale_active(&self, now: i64) -> bool {
+        matches!(self, CurveParams::Lbp { end_ts, .. } if now < *end_ts)
+    }
+
+    /// Amplification coefficient of a stable curve at unix time `now`
+    pub fn current_amp(&self, now: i64) -> Option<u64> {
+        let CurveParams::Stable {
+            initial_amp,
+            target_amp,
+            ramp_start_ts,
+            ramp_stop_ts,
+        } = *self
+        else {
+            return None;
+        };
+        Some(interpolate(initial_amp, target_amp, ramp_start_ts, ramp_stop_ts, now))
+    }
+}
+
+/// `start` before `start_ts`, `end` from `end_ts` on, linear in between
+fn interpolate(start: u64, end: u64, start_ts: i64, end_ts: i64, now: i64) -> u64 {
+    if now >= end_ts {
+        return end;
+    }
+    if now <= start_ts {
+        return start;
+    }
+    let elapsed = (now - start_ts) as i128;
+    let duration = (end_ts - start_ts) as i128;
+    let delta = end as i128 - start as i128;
+    (start as i128 + delta * elapsed / duration) as u64
+}
+
+/// Proportional LP amount: the first deposit mints `amount_a`, later ones
+/// mint the smaller of the two pro-rata shares
+pub fn proportional_deposit(
+    amount_a: u64,
+    amount_b: u64,
+    reserve_a: u64,
+    reserve_b: u64,
+    lp_supply: u64,
+) -> Result<u64> {
+    if lp_supply == 0 {
+        return Ok(amount_a);
+    }
+    let lp_tokens_a = mul_div(amount_a, lp_supply, reserve_a)?;
+    let lp_tokens_b = mul_div(amount_b, lp_supply, reserve_b)?;
+    Ok(lp_tokens_a.min(lp_tokens_b))
+}
+
+/// Pro-rata share of both reserves, rounded down
+pub fn proportional_withdraw(
+    lp_amount: u64,
+    reserve_a: u64,
+    reserve_b: u64,
+    lp_supply: u64,
+) -> Result<(u64, u64)> {
+    require!(lp_amount <= lp_supply, AmmError::InvalidLiquidityCalculation);
+    Ok((
+        mul_div(lp_amount, reserve_a, lp_supply)?,
+        mul_div(lp_amount, reserve_b, lp_supply)?,
+    ))
+}
+
+/// `a * b / c` in u128, rounded down
+pub fn mul_div(a: u64, b: u64, c: u64) -> Result<u64> {
+    let result = (a as u128)
+        .checked_mul(b as u128)
+        .and_then(|n| n.checked_div(c as u128))
+        .ok_or(AmmError::InvalidLiquidityCalculation)?;
+    u64::try_from(result).map_err(|_| AmmError::InvalidLiquidityCalculation.into())
+}
@@ -0,0 +1,282 @@
+//! StableSwap: A * n^n * sum(x) + D = A * D * n^n + D^(n+1) / (n^n * prod(x))
+//! over n coins; `StableCurve` is the two-coin case and basket pools use the
+//! general one. D and the post-trade balance are found by Newton's method in
+//! 256-bit integers.
+//!
+//! Balances are raw token amounts, pegging one unit of each coin to one unit
+//! of every other, so stable pools and baskets only admit mints with equal
+//! decimals.
+
+use anchor_lang::prelude::*;
+
+use super::{proportional_deposit, SwapCurve, U256};
+use crate::AmmError;
+
+pub const MIN_AMP: u64 = 1;
+pub const MAX_AMP: u64 = 1_000_000;
+/// A ramp may at most multiply or divide the current amplification by this
+pub const MAX_AMP_CHANGE: u64 = 10;
+/// Shortest allowed ramp, in seconds
+pub const MIN_RAMP_DURATION: i64 = 86_400;
+
+/// Newton's method converges in well under ten steps for sane reserves; the
+/// cap keeps pathological inputs within the compute budget
+const MAX_ITERATIONS: usize = 32;
+/// Extra units `swap_exact_out` may add to absorb rounding in `compute_y`
+const MAX_ROUNDING_STEPS: u64 = 4;
+
+pub fn amp_in_range(amp: u64) -> bool {
+    (MIN_AMP..=MAX_AMP).contains(&amp)
+}
+
+/// A * n^n for `n` coins
+fn ann(amp: u64, n: usize) -> Result<U256> {
+    require!(amp_in_range(amp), AmmError::InvalidCurveParameters);
+    let n = U256::from(n);
+    Ok(U256::from(amp) * n.pow(n))
+}
+
+/// D^(n+1) / (n^n * prod(x))
+fn d_product(d: U256, balances: &[u64]) -> Result<U256> {
+    let n = U256::from(balances.len());
+    let mut d_p = d;
+    for balance in balances {
+        d_p = checked_mul(d_p, d)? / (U256::from(*balance) * n);
+    }
+    Ok(d_p)
+}
+
+/// The invariant D of `balances`, one per coin
+pub fn compute_d(amp: u64, balances: &[u64]) -> Result<U256> {
+    require!(balances.iter().all(|b| *b > 0), AmmError::InvalidSwapCalculation);
+    let ann = ann(amp, balances.len())?;
+    let n = U256::from(balances.len());
+    let sum = balances.iter().fold(U256::zero(), |s, b| s + U256::from(*b));
+
+    let mut d = sum;
+    for _ in 0..MAX_ITERATIONS {
+        let d_p = d_product(d, balances)?;
+        let d_prev = d;
+        // D = (Ann * S + n * D_P) * D / ((Ann - 1) * D + (n + 1) * D_P)
+        let numerator = checked_mul(ann * sum + d_p * n, d)?;
+        let denominator = (ann - 1) * d + d_p * (n + 1);
+        d = numerator / denominator;
+        if abs_diff(d, d_prev) <= U256::one() {
+            return Ok(d);
+        }
+    }
+    err!(AmmError::CurveDidNotConverge)
+}
+
+/// Balance of coin `j` that keeps D fixed given every other entry of
+/// `balances`
+pub fn compute_y(amp: u64, balances: &[u64], j: usize, d: U256) -> Result<u64> {
+    let ann = ann(amp, balances.len())?;
+    let n = U256::from(balances.len());
+
+    // y^2 + (b - D) * y = c
+    let mut c = d;
+    let mut sum = U256::zero();
+    let others = balances.iter().enumerate().filter(|(k, _)| *k != j);
+    for (_, balance) in others {
+        require!(*balance > 0, AmmError::InvalidSwapCalculation);
+        let x = U256::from(*balance);
+        sum += x;
+        c = checked_mul(c, d)? / (x * n);
+    }
+    let c = checked_mul(c, d)? / (ann * n);
+    let b = sum + d / ann;
+
+    let mut y = d;
+    for _ in 0..MAX_ITERATIONS {
+        let y_prev = y;
+        let denominator = (y + y + b)
+            .checked_sub(d)
+            .filter(|v| !v.is_zero())
+            .ok_or(AmmError::InvalidSwapCalculation)?;
+        y = (checked_mul(y, y)? + c) / denominator;
+        if abs_diff(y, y_prev) <= U256::one() {
+            return to_u64(y);
+        }
+    }
+    err!(AmmError::CurveDidNotConverge)
+}
+
+/// Output for `amount_in` of coin `i` paid out in coin `j`
+pub fn swap_exact_in(amp: u64, balances: &[u64], i: usize, j: usize, amount_in: u64) -> Result<u64> {
+    let d = compute_d(amp, balances)?;
+    let mut after = balances.to_vec();
+    after[i] = after[i]
+        .checked_add(amount_in)
+        .ok_or(AmmError::InvalidSwapCalculation)?;
+    let new_balance_out = compute_y(amp, &after, j, d)?;
+    // Measure against the balance D itself implies, so the rounding of D on
+    // skewed balances cannot pay out more than the balance allows, and one
+    // unit less covers the rounding of Newton's method
+    let implied_balance_out = compute_y(amp, balances, j, d)?.min(balances[j]);
+    Ok(implied_balance_out
+        .saturating_sub(new_balance_out)
+        .saturating_sub(1))
+}
+
+pub struct StableCurve {
+    pub amp: u64,
+}
+
+impl SwapCurve for StableCurve {
+    fn swap_exact_in(&self, amount_in: u64, reserve_in: u64, reserve_out: u64, _a_to_b: bool) -> Result<u64> {
+        swap_exact_in(self.amp, &[reserve_in, reserve_out], 0, 1, amount_in)
+    }
+
+    fn swap_exact_out(&self, amount_out: u64, reserve_in: u64, reserve_out: u64, a_to_b: bool) -> Result<u64> {
+        require!(amount_out < reserve_out, AmmError::InvalidSwapCalculation);
+        let d = compute_d(self.amp, &[reserve_in, reserve_out])?;
+        let new_reserve_in = compute_y(self.amp, &[reserve_in, reserve_out - amount_out], 0, d)?;
+        let estimate = new_reserve_in.saturating_sub(reserve_in) + 1;
+
+        for amount_in in estimate..estimate + MAX_ROUNDING_STEPS {
+            if self.swap_exact_in(amount_in, reserve_in, reserve_out, a_to_b)? >= amount_out {
+                return Ok(amount_in);
+            }
+        }
+        err!(AmmError::CurveDidNotConverge)
+    }
+
+    fn spot_price(&self, reserve_in: u64, reserve_out: u64, _a_to_b: bool) -> Result<u128> {
+        // -dy/dx = y * (Ann * x + D_P) / (x * (Ann * y + D_P))
+        let balances = [reserve_in, reserve_out];
+        let ann = ann(self.amp, balances.len())?;
+        let d = compute_d(self.amp, &balances)?;
+        let d_p = d_product(d, &balances)?;
+        let (x, y) = (U256::from(reserve_in), U256::from(reserve_out));
+
+        let numerator = checked_mul(y, ann * x + d_p)? << 64;
+        let denominator = checked_mul(x, ann * y + d_p)?;
+        u128::try_from(numerator / denominator).map_err(|_| AmmError::InvalidSwapCalculation.into())
+    }
+
+    fn deposit(&self, amount_a: u64, amount_b: u64, reserve_a: u64, reserve_b: u64, lp_supply: u64) -> Result<u64> {
+        proportional_deposit(amount_a, amount_b, reserve_a, reserve_b, lp_supply)
+    }
+}
+
+fn checked_mul(a: U256, b: U256) -> Result<U256> {
+    a.checked_mul(b).ok_or(AmmError::InvalidSwapCalculation.into())
+}
+
+fn abs_diff(a: U256, b: U256) -> U256 {
+    if a > b {
+        a - b
+    } else {
+        b - a
+    }
+}
+
+fn to_u64(value: U256) -> Result<u64> {
+    u64::try_from(value).map_err(|_| AmmError::InvalidSwapCalculation.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reserve pairs from balanced to a billion to one
+    const RESERVES: [(u64, u64); 4] = [
+        (1_000_000_000, 1_000_000_000),
+        (1_000_000, 100_000_000_000),
+        (1, 1_000_000_000),
+        (10, u64::MAX / 4),
+    ];
+
+    /// `compute_y` on one side of reserves `x`, `y` gives back `y` to
+    /// within a millionth
+    fn assert_round_trip(amp: u64, x: u64, y: u64) {
+        let d = compute_d(amp, &[x, y]).unwrap();
+        let back = compute_y(amp, &[x, y], 1, d).unwrap();
+        assert!(back.abs_diff(y) <= 2 + y / 1_000_000, "amp {amp} x {x} y {y}: got {back}");
+    }
+
+    #[test]
+    fn balanced_reserves_give_their_sum() {
+        for amp in [MIN_AMP, 100, MAX_AMP] {
+            let d = compute_d(amp, &[1_000_000_000, 1_000_000_000]).unwrap();
+            assert!(abs_diff(d, U256::from(2_000_000_000u64)) <= U256::one());
+        }
+    }
+
+    #[test]
+    fn converges_at_extreme_amplification() {
+        for (x, y) in RESERVES.into_iter().take(3) {
+            assert_round_trip(MAX_AMP, x, y);
+            assert_round_trip(MAX_AMP, y, x);
+        }
+        assert_round_trip(MIN_AMP, 1_000_000_000, 1_000_000_000);
+        assert_round_trip(MIN_AMP, 1_000_000, 100_000_000_000);
+    }
+
+    #[test]
+    fn converges_on_imbalanced_reserves() {
+        for (x, y) in RESERVES.into_iter().take(3) {
+            assert_round_trip(100, x, y);
+            assert_round_trip(100, y, x);
+        }
+    }
+
+    #[test]
+    fn gives_up_on_reserves_too_skewed_to_converge() {
+        let err = compute_d(MIN_AMP, &[1_000_000_000, 1]).unwrap_err();
+        assert_eq!(err, AmmError::CurveDidNotConverge.into());
+        let err = compute_d(100, &[10, u64::MAX / 4]).unwrap_err();
+        assert_eq!(err, AmmError::CurveDidNotConverge.into());
+    }
+
+    #[test]
+    fn pays_nothing_for_nothing() {
+        for amp in [MIN_AMP, 100, MAX_AMP] {
+            let curve = StableCurve { amp };
+            for (x, y) in RESERVES {
+                for (reserve_in, reserve_out) in [(x, y), (y, x)] {
+                    if compute_d(amp, &[reserve_in, reserve_out]).is_ok() {
+                        let out = curve.swap_exact_in(0, reserve_in, reserve_out, true).unwrap();
+                        assert_eq!(out, 0, "amp {amp} reserves {reserve_in}/{reserve_out}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn high_amplification_stays_near_one_to_one() {
+        let out = StableCurve { amp: MAX_AMP }
+            .swap_exact_in(1_000_000, 1_000_000_000, 900_000_000, true)
+            .unwrap();
+        assert!(out > 999_000 && out < 1_000_000, "got {out}");
+    }
+
+    #[test]
+    fn exact_out_covers_the_requested_amount() {
+        let curve = StableCurve { amp: 100 };
+        for (reserve_in, reserve_out) in [(1_000_000_000, 1_000_000_000), (1_000_000, 100_000_000_000)] {
+            let amount_in = curve.swap_exact_out(5_000_000, reserve_in, reserve_out, true).unwrap();
+            assert!(curve.swap_exact_in(amount_in, reserve_in, reserve_out, true).unwrap() >= 5_000_000);
+            assert!(curve.swap_exact_in(amount_in - 1, reserve_in, reserve_out, true).unwrap() < 5_000_000);
+        }
+    }
+
+    #[test]
+    fn three_coins_trade_near_one_to_one() {
+        let balances = [1_000_000_000, 1_000_000_000, 500_000_000];
+        let d = compute_d(100, &balances).unwrap();
+        assert!(compute_y(100, &balances, 2, d).unwrap().abs_diff(500_000_000) <= 2);
+        let out = swap_exact_in(100, &balances, 0, 1, 1_000_000).unwrap();
+        assert!(out > 990_000 && out < 1_000_000, "got {out}");
+        assert_eq!(swap_exact_in(100, &balances, 0, 1, 0).unwrap(), 0);
+    }
+
+    #[test]
+    fn rejects_empty_reserves_and_bad_amplification() {
+        assert!(compute_d(100, &[0, 1_000]).is_err());
+        assert!(compute_d(0, &[1_000, 1_000]).is_err());
+        assert!(compute_d(MAX_AMP + 1, &[1_000, 1_000]).is_err());
+    }
+}
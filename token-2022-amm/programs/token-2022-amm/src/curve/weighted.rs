@@ -0,0 +1,195 @@
+//! Weighted product: x^w_a * y^w_b = k, with w_a + w_b = 1
+
+use anchor_lang::prelude::*;
+
+use super::fixed_point::{self, ONE};
+use super::{proportional_deposit, SwapCurve, U256};
+use crate::AmmError;
+
+/// Weights are stored scaled by this; a pool's two weights sum to it
+pub const WEIGHT_ONE: u64 = 1_000_000_000;
+/// Neither side may weigh less than 1%
+pub const MIN_WEIGHT: u64 = WEIGHT_ONE / 100;
+
+/// Extra units `swap_exact_out` may add to absorb rounding in `pow`
+const MAX_ROUNDING_STEPS: u64 = 4;
+
+pub fn weights_valid(weight_a: u64, weight_b: u64) -> bool {
+    weight_a >= MIN_WEIGHT && weight_b >= MIN_WEIGHT && weight_a.checked_add(weight_b) == Some(WEIGHT_ONE)
+}
+
+pub struct WeightedCurve {
+    pub weight_a: u64,
+    pub weight_b: u64,
+}
+
+impl WeightedCurve {
+    /// Two members of a larger weighted basket, rescaled so their weights sum
+    /// to `WEIGHT_ONE`. Swaps on the result run a-to-b, from `weight_in` to
+    /// `weight_out`.
+    pub fn for_pair(weight_in: u64, weight_out: u64) -> Result<Self> {
+        let total = weight_in
+            .checked_add(weight_out)
+            .filter(|t| *t > 0)
+            .ok_or(AmmError::InvalidCurveParameters)?;
+        let weight_a = super::mul_div(weight_in, WEIGHT_ONE, total)?;
+        Ok(Self {
+            weight_a,
+            weight_b: WEIGHT_ONE - weight_a,
+        })
+    }
+
+    /// `(weight_in, weight_out)` for a swap in the given direction
+    fn weights(&self, a_to_b: bool) -> Result<(u64, u64)> {
+        require!(
+            weights_valid(self.weight_a, self.weight_b),
+            AmmError::InvalidCurveParameters
+        );
+        Ok(if a_to_b {
+            (self.weight_a, self.weight_b)
+        } else {
+            (self.weight_b, self.weight_a)
+        })
+    }
+
+    /// Largest error `pow` can introduce into an amount drawn from `reserve`
+    fn rounding_margin(reserve: u64) -> u64 {
+        ((reserve as u128 * fixed_point::MAX_POW_RELATIVE_ERROR) >> 64) as u64 + 1
+    }
+}
+
+impl SwapCurve for WeightedCurve {
+    fn swap_exact_in(&self, amount_in: u64, reserve_in: u64, reserve_out: u64, a_to_b: bool) -> Result<u64> {
+        // out = Ro * (1 - (Ri / (Ri + in))^(w_in / w_out))
+        let (weight_in, weight_out) = self.weights(a_to_b)?;
+        let new_reserve_in = (reserve_in as u128)
+            .checked_add(amount_in as u128)
+            .filter(|r| *r > 0)
+            .ok_or(AmmError::InvalidSwapCalculation)?;
+        let base = ((reserve_in as u128) << 64) / new_reserve_in;
+        let exponent = ((weight_in as u128) << 64) / weight_out as u128;
+
+        let power = fixed_point::pow_up(base, exponent)?.min(ONE);
+        Ok(((reserve_out as u128 * (ONE - power)) >> 64) as u64)
+    }
+
+    fn swap_exact_out(&self, amount_out: u64, reserve_in: u64, reserve_out: u64, a_to_b: bool) -> Result<u64> {
+        // in = Ri * ((Ro / (Ro - out))^(w_out / w_in) - 1), aimed past
+        // `amount_out` by the error `swap_exact_in` may take off again
+        let target = amount_out.saturating_add(Self::rounding_margin(reserve_out));
+        require!(target < reserve_out, AmmError::InvalidSwapCalculation);
+        let (weight_in, weight_out) = self.weights(a_to_b)?;
+        let base = ((reserve_out as u128) << 64).div_ceil((reserve_out - target) as u128);
+        let exponent = ((weight_out as u128) << 64).div_ceil(weight_in as u128);
+
+        let power = fixed_point::pow_up(base, exponent)?;
+        let estimate = (reserve_in as u128)
+            .checked_mul(power.saturating_sub(ONE))
+            .and_then(|n| u64::try_from(n.div_ceil(ONE)).ok())
+            .ok_or(AmmError::InvalidSwapCalculation)?;
+
+        let margin = Self::rounding_margin(reserve_in);
+        let mut amount_in = estimate;
+        for _ in 0..MAX_ROUNDING_STEPS {
+            if self.swap_exact_in(amount_in, reserve_in, reserve_out, a_to_b)? >= amount_out {
+                return Ok(amount_in);
+            }
+            amount_in = amount_in
+                .checked_add(margin)
+                .ok_or(AmmError::InvalidSwapCalculation)?;
+        }
+        err!(AmmError::CurveDidNotConverge)
+    }
+
+    fn spot_price(&self, reserve_in: u64, reserve_out: u64, a_to_b: bool) -> Result<u128> {
+        // (Ro / w_out) / (Ri / w_in)
+        require!(reserve_in > 0, AmmError::InvalidSwapCalculation);
+        let (weight_in, weight_out) = self.weights(a_to_b)?;
+        let numerator = (U256::from(reserve_out) * U256::from(weight_in)) << 64;
+        let denominator = U256::from(reserve_in) * U256::from(weight_out);
+        u128::try_from(numerator / denominator).map_err(|_| AmmError::InvalidSwapCalculation.into())
+    }
+
+    fn deposit(&self, amount_a: u64, amount_b: u64, reserve_a: u64, reserve_b: u64, lp_supply: u64) -> Result<u64> {
+        proportional_deposit(amount_a, amount_b, reserve_a, reserve_b, lp_supply)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EIGHTY_TWENTY: WeightedCurve = WeightedCurve {
+        weight_a: 800_000_000,
+        weight_b: 200_000_000,
+    };
+
+    /// (amount_in, a_to_b, amount_out) on an 80/20 pool holding 1e9 of A and
+    /// 4e9 of B, rounded down; the same vectors as tests/weighted.ts, net of
+    /// its 25 bps fee
+    const REFERENCE: [(u64, bool, u64); 4] = [
+        (9_975_000, true, 155_698_012),
+        (9_975_000, false, 622_467),
+        (997_500_000, true, 3_748_746_083),
+        (997_500_000, false, 54_140_136),
+    ];
+
+    /// (reserve_in, reserve_out) pairs for the invariant checks
+    const RESERVES: [(u64, u64); 4] = [
+        (1_000_000_000, 4_000_000_000),
+        (4_000_000_000, 1_000_000_000),
+        (1_000, 1_000_000_000_000),
+        (1_000_000_000_000, 1_000),
+    ];
+
+    #[test]
+    fn rejects_weights_off_one_or_below_the_minimum() {
+        assert!(weights_valid(MIN_WEIGHT, WEIGHT_ONE - MIN_WEIGHT));
+        assert!(!weights_valid(MIN_WEIGHT - 1, WEIGHT_ONE - MIN_WEIGHT + 1));
+        assert!(!weights_valid(500_000_000, 499_999_999));
+        assert!(!weights_valid(500_000_000, 500_000_001));
+        assert!(!weights_valid(u64::MAX, 1));
+
+        let curve = WeightedCurve {
+            weight_a: 600_000_000,
+            weight_b: 300_000_000,
+        };
+        let err = curve.swap_exact_in(1_000, 1_000_000, 1_000_000, true).unwrap_err();
+        assert_eq!(err, AmmError::InvalidCurveParameters.into());
+    }
+
+    #[test]
+    fn matches_reference_vectors() {
+        for (amount_in, a_to_b, want) in REFERENCE {
+            let (reserve_in, reserve_out) = if a_to_b {
+                (1_000_000_000, 4_000_000_000)
+            } else {
+                (4_000_000_000, 1_000_000_000)
+            };
+            let got = EIGHTY_TWENTY.swap_exact_in(amount_in, reserve_in, reserve_out, a_to_b).unwrap();
+            assert!(got <= want && want - got <= 1, "in {amount_in} a_to_b {a_to_b}: got {got}, want {want}");
+        }
+    }
+
+    /// For weights in a 4:1 ratio the invariant check is exact in integers:
+    /// (Ro - out) / Ro >= (Ri / (Ri + in))^(w_in / w_out)
+    #[test]
+    fn invariant_never_decreases_on_swap_exact_in() {
+        for (reserve_in, reserve_out) in RESERVES {
+            for amount_in in [1, 1_000, reserve_in / 3, reserve_in, 1_000 * reserve_in] {
+                for a_to_b in [true, false] {
+                    let out = EIGHTY_TWENTY.swap_exact_in(amount_in, reserve_in, reserve_out, a_to_b).unwrap();
+                    assert!(out < reserve_out);
+                    let (ri, ro) = (U256::from(reserve_in), U256::from(reserve_out));
+                    let (new_ri, new_ro) = (ri + U256::from(amount_in), ro - U256::from(out));
+                    let holds = if a_to_b {
+                        new_ro * new_ri.pow(4.into()) >= ro * ri.pow(4.into())
+                    } else {
+                        new_ro.pow(4.into()) * new_ri >= ro.pow(4.into()) * ri
+                    };
+                    assert!(holds, "reserves {reserve_in}/{reserve_out} in {amount_in} a_to_b {a_to_b}: out {out}");
+                }
+            }
+        }
+    }
+}
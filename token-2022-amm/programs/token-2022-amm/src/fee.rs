@@ -0,0 +1,227 @@
+//! Swap fee rates. Pools charge the AMM's static fee unless a dynamic fee is
+//! configured, in which case the rate follows a volatility accumulator fed
+//! by the pool's own price moves and halving every `half_life_slots`.
+
+use anchor_lang::prelude::*;
+
+use crate::curve::mul_div;
+use crate::AmmError;
+
+const BPS: u64 = 10_000;
+/// Highest fee a dynamic-fee pool may charge
+pub const MAX_DYNAMIC_FEE_BPS: u16 = 1_000;
+/// Cap on the accumulator so a single extreme move cannot pin the fee at
+/// its maximum for long
+const MAX_VOLATILITY_BPS: u64 = 1_000_000;
+
+/// `fee / denominator` of the input
+#[derive(Clone, Copy)]
+pub struct FeeRate {
+    pub fee: u64,
+    pub denominator: u64,
+}
+
+impl FeeRate {
+    /// Swap fee charged on `amount_in`, rounded down
+    pub fn fee_on(&self, amount_in: u64) -> Result<u64> {
+        require!(
+            self.fee <= self.denominator,
+            AmmError::InvalidSwapCalculation
+        );
+        mul_div(amount_in, self.fee, self.denominator)
+    }
+
+    /// Smallest `amount_in` that leaves at least `amount_in_after_fee` once
+    /// the fee is taken
+    pub fn gross_up(&self, amount_in_after_fee: u64) -> Result<u64> {
+        let fee_multiplier = self
+            .denominator
+            .checked_sub(self.fee)
+            .filter(|m| *m > 0)
+            .ok_or(AmmError::InvalidSwapCalculation)?;
+        let mut amount_in = u64::try_from(
+            (amount_in_after_fee as u128 * self.denominator as u128)
+                .div_ceil(fee_multiplier as u128),
+        )
+        .map_err(|_| AmmError::InvalidSwapCalculation)?;
+        // The fee rounds down, so a slightly smaller input may still be enough
+        while amount_in > 0 && amount_in - 1 - self.fee_on(amount_in - 1)? >= amount_in_after_fee {
+            amount_in -= 1;
+        }
+        Ok(amount_in)
+    }
+
+    /// The rate in basis points, rounded down
+    pub fn bps(&self) -> u64 {
+        mul_div(self.fee, BPS, self.denominator).unwrap_or(0)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct DynamicFee {
+    pub min_fee_bps: u16,
+    pub max_fee_bps: u16,        // Zero while the pool charges the AMM's static fee
+    pub fee_per_volatility: u16, // Extra fee bps per 100 bps of accumulated volatility
+    pub half_life_slots: u64,
+    pub volatility_bps: u64,     // Sum of recent price moves, decaying
+    pub last_update_slot: u64,
+}
+
+impl DynamicFee {
+    pub fn enabled(&self) -> bool {
+        self.max_fee_bps > 0
+    }
+
+    /// A new configuration keeping the current accumulator
+    pub fn configure(
+        &mut self,
+        min_fee_bps: u16,
+        max_fee_bps: u16,
+        fee_per_volatility: u16,
+        half_life_slots: u64,
+    ) -> Result<()> {
+        require!(
+            max_fee_bps == 0
+                || (min_fee_bps <= max_fee_bps && max_fee_bps <= MAX_DYNAMIC_FEE_BPS && half_life_slots > 0),
+            AmmError::InvalidCurveParameters
+        );
+        self.min_fee_bps = min_fee_bps;
+        self.max_fee_bps = max_fee_bps;
+        self.fee_per_volatility = fee_per_volatility;
+        self.half_life_slots = half_life_slots;
+        Ok(())
+    }
+
+    /// The accumulator as of `slot`, halved once per elapsed half-life
+    pub fn volatility_at(&self, slot: u64) -> u64 {
+        let mut decayed = *self;
+        decayed.decay_to(slot);
+        decayed.volatility_bps
+    }
+
+    /// Apply whole elapsed half-lives. Partial ones carry over, so frequent
+    /// updates do not hold off the decay.
+    fn decay_to(&mut self, slot: u64) {
+        let half_life = self.half_life_slots.max(1);
+        let halvings = slot.saturating_sub(self.last_update_slot) / half_life;
+        self.volatility_bps = u32::try_from(halvings)
+            .ok()
+            .and_then(|h| self.volatility_bps.checked_shr(h))
+            .unwrap_or(0);
+        self.last_update_slot += halvings * half_life;
+    }
+
+    pub fn fee_rate(&self, slot: u64) -> FeeRate {
+        let variable = self.volatility_at(slot).saturating_mul(self.fee_per_volatility as u64) / 100;
+        let fee = variable
+            .saturating_add(self.min_fee_bps as u64)
+            .min(self.max_fee_bps as u64);
+        FeeRate {
+            fee,
+            denominator: BPS,
+        }
+    }
+
+    /// Fold a move from `price_before` to `price_after` into the accumulator
+    pub fn record_move(&mut self, slot: u64, price_before: u128, price_after: u128) {
+        let move_bps = price_move_bps(price_before, price_after);
+        self.decay_to(slot);
+        self.volatility_bps = self
+            .volatility_bps
+            .saturating_add(move_bps)
+            .min(MAX_VOLATILITY_BPS);
+    }
+}
+
+/// Size of a move from `price_before` to `price_after` in basis points of
+/// `price_before`, or zero without a starting price
+pub(crate) fn price_move_bps(price_before: u128, price_after: u128) -> u64 {
+    if price_before == 0 {
+        return 0;
+    }
+    let change = price_before.abs_diff(price_after);
+    u64::try_from(change.saturating_mul(BPS as u128) / price_before).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ONE: u128 = 1 << 64;
+
+    fn dynamic(volatility_bps: u64) -> DynamicFee {
+        DynamicFee {
+            min_fee_bps: 5,
+            max_fee_bps: 200,
+            fee_per_volatility: 10,
+            half_life_slots: 100,
+            volatility_bps,
+            last_update_slot: 0,
+        }
+    }
+
+    #[test]
+    fn accumulator_halves_once_per_half_life() {
+        let fee = dynamic(800);
+        assert_eq!(fee.volatility_at(99), 800);
+        assert_eq!(fee.volatility_at(100), 400);
+        assert_eq!(fee.volatility_at(250), 200);
+        assert_eq!(fee.volatility_at(100 * 64), 0);
+        assert_eq!(fee.volatility_at(u64::MAX), 0);
+    }
+
+    #[test]
+    fn partial_half_lives_carry_over() {
+        // Updates every 60 slots still halve the accumulator by slot 100
+        let mut fee = dynamic(800);
+        fee.record_move(60, ONE, ONE);
+        assert_eq!((fee.volatility_bps, fee.last_update_slot), (800, 0));
+        fee.record_move(120, ONE, ONE);
+        assert_eq!((fee.volatility_bps, fee.last_update_slot), (400, 100));
+    }
+
+    #[test]
+    fn record_move_adds_the_move_and_caps_the_accumulator() {
+        let mut fee = dynamic(0);
+        fee.record_move(0, 100 * ONE, 101 * ONE);
+        assert_eq!(fee.volatility_bps, 100);
+        fee.record_move(0, 101 * ONE, 100 * ONE);
+        assert_eq!(fee.volatility_bps, 199);
+        fee.record_move(0, ONE, u128::MAX);
+        assert_eq!(fee.volatility_bps, MAX_VOLATILITY_BPS);
+        assert_eq!(price_move_bps(0, ONE), 0);
+    }
+
+    #[test]
+    fn rate_runs_from_the_minimum_to_the_maximum() {
+        assert_eq!(dynamic(0).fee_rate(0).fee, 5);
+        // 5 + 500 * 10 / 100
+        assert_eq!(dynamic(500).fee_rate(0).fee, 55);
+        assert_eq!(dynamic(MAX_VOLATILITY_BPS).fee_rate(0).fee, 200);
+        assert_eq!(dynamic(0).fee_rate(0).denominator, BPS);
+    }
+
+    #[test]
+    fn configure_clamps_the_maximum_fee() {
+        let mut fee = dynamic(300);
+        assert!(fee.configure(0, MAX_DYNAMIC_FEE_BPS, 10, 1).is_ok());
+        assert_eq!(fee.volatility_bps, 300);
+        for (min, max, half_life) in [(0, MAX_DYNAMIC_FEE_BPS + 1, 1), (20, 10, 1), (0, 10, 0)] {
+            let err = fee.configure(min, max, 10, half_life).unwrap_err();
+            assert_eq!(err, AmmError::InvalidCurveParameters.into());
+        }
+        // A zero maximum switches back to the static fee
+        assert!(fee.configure(0, 0, 0, 0).is_ok());
+        assert!(!fee.enabled());
+    }
+
+    #[test]
+    fn gross_up_inverts_fee_on() {
+        let rate = FeeRate { fee: 25, denominator: BPS };
+        for net in [0, 1, 997, 1_000_000, u64::MAX / BPS] {
+            let gross = rate.gross_up(net).unwrap();
+            assert!(gross - rate.fee_on(gross).unwrap() >= net);
+            assert!(gross == 0 || gross - 1 - rate.fee_on(gross - 1).unwrap() < net);
+        }
+    }
+}
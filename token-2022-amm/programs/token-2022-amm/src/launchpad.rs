@@ -0,0 +1,705 @@
+//! Bonding-curve launchpad. A new mint is sold along a curve against a quote
+//! token, with proceeds held in escrow; once the market cap reaches the
+//! launch's threshold, `graduate_launch` seeds a constant-product pool with
+//! the reserved tokens and the proceeds. The pool's opening LP supply is
+//! never minted, so that liquidity stays locked for good.
+//!
+//! The pool PDA of a launch's pair is reserved for its graduation:
+//! `create_pool` refuses that pair while the launch has not graduated, and
+//! `create_launch` refuses a pair whose pool already exists. Only the mint's
+//! own authority, or its transfer-hook authority, may launch it.
+
+use anchor_lang::prelude::*;
+use anchor_spl::token_2022::Token2022;
+use anchor_spl::token_interface::{Mint, TokenAccount};
+use spl_token_2022::extension::transfer_hook::TransferHook;
+use spl_token_2022::extension::{BaseStateWithExtensions, StateWithExtensions};
+use spl_token_2022::onchain::invoke_transfer_checked;
+
+use crate::curve::fixed_point::{self, ONE};
+use crate::curve::U256;
+use crate::{
+    seed_pool_accounts, Amm, AmmError, CurveParams, Observations, Pool, PoolCreated, PoolStats, LP_DECIMALS,
+};
+
+/// ln(2) in Q64.64
+const LN_2: u128 = 12_786_308_645_202_655_660;
+
+/// Price of one base unit of the launch token, in quote base units (Q64.64),
+/// as a function of the number of units sold
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, InitSpace)]
+pub enum BondingCurve {
+    /// base_price + slope * sold
+    Linear { base_price: u128, slope: u128 },
+    /// base_price * 2^(sold / doubling_supply)
+    Exponential { base_price: u128, doubling_supply: u64 },
+}
+
+impl BondingCurve {
+    pub fn validate(&self) -> Result<()> {
+        let valid = match self {
+            BondingCurve::Linear { base_price, slope } => *base_price > 0 || *slope > 0,
+            BondingCurve::Exponential {
+                base_price,
+                doubling_supply,
+            } => *base_price > 0 && *doubling_supply > 0,
+        };
+        require!(valid, AmmError::InvalidCurveParameters);
+        Ok(())
+    }
+
+    /// Q64.64 price at `sold` units
+    pub fn price(&self, sold: u64) -> Result<u128> {
+        match *self {
+            BondingCurve::Linear { base_price, slope } => slope
+                .checked_mul(sold as u128)
+                .and_then(|p| p.checked_add(base_price))
+                .ok_or(AmmError::InvalidSwapCalculation.into()),
+            BondingCurve::Exponential {
+                base_price,
+                doubling_supply,
+            } => fixed_point::mul(base_price, Self::growth(sold, doubling_supply)?),
+        }
+    }
+
+    /// Quote units for moving the supply sold from `from` to `to`: the area
+    /// under the price curve, rounded up when buying and down when selling
+    pub fn cost(&self, from: u64, to: u64, round_up: bool) -> Result<u64> {
+        let area = match *self {
+            // base * (to - from) + slope * (to^2 - from^2) / 2
+            BondingCurve::Linear { base_price, slope } => {
+                let (from, to) = (U256::from(from), U256::from(to));
+                U256::from(slope)
+                    .checked_mul(to * to - from * from)
+                    .and_then(|a| (U256::from(base_price) * (to - from)).checked_add(a / 2))
+                    .ok_or(AmmError::InvalidSwapCalculation)?
+            }
+            // base * d / ln 2 * (2^(to / d) - 2^(from / d))
+            BondingCurve::Exponential {
+                base_price,
+                doubling_supply,
+            } => {
+                let growth = Self::growth(to, doubling_supply)? - Self::growth(from, doubling_supply)?;
+                // exp2 rounds down; one unit either way keeps the escrow solvent
+                let growth = if round_up {
+                    U256::from(growth) + 1
+                } else {
+                    U256::from(growth.saturating_sub(1))
+                };
+                U256::from(base_price)
+                    .checked_mul(growth)
+                    .and_then(|a| a.checked_mul(U256::from(doubling_supply)))
+                    .ok_or(AmmError::InvalidSwapCalculation)?
+                    / U256::from(LN_2)
+            }
+        };
+        let one = U256::from(ONE);
+        let units = if round_up { (area + one - U256::one()) / one } else { area / one };
+        u64::try_from(units).map_err(|_| AmmError::InvalidSwapCalculation.into())
+    }
+
+    /// 2^(sold / doubling_supply) in Q64.64
+    fn growth(sold: u64, doubling_supply: u64) -> Result<u128> {
+        fixed_point::exp2((((sold as u128) << 64) / doubling_supply as u128) as i128)
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Launch {
+    pub creator: Pubkey,
+    pub token_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    pub token_vault: Pubkey,   // Escrow of unsold and reserved launch tokens
+    pub quote_vault: Pubkey,   // Escrow of sale proceeds
+    pub curve: BondingCurve,
+    pub sale_supply: u64,      // Units sold along the curve
+    pub liquidity_supply: u64, // Units reserved for the graduation pool
+    pub tokens_sold: u64,
+    pub quote_raised: u64,
+    pub graduation_market_cap: u64, // In quote units, at the curve's spot price
+    pub complete: bool,        // Threshold reached; trading on the curve has stopped
+    pub pool: Pubkey,          // Set on graduation
+    pub bump: u8,
+}
+
+impl Launch {
+    /// Market cap in quote units at the current curve price
+    fn market_cap(&self, total_supply: u64) -> Result<u128> {
+        let price = self.curve.price(self.tokens_sold)?;
+        Ok(((U256::from(price) * U256::from(total_supply)) >> 64)
+            .try_into()
+            .unwrap_or(u128::MAX))
+    }
+}
+
+pub fn create<'info>(
+    ctx: Context<'_, '_, '_, 'info, CreateLaunch<'info>>,
+    curve: BondingCurve,
+    sale_supply: u64,
+    liquidity_supply: u64,
+    graduation_market_cap: u64,
+) -> Result<()> {
+    curve.validate()?;
+    require!(
+        is_mint_admin(&ctx.accounts.token_mint, &ctx.accounts.creator.key())?,
+        AmmError::NotMintAuthority
+    );
+    require!(
+        sale_supply > 0 && liquidity_supply > 0,
+        AmmError::InvalidCurveParameters
+    );
+    let deposit = sale_supply
+        .checked_add(liquidity_supply)
+        .ok_or(AmmError::InvalidCurveParameters)?;
+
+    let mut all_accounts = vec![
+        ctx.accounts.creator.to_account_info(),
+        ctx.accounts.creator_token_account.to_account_info(),
+        ctx.accounts.token_mint.to_account_info(),
+        ctx.accounts.token_vault.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+    ];
+    all_accounts.extend(ctx.remaining_accounts.iter().cloned());
+
+    invoke_transfer_checked(
+        &ctx.accounts.token_program.key(),
+        ctx.accounts.creator_token_account.to_account_info(),
+        ctx.accounts.token_mint.to_account_info(),
+        ctx.accounts.token_vault.to_account_info(),
+        ctx.accounts.creator.to_account_info(),
+        &all_accounts,
+        deposit,
+        ctx.accounts.token_mint.decimals,
+        &[],
+    )?;
+
+    let launch = &mut ctx.accounts.launch;
+    launch.creator = ctx.accounts.creator.key();
+    launch.token_mint = ctx.accounts.token_mint.key();
+    launch.quote_mint = ctx.accounts.quote_mint.key();
+    launch.token_vault = ctx.accounts.token_vault.key();
+    launch.quote_vault = ctx.accounts.quote_vault.key();
+    launch.curve = curve;
+    launch.sale_supply = sale_supply;
+    launch.liquidity_supply = liquidity_supply;
+    launch.graduation_market_cap = graduation_market_cap;
+    launch.bump = ctx.bumps.launch;
+
+    emit_cpi!(LaunchCreated {
+        launch: launch.key(),
+        creator: launch.creator,
+        token_mint: launch.token_mint,
+        quote_mint: launch.quote_mint,
+        sale_supply,
+        liquidity_supply,
+        graduation_market_cap,
+    });
+    Ok(())
+}
+
+pub fn buy<'info>(
+    ctx: Context<'_, '_, '_, 'info, LaunchTrade<'info>>,
+    token_amount: u64,
+    maximum_quote_in: u64,
+) -> Result<()> {
+    let launch = &ctx.accounts.launch;
+    let tokens_sold = launch
+        .tokens_sold
+        .checked_add(token_amount)
+        .filter(|sold| *sold <= launch.sale_supply)
+        .ok_or(AmmError::InsufficientLaunchSupply)?;
+    let quote_in = launch.curve.cost(launch.tokens_sold, tokens_sold, true)?;
+    require!(quote_in <= maximum_quote_in, AmmError::ExcessiveInputAmount);
+
+    let all_accounts = trade_accounts(&ctx);
+    invoke_transfer_checked(
+        &ctx.accounts.token_program.key(),
+        ctx.accounts.user_quote_account.to_account_info(),
+        ctx.accounts.quote_mint.to_account_info(),
+        ctx.accounts.quote_vault.to_account_info(),
+        ctx.accounts.user.to_account_info(),
+        &all_accounts,
+        quote_in,
+        ctx.accounts.quote_mint.decimals,
+        &[],
+    )?;
+    invoke_transfer_checked(
+        &ctx.accounts.token_program.key(),
+        ctx.accounts.token_vault.to_account_info(),
+        ctx.accounts.token_mint.to_account_info(),
+        ctx.accounts.user_token_account.to_account_info(),
+        ctx.accounts.launch.to_account_info(),
+        &all_accounts,
+        token_amount,
+        ctx.accounts.token_mint.decimals,
+        &[&launch_seeds(&ctx.accounts.launch)],
+    )?;
+
+    let launch = &mut ctx.accounts.launch;
+    launch.tokens_sold = tokens_sold;
+    launch.quote_raised = launch
+        .quote_raised
+        .checked_add(quote_in)
+        .ok_or(AmmError::InvalidSwapCalculation)?;
+    launch.complete = tokens_sold == launch.sale_supply
+        || launch.market_cap(ctx.accounts.token_mint.supply)? >= launch.graduation_market_cap as u128;
+
+    emit_cpi!(LaunchTraded {
+        launch: launch.key(),
+        user: ctx.accounts.user.key(),
+        is_buy: true,
+        token_amount,
+        quote_amount: quote_in,
+        tokens_sold: launch.tokens_sold,
+        quote_raised: launch.quote_raised,
+        complete: launch.complete,
+    });
+    Ok(())
+}
+
+pub fn sell<'info>(
+    ctx: Context<'_, '_, '_, 'info, LaunchTrade<'info>>,
+    token_amount: u64,
+    minimum_quote_out: u64,
+) -> Result<()> {
+    let launch = &ctx.accounts.launch;
+    let tokens_sold = launch
+        .tokens_sold
+        .checked_sub(token_amount)
+        .ok_or(AmmError::InsufficientLaunchSupply)?;
+    let quote_out = launch
+        .curve
+        .cost(tokens_sold, launch.tokens_sold, false)?
+        .min(launch.quote_raised);
+    require!(quote_out >= minimum_quote_out, AmmError::InsufficientOutputAmount);
+
+    let all_accounts = trade_accounts(&ctx);
+    invoke_transfer_checked(
+        &ctx.accounts.token_program.key(),
+        ctx.accounts.user_token_account.to_account_info(),
+        ctx.accounts.token_mint.to_account_info(),
+        ctx.accounts.token_vault.to_account_info(),
+        ctx.accounts.user.to_account_info(),
+        &all_accounts,
+        token_amount,
+        ctx.accounts.token_mint.decimals,
+        &[],
+    )?;
+    invoke_transfer_checked(
+        &ctx.accounts.token_program.key(),
+        ctx.accounts.quote_vault.to_account_info(),
+        ctx.accounts.quote_mint.to_account_info(),
+        ctx.accounts.user_quote_account.to_account_info(),
+        ctx.accounts.launch.to_account_info(),
+        &all_accounts,
+        quote_out,
+        ctx.accounts.quote_mint.decimals,
+        &[&launch_seeds(&ctx.accounts.launch)],
+    )?;
+
+    let launch = &mut ctx.accounts.launch;
+    launch.tokens_sold = tokens_sold;
+    launch.quote_raised -= quote_out;
+
+    emit_cpi!(LaunchTraded {
+        launch: launch.key(),
+        user: ctx.accounts.user.key(),
+        is_buy: false,
+        token_amount,
+        quote_amount: quote_out,
+        tokens_sold: launch.tokens_sold,
+        quote_raised: launch.quote_raised,
+        complete: false,
+    });
+    Ok(())
+}
+
+pub fn graduate<'info>(ctx: Context<'_, '_, '_, 'info, GraduateLaunch<'info>>) -> Result<()> {
+    let launch = &ctx.accounts.launch;
+    let (token_amount, quote_amount) = (launch.liquidity_supply, launch.quote_raised);
+
+    let mut all_accounts = vec![
+        ctx.accounts.launch.to_account_info(),
+        ctx.accounts.token_mint.to_account_info(),
+        ctx.accounts.quote_mint.to_account_info(),
+        ctx.accounts.token_vault.to_account_info(),
+        ctx.accounts.quote_vault.to_account_info(),
+        ctx.accounts.pool_token_vault.to_account_info(),
+        ctx.accounts.pool_quote_vault.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+    ];
+    all_accounts.extend(ctx.remaining_accounts.iter().cloned());
+
+    let seeds = launch_seeds(launch);
+    invoke_transfer_checked(
+        &ctx.accounts.token_program.key(),
+        ctx.accounts.token_vault.to_account_info(),
+        ctx.accounts.token_mint.to_account_info(),
+        ctx.accounts.pool_token_vault.to_account_info(),
+        ctx.accounts.launch.to_account_info(),
+        &all_accounts,
+        token_amount,
+        ctx.accounts.token_mint.decimals,
+        &[&seeds],
+    )?;
+    invoke_transfer_checked(
+        &ctx.accounts.token_program.key(),
+        ctx.accounts.quote_vault.to_account_info(),
+        ctx.accounts.quote_mint.to_account_info(),
+        ctx.accounts.pool_quote_vault.to_account_info(),
+        ctx.accounts.launch.to_account_info(),
+        &all_accounts,
+        quote_amount,
+        ctx.accounts.quote_mint.decimals,
+        &[&seeds],
+    )?;
+
+    // The launch owns the pool, and the opening LP supply has no tokens
+    let clock = Clock::get()?;
+    let pool = &mut ctx.accounts.pool;
+    pool.open(
+        (ctx.accounts.token_mint.key(), ctx.accounts.quote_mint.key()),
+        (ctx.accounts.pool_token_vault.key(), ctx.accounts.pool_quote_vault.key()),
+        (token_amount, quote_amount),
+        CurveParams::ConstantProduct,
+        ctx.accounts.launch.key(),
+        &clock,
+    )?;
+    pool.lp_mint = ctx.accounts.lp_mint.key();
+    pool.bump = ctx.bumps.pool;
+    seed_pool_accounts(pool.key(), clock.slot, &mut ctx.accounts.observations, &mut ctx.accounts.stats);
+    ctx.accounts.launch.pool = pool.key();
+
+    emit_cpi!(PoolCreated {
+        pool: pool.key(),
+        creator: ctx.accounts.launch.key(),
+        token_a_mint: pool.token_a_mint,
+        token_b_mint: pool.token_b_mint,
+        token_a_amount: pool.token_a_amount,
+        token_b_amount: pool.token_b_amount,
+        lp_supply: pool.lp_supply,
+    });
+    emit_cpi!(LaunchGraduated {
+        launch: ctx.accounts.launch.key(),
+        pool: pool.key(),
+        token_amount,
+        quote_amount,
+        locked_lp_supply: pool.lp_supply,
+    });
+    Ok(())
+}
+
+/// Fails while `launch`, the launch PDA of a mint, holds a launch against
+/// `other_mint` that has not graduated. Pairs with any other mint are free.
+pub fn require_graduated(launch: &AccountInfo, other_mint: &Pubkey) -> Result<()> {
+    if launch.owner != &crate::ID {
+        return Ok(());
+    }
+    let launch = Launch::try_deserialize(&mut &launch.try_borrow_data()?[..])?;
+    require!(
+        launch.quote_mint != *other_mint || launch.pool != Pubkey::default(),
+        AmmError::LaunchInProgress
+    );
+    Ok(())
+}
+
+/// Whether `signer` is the mint's authority or its transfer-hook authority
+fn is_mint_admin(mint: &InterfaceAccount<Mint>, signer: &Pubkey) -> Result<bool> {
+    let info = mint.to_account_info();
+    let data = info.try_borrow_data()?;
+    let state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+    let hook_authority = state
+        .get_extension::<TransferHook>()
+        .ok()
+        .and_then(|hook| Option::<Pubkey>::from(hook.authority));
+    Ok(hook_authority.as_ref() == Some(signer)
+        || Option::<Pubkey>::from(mint.mint_authority).as_ref() == Some(signer))
+}
+
+fn launch_seeds(launch: &Launch) -> [&[u8]; 3] {
+    [b"launch", launch.token_mint.as_ref(), std::slice::from_ref(&launch.bump)]
+}
+
+fn trade_accounts<'info>(ctx: &Context<'_, '_, '_, 'info, LaunchTrade<'info>>) -> Vec<AccountInfo<'info>> {
+    let mut all_accounts = vec![
+        ctx.accounts.launch.to_account_info(),
+        ctx.accounts.user.to_account_info(),
+        ctx.accounts.user_token_account.to_account_info(),
+        ctx.accounts.user_quote_account.to_account_info(),
+        ctx.accounts.token_mint.to_account_info(),
+        ctx.accounts.quote_mint.to_account_info(),
+        ctx.accounts.token_vault.to_account_info(),
+        ctx.accounts.quote_vault.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+    ];
+    all_accounts.extend(ctx.remaining_accounts.iter().cloned());
+    all_accounts
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CreateLaunch<'info> {
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + Launch::INIT_SPACE,
+        seeds = [b"launch", token_mint.key().as_ref()],
+        bump
+    )]
+    pub launch: Account<'info, Launch>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+    #[account(
+        mut,
+        token::mint = token_mint,
+        token::authority = creator
+    )]
+    pub creator_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_mint: InterfaceAccount<'info, Mint>,
+    #[account(constraint = quote_mint.key() != token_mint.key() @ AmmError::InvalidTokenPair)]
+    pub quote_mint: InterfaceAccount<'info, Mint>,
+
+    /// CHECK: Pool graduation will create, which must not exist yet
+    #[account(
+        seeds = [
+            b"pool",
+            std::cmp::min(token_mint.key(), quote_mint.key()).as_ref(),
+            std::cmp::max(token_mint.key(), quote_mint.key()).as_ref()
+        ],
+        bump,
+        constraint = pool.data_is_empty() @ AmmError::PoolAlreadyExists
+    )]
+    pub pool: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        token::mint = token_mint,
+        token::authority = launch
+    )]
+    pub token_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        token::mint = quote_mint,
+        token::authority = launch
+    )]
+    pub quote_vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token2022>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct LaunchTrade<'info> {
+    #[account(
+        mut,
+        seeds = [b"launch", token_mint.key().as_ref()],
+        bump = launch.bump,
+        has_one = quote_mint @ AmmError::InvalidTokenPair,
+        has_one = token_vault,
+        has_one = quote_vault,
+        constraint = !launch.complete @ AmmError::LaunchComplete
+    )]
+    pub launch: Account<'info, Launch>,
+
+    pub user: Signer<'info>,
+    #[account(mut)]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub user_quote_account: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_mint: InterfaceAccount<'info, Mint>,
+    pub quote_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut)]
+    pub token_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub quote_vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token2022>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct GraduateLaunch<'info> {
+    #[account(
+        mut,
+        seeds = [b"launch", launch.token_mint.as_ref()],
+        bump = launch.bump,
+        has_one = token_mint @ AmmError::InvalidTokenPair,
+        has_one = quote_mint @ AmmError::InvalidTokenPair,
+        has_one = token_vault,
+        has_one = quote_vault,
+        constraint = launch.complete @ AmmError::LaunchNotComplete,
+        constraint = launch.pool == Pubkey::default() @ AmmError::AlreadyGraduated
+    )]
+    pub launch: Box<Account<'info, Launch>>,
+
+    /// Anyone may graduate a completed launch; they pay the new accounts' rent
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Pool::INIT_SPACE,
+        seeds = [
+            b"pool",
+            std::cmp::min(token_mint.key(), quote_mint.key()).as_ref(),
+            std::cmp::max(token_mint.key(), quote_mint.key()).as_ref()
+        ],
+        bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+    #[account(seeds = [b"amm"], bump = amm.bump)]
+    pub amm: Box<Account<'info, Amm>>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Observations::INIT_SPACE,
+        seeds = [b"observations", pool.key().as_ref()],
+        bump
+    )]
+    pub observations: Box<Account<'info, Observations>>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + PoolStats::INIT_SPACE,
+        seeds = [b"pool-stats", pool.key().as_ref()],
+        bump
+    )]
+    pub stats: Box<Account<'info, PoolStats>>,
+
+    pub token_mint: Box<InterfaceAccount<'info, Mint>>,
+    pub quote_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub token_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut)]
+    pub quote_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        token::mint = token_mint,
+        token::authority = amm
+    )]
+    pub pool_token_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::mint = quote_mint,
+        token::authority = amm
+    )]
+    pub pool_quote_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"lp-mint", pool.key().as_ref()],
+        bump,
+        mint::decimals = LP_DECIMALS,
+        mint::authority = pool
+    )]
+    pub lp_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token2022>,
+}
+
+#[event]
+pub struct LaunchCreated {
+    pub launch: Pubkey,
+    pub creator: Pubkey,
+    pub token_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    pub sale_supply: u64,
+    pub liquidity_supply: u64,
+    pub graduation_market_cap: u64,
+}
+
+#[event]
+pub struct LaunchTraded {
+    pub launch: Pubkey,
+    pub user: Pubkey,
+    pub is_buy: bool,
+    pub token_amount: u64,
+    pub quote_amount: u64,
+    pub tokens_sold: u64,
+    pub quote_raised: u64,
+    pub complete: bool,
+}
+
+#[event]
+pub struct LaunchGraduated {
+    pub launch: Pubkey,
+    pub pool: Pubkey,
+    pub token_amount: u64,
+    pub quote_amount: u64,
+    pub locked_lp_supply: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINEAR: BondingCurve = BondingCurve::Linear {
+        base_price: ONE / 10,
+        slope: ONE / 1_000,
+    };
+    const EXPONENTIAL: BondingCurve = BondingCurve::Exponential {
+        base_price: ONE,
+        doubling_supply: 1_000,
+    };
+
+    #[test]
+    fn prices_along_each_curve() {
+        assert_eq!(LINEAR.price(0).unwrap(), ONE / 10);
+        assert_eq!(LINEAR.price(1_000).unwrap(), ONE / 10 + ONE / 1_000 * 1_000);
+        assert_eq!(EXPONENTIAL.price(0).unwrap(), ONE);
+        assert_eq!(EXPONENTIAL.price(1_000).unwrap(), 2 * ONE);
+        assert_eq!(EXPONENTIAL.price(3_000).unwrap(), 8 * ONE);
+    }
+
+    #[test]
+    fn cost_rounds_up_to_buy_and_down_to_sell() {
+        // Area 9/2 under a price of `sold` from 0 to 3
+        let curve = BondingCurve::Linear { base_price: 0, slope: ONE };
+        assert_eq!(curve.cost(0, 3, true).unwrap(), 5);
+        assert_eq!(curve.cost(0, 3, false).unwrap(), 4);
+
+        // 1000 / ln 2 = 1442.69...
+        assert_eq!(EXPONENTIAL.cost(0, 1_000, true).unwrap(), 1_443);
+        assert_eq!(EXPONENTIAL.cost(0, 1_000, false).unwrap(), 1_442);
+
+        // Exact areas need no rounding either way
+        let flat = BondingCurve::Linear { base_price: ONE, slope: 0 };
+        assert_eq!(flat.cost(7, 107, true).unwrap(), 100);
+        assert_eq!(flat.cost(7, 107, false).unwrap(), 100);
+    }
+
+    #[test]
+    fn buying_in_pieces_never_pays_less_than_selling_at_once() {
+        for curve in [LINEAR, EXPONENTIAL] {
+            for split in [1, 10, 333, 999] {
+                let bought = curve.cost(0, split, true).unwrap() + curve.cost(split, 1_000, true).unwrap();
+                assert!(bought >= curve.cost(0, 1_000, false).unwrap(), "{curve:?} split at {split}");
+            }
+        }
+    }
+
+    #[test]
+    fn overflow_is_an_error() {
+        let steep = BondingCurve::Linear {
+            base_price: 0,
+            slope: u128::MAX,
+        };
+        assert_eq!(steep.price(2).unwrap_err(), AmmError::InvalidSwapCalculation.into());
+        assert_eq!(steep.cost(0, u64::MAX, true).unwrap_err(), AmmError::InvalidSwapCalculation.into());
+
+        let expensive = BondingCurve::Exponential {
+            base_price: u128::MAX,
+            doubling_supply: u64::MAX,
+        };
+        assert_eq!(expensive.cost(0, u64::MAX, true).unwrap_err(), AmmError::InvalidSwapCalculation.into());
+    }
+}
@@ -1,10 +1,26 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token_2022::{
-    Token2022, TransferChecked, transfer_checked,
-};
+use anchor_spl::token_2022::Token2022;
 use anchor_spl::token_interface::{Mint, TokenAccount};
 use spl_token_2022::onchain::invoke_transfer_checked;
 
+pub mod basket;
+pub mod breaker;
+pub mod concentrated;
+pub mod curve;
+pub mod fee;
+pub mod launchpad;
+pub mod pmm;
+
+pub use basket::*;
+pub use breaker::CircuitBreaker;
+pub use concentrated::*;
+pub use curve::CurveParams;
+pub use fee::DynamicFee;
+pub use launchpad::*;
+pub use pmm::*;
+use curve::SwapCurve;
+use fee::FeeRate;
+
 declare_id!("6vL4UPFu43VpdcD8jBs8F4AvtaMtDxkEWMNpZJZtueYM");
 
 #[program]
@@ -54,13 +70,25 @@ pub mod token_2022_amm {
     }
 
     /// Create a new liquidity pool (simplified)
-    pub fn create_pool(
-        ctx: Context<CreatePool>,
+    pub fn create_pool<'info>(
+        ctx: Context<'_, '_, '_, 'info, CreatePool<'info>>,
         initial_token_a_amount: u64,
         initial_token_b_amount: u64,
+        curve: CurveParams,
     ) -> Result<()> {
+        curve.validate()?;
+        if matches!(curve, CurveParams::Stable { .. }) {
+            require!(
+                ctx.accounts.token_a_mint.decimals == ctx.accounts.token_b_mint.decimals,
+                AmmError::MismatchedDecimals
+            );
+        }
+        // The pair's pool belongs to a launch until it graduates
+        launchpad::require_graduated(&ctx.accounts.token_a_launch, &ctx.accounts.token_b_mint.key())?;
+        launchpad::require_graduated(&ctx.accounts.token_b_launch, &ctx.accounts.token_a_mint.key())?;
+
         // Prepare all accounts for transfer hook resolution
-        let all_accounts = vec![
+        let mut all_accounts = vec![
             ctx.accounts.pool.to_account_info(),
             ctx.accounts.amm.to_account_info(),
             ctx.accounts.user.to_account_info(),
@@ -76,6 +104,8 @@ pub mod token_2022_amm {
             ctx.accounts.system_program.to_account_info(),
             ctx.accounts.token_program.to_account_info(),
         ];
+        // Hooks that need additional accounts get them through remaining accounts
+        all_accounts.extend(ctx.remaining_accounts.iter().cloned());
         
         // Transfer token A using transfer hook compatible function
         invoke_transfer_checked(
@@ -105,30 +135,44 @@ pub mod token_2022_amm {
 
         // Initialize pool state with consistent token ordering
         let pool = &mut ctx.accounts.pool;
-        let (token_min, token_max, amount_min, amount_max) = if ctx.accounts.token_a_mint.key() < ctx.accounts.token_b_mint.key() {
-            (ctx.accounts.token_a_mint.key(), ctx.accounts.token_b_mint.key(), initial_token_a_amount, initial_token_b_amount)
-        } else {
-            (ctx.accounts.token_b_mint.key(), ctx.accounts.token_a_mint.key(), initial_token_b_amount, initial_token_a_amount)
-        };
-        
-        pool.token_a_mint = token_min;
-        pool.token_b_mint = token_max;
-        pool.token_a_amount = amount_min;
-        pool.token_b_amount = amount_max;
-        pool.lp_supply = initial_token_a_amount; // Simplified LP calculation
+        let clock = Clock::get()?;
+        pool.open(
+            (ctx.accounts.token_a_mint.key(), ctx.accounts.token_b_mint.key()),
+            (ctx.accounts.token_a_vault.key(), ctx.accounts.token_b_vault.key()),
+            (initial_token_a_amount, initial_token_b_amount),
+            curve,
+            ctx.accounts.user.key(),
+            &clock,
+        )?;
+        pool.lp_mint = ctx.accounts.lp_mint.key();
+        pool.bump = ctx.bumps.pool;
+        let slot = clock.slot;
+        seed_pool_accounts(pool.key(), slot, &mut ctx.accounts.observations, &mut ctx.accounts.stats);
+
+        let stats = &mut ctx.accounts.stats;
+        stats.record_liquidity_provider(&mut ctx.accounts.liquidity_provider, pool.key(), ctx.accounts.user.key(), slot);
 
+        emit_cpi!(PoolCreated {
+            pool: pool.key(),
+            creator: ctx.accounts.user.key(),
+            token_a_mint: pool.token_a_mint,
+            token_b_mint: pool.token_b_mint,
+            token_a_amount: pool.token_a_amount,
+            token_b_amount: pool.token_b_amount,
+            lp_supply: pool.lp_supply,
+        });
         msg!("Pool created with {} token A and {} token B", initial_token_a_amount, initial_token_b_amount);
         Ok(())
     }
 
     /// Swap tokens with transfer hook support
-    pub fn swap(
-        ctx: Context<Swap>,
+    pub fn swap<'info>(
+        ctx: Context<'_, '_, '_, 'info, Swap<'info>>,
         amount_in: u64,
         minimum_amount_out: u64,
     ) -> Result<()> {
         // Prepare all accounts for transfer hook resolution (before mutable borrow)
-        let all_accounts = vec![
+        let mut all_accounts = vec![
             ctx.accounts.pool.to_account_info(),
             ctx.accounts.amm.to_account_info(),
             ctx.accounts.user.to_account_info(),
@@ -143,22 +187,28 @@ pub mod token_2022_amm {
             ctx.accounts.transfer_hook_program.to_account_info(),
             ctx.accounts.token_program.to_account_info(),
         ];
+        all_accounts.extend(ctx.remaining_accounts.iter().cloned());
         
         let pool = &mut ctx.accounts.pool;
+        let clock = Clock::get()?;
+        let slot = clock.slot;
+        update_price_accumulators(pool, &mut ctx.accounts.observations, slot);
         
         // Calculate swap amounts (constant product formula)
-        let amount_out = calculate_swap_output(
-            amount_in,
-            pool.token_a_amount,
-            pool.token_b_amount,
-            ctx.accounts.amm.pool_fee,
-            ctx.accounts.amm.pool_fee_denominator,
-        )?;
+        let token_in_mint = ctx.accounts.token_in_mint.key();
+        let quote = pool.quote_exact_in(&ctx.accounts.amm, amount_in, token_in_mint, &clock)?;
+        let amount_out = quote.amount_out;
 
         require!(
             amount_out >= minimum_amount_out,
             AmmError::InsufficientOutputAmount
         );
+        pool.circuit_breaker.check_impact(quote.price_impact_bps)?;
+
+        // Take the reentrancy lock and persist it before the hook-bearing
+        // transfers, so a hook calling back into this program sees it
+        pool.lock()?;
+        pool.exit(&crate::ID)?;
 
         // Transfer tokens from user to vault using transfer hook compatible function
         invoke_transfer_checked(
@@ -190,71 +240,476 @@ pub mod token_2022_amm {
         )?;
 
         // Update pool balances
-        pool.token_a_amount = pool.token_a_amount.checked_add(amount_in).unwrap();
-        pool.token_b_amount = pool.token_b_amount.checked_sub(amount_out).unwrap();
+        let (price_before, _) = spot_prices(pool);
+        pool.apply_quote(token_in_mint, &quote);
+        let (price_after, _) = spot_prices(pool);
+        if pool.dynamic_fee.enabled() {
+            pool.dynamic_fee.record_move(slot, price_before, price_after);
+        }
+        pool.circuit_breaker.check_window(slot, price_before, price_after)?;
+        pool.locked = false;
+        ctx.accounts.stats.record_swap(
+            token_in_mint == pool.token_a_mint,
+            &quote,
+            pool.last_update_slot,
+        );
 
+        emit_cpi!(Swapped {
+            pool: pool.key(),
+            user: ctx.accounts.user.key(),
+            token_in_mint,
+            token_out_mint: ctx.accounts.token_out_mint.key(),
+            amount_in,
+            amount_out,
+            fee: quote.fee,
+            fee_bps: quote.fee_bps,
+            token_a_amount: pool.token_a_amount,
+            token_b_amount: pool.token_b_amount,
+        });
         msg!("Swap completed: {} in, {} out", amount_in, amount_out);
         Ok(())
     }
 
     /// Add liquidity to the pool
-    pub fn add_liquidity(
-        ctx: Context<AddLiquidity>,
+    pub fn add_liquidity<'info>(
+        ctx: Context<'_, '_, '_, 'info, AddLiquidity<'info>>,
         token_a_amount: u64,
         token_b_amount: u64,
     ) -> Result<()> {
+        // Prepare all accounts for transfer hook resolution (before mutable borrow)
+        let mut all_accounts = vec![
+            ctx.accounts.pool.to_account_info(),
+            ctx.accounts.amm.to_account_info(),
+            ctx.accounts.user.to_account_info(),
+            ctx.accounts.user_token_a.to_account_info(),
+            ctx.accounts.user_token_b.to_account_info(),
+            ctx.accounts.token_a_mint.to_account_info(),
+            ctx.accounts.token_b_mint.to_account_info(),
+            ctx.accounts.token_a_vault.to_account_info(),
+            ctx.accounts.token_b_vault.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+        ];
+        all_accounts.extend(ctx.remaining_accounts.iter().cloned());
+
         let pool = &mut ctx.accounts.pool;
+        let clock = Clock::get()?;
+        require!(
+            !pool.curve.sale_active(clock.unix_timestamp) || ctx.accounts.user.key() == pool.owner,
+            AmmError::SaleInProgress
+        );
+        update_price_accumulators(pool, &mut ctx.accounts.observations, clock.slot);
+        pool.lock()?;
+        pool.exit(&crate::ID)?;
 
         // Transfer tokens to vaults
-        let transfer_a_ctx = CpiContext::new(
-            ctx.accounts.token_program.to_account_info(),
-            TransferChecked {
-                from: ctx.accounts.user_token_a.to_account_info(),
-                mint: ctx.accounts.token_a_mint.to_account_info(),
-                to: ctx.accounts.token_a_vault.to_account_info(),
-                authority: ctx.accounts.user.to_account_info(),
-            },
-        );
-        transfer_checked(transfer_a_ctx, token_a_amount, ctx.accounts.token_a_mint.decimals)?;
+        invoke_transfer_checked(
+            &ctx.accounts.token_program.key(),
+            ctx.accounts.user_token_a.to_account_info(),
+            ctx.accounts.token_a_mint.to_account_info(),
+            ctx.accounts.token_a_vault.to_account_info(),
+            ctx.accounts.user.to_account_info(),
+            &all_accounts,
+            token_a_amount,
+            ctx.accounts.token_a_mint.decimals,
+            &[],
+        )?;
 
-        let transfer_b_ctx = CpiContext::new(
-            ctx.accounts.token_program.to_account_info(),
-            TransferChecked {
-                from: ctx.accounts.user_token_b.to_account_info(),
-                mint: ctx.accounts.token_b_mint.to_account_info(),
-                to: ctx.accounts.token_b_vault.to_account_info(),
-                authority: ctx.accounts.user.to_account_info(),
-            },
-        );
-        transfer_checked(transfer_b_ctx, token_b_amount, ctx.accounts.token_b_mint.decimals)?;
+        invoke_transfer_checked(
+            &ctx.accounts.token_program.key(),
+            ctx.accounts.user_token_b.to_account_info(),
+            ctx.accounts.token_b_mint.to_account_info(),
+            ctx.accounts.token_b_vault.to_account_info(),
+            ctx.accounts.user.to_account_info(),
+            &all_accounts,
+            token_b_amount,
+            ctx.accounts.token_b_mint.decimals,
+            &[],
+        )?;
 
         // Calculate LP tokens to mint
-        let lp_tokens_to_mint = calculate_lp_tokens(token_a_amount, token_b_amount, pool)?;
+        let lp_tokens_to_mint = pool.swap_curve()?.deposit(
+            token_a_amount,
+            token_b_amount,
+            pool.token_a_amount,
+            pool.token_b_amount,
+            pool.lp_supply,
+        )?;
 
-        // Mint LP tokens to user
-        let amm_seeds = &[b"amm".as_ref(), &[ctx.accounts.amm.bump]];
-        let signer_seeds = &[&amm_seeds[..]];
+        // Mint LP tokens to user; the pool is the LP mint's authority
+        let bump = [pool.bump];
+        let pool_seeds = pool.signer_seeds(&bump);
+        let signer_seeds = &[&pool_seeds[..]];
         
         let mint_lp_ctx = CpiContext::new_with_signer(
             ctx.accounts.token_program.to_account_info(),
             anchor_spl::token_2022::MintTo {
                 mint: ctx.accounts.lp_mint.to_account_info(),
                 to: ctx.accounts.user_lp_token.to_account_info(),
-                authority: ctx.accounts.amm.to_account_info(),
+                authority: pool.to_account_info(),
             },
             signer_seeds,
         );
         anchor_spl::token_2022::mint_to(mint_lp_ctx, lp_tokens_to_mint)?;
 
         // Update pool balances
-        pool.token_a_amount = pool.token_a_amount.checked_add(token_a_amount).unwrap();
-        pool.token_b_amount = pool.token_b_amount.checked_add(token_b_amount).unwrap();
-        pool.lp_supply = pool.lp_supply.checked_add(lp_tokens_to_mint).unwrap();
+        pool.token_a_amount = pool
+            .token_a_amount
+            .checked_add(token_a_amount)
+            .ok_or(AmmError::InvalidLiquidityCalculation)?;
+        pool.token_b_amount = pool
+            .token_b_amount
+            .checked_add(token_b_amount)
+            .ok_or(AmmError::InvalidLiquidityCalculation)?;
+        pool.lp_supply = pool
+            .lp_supply
+            .checked_add(lp_tokens_to_mint)
+            .ok_or(AmmError::InvalidLiquidityCalculation)?;
+        pool.locked = false;
+
+        let stats = &mut ctx.accounts.stats;
+        stats.record_liquidity_provider(&mut ctx.accounts.liquidity_provider, pool.key(), ctx.accounts.user.key(), pool.last_update_slot);
 
+        emit_cpi!(LiquidityAdded {
+            pool: pool.key(),
+            user: ctx.accounts.user.key(),
+            token_a_amount,
+            token_b_amount,
+            lp_minted: lp_tokens_to_mint,
+            lp_supply: pool.lp_supply,
+        });
         msg!("Liquidity added: {} LP tokens minted", lp_tokens_to_mint);
         Ok(())
     }
 
+    /// Price an exact-input swap against current reserves without executing
+    /// it. The quote is returned through return data.
+    pub fn quote_swap(
+        ctx: Context<Quote>,
+        amount_in: u64,
+        token_in_mint: Pubkey,
+    ) -> Result<SwapQuote> {
+        ctx.accounts
+            .pool
+            .quote_exact_in(&ctx.accounts.amm, amount_in, token_in_mint, &Clock::get()?)
+    }
+
+    /// Price an exact-output swap: the input needed to receive `amount_out`
+    pub fn quote_exact_out(
+        ctx: Context<Quote>,
+        amount_out: u64,
+        token_in_mint: Pubkey,
+    ) -> Result<SwapQuote> {
+        ctx.accounts
+            .pool
+            .quote_exact_out(&ctx.accounts.amm, amount_out, token_in_mint, &Clock::get()?)
+    }
+
+    /// Cumulative pool statistics, returned through return data
+    pub fn get_pool_stats(ctx: Context<GetPoolStats>) -> Result<PoolStats> {
+        Ok(ctx.accounts.stats.clone().into_inner())
+    }
+
+    /// Time-weighted average prices over at least `window` slots, returned
+    /// through return data
+    pub fn consult(ctx: Context<Consult>, window: u64) -> Result<TwapPrice> {
+        require!(window > 0, AmmError::InvalidTwapWindow);
+        let pool = &ctx.accounts.pool;
+        let now = Clock::get()?.slot;
+
+        // Cumulative prices as they would read if the pool were touched now
+        let elapsed = now.saturating_sub(pool.last_update_slot) as u128;
+        let (price_a, price_b) = spot_prices(pool);
+        let price_a_cumulative = pool
+            .price_a_cumulative
+            .wrapping_add(price_a.wrapping_mul(elapsed));
+        let price_b_cumulative = pool
+            .price_b_cumulative
+            .wrapping_add(price_b.wrapping_mul(elapsed));
+
+        let start = ctx
+            .accounts
+            .observations
+            .at_or_before(now.saturating_sub(window))
+            .ok_or(AmmError::TwapWindowUnavailable)?;
+        let span = (now - start.slot) as u128;
+
+        Ok(TwapPrice {
+            price_a: price_a_cumulative.wrapping_sub(start.price_a_cumulative) / span,
+            price_b: price_b_cumulative.wrapping_sub(start.price_b_cumulative) / span,
+            start_slot: start.slot,
+            end_slot: now,
+        })
+    }
+
+    /// Move a stable pool's amplification linearly from its current value to
+    /// `target_amp`, reaching it at `ramp_stop_ts`
+    pub fn ramp_a(ctx: Context<RampA>, target_amp: u64, ramp_stop_ts: i64) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        let now = Clock::get()?.unix_timestamp;
+        let current_amp = pool
+            .curve
+            .current_amp(now)
+            .ok_or(AmmError::InvalidCurveParameters)?;
+
+        require!(
+            curve::stable::amp_in_range(target_amp)
+                && target_amp <= current_amp.saturating_mul(curve::stable::MAX_AMP_CHANGE)
+                && target_amp.saturating_mul(curve::stable::MAX_AMP_CHANGE) >= current_amp,
+            AmmError::InvalidCurveParameters
+        );
+        require!(
+            ramp_stop_ts >= now.saturating_add(curve::stable::MIN_RAMP_DURATION),
+            AmmError::InvalidCurveParameters
+        );
+
+        pool.curve = CurveParams::Stable {
+            initial_amp: current_amp,
+            target_amp,
+            ramp_start_ts: now,
+            ramp_stop_ts,
+        };
+
+        emit_cpi!(AmpRamped {
+            pool: pool.key(),
+            initial_amp: current_amp,
+            target_amp,
+            ramp_start_ts: now,
+            ramp_stop_ts,
+        });
+        Ok(())
+    }
+
+    /// Charge a pool a fee between `min_fee_bps` and `max_fee_bps` that rises
+    /// by `fee_per_volatility` bps per 100 bps of recent price movement. A
+    /// `max_fee_bps` of zero returns the pool to the AMM's static fee.
+    pub fn set_dynamic_fee(
+        ctx: Context<SetDynamicFee>,
+        min_fee_bps: u16,
+        max_fee_bps: u16,
+        fee_per_volatility: u16,
+        half_life_slots: u64,
+    ) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        pool.dynamic_fee
+            .configure(min_fee_bps, max_fee_bps, fee_per_volatility, half_life_slots)?;
+
+        emit_cpi!(DynamicFeeConfigured {
+            pool: pool.key(),
+            min_fee_bps,
+            max_fee_bps,
+            fee_per_volatility,
+            half_life_slots,
+        });
+        Ok(())
+    }
+
+    /// Bound a pool's price impact per swap to `max_price_impact_bps` and
+    /// its spot price move to `max_window_move_bps` within any
+    /// `window_slots`-slot window. Zero disables either bound.
+    pub fn set_circuit_breaker(
+        ctx: Context<SetCircuitBreaker>,
+        max_price_impact_bps: u16,
+        max_window_move_bps: u16,
+        window_slots: u64,
+    ) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        pool.circuit_breaker
+            .configure(max_price_impact_bps, max_window_move_bps, window_slots)?;
+
+        emit_cpi!(CircuitBreakerConfigured {
+            pool: pool.key(),
+            max_price_impact_bps,
+            max_window_move_bps,
+            window_slots,
+        });
+        Ok(())
+    }
+
+    /// Put `sale_supply` tokens of a new mint up for sale along `curve` and
+    /// reserve `liquidity_supply` more for the pool it graduates into
+    pub fn create_launch<'info>(
+        ctx: Context<'_, '_, '_, 'info, CreateLaunch<'info>>,
+        curve: BondingCurve,
+        sale_supply: u64,
+        liquidity_supply: u64,
+        graduation_market_cap: u64,
+    ) -> Result<()> {
+        launchpad::create(ctx, curve, sale_supply, liquidity_supply, graduation_market_cap)
+    }
+
+    /// Buy `token_amount` launch tokens at the curve price
+    pub fn buy_launch_tokens<'info>(
+        ctx: Context<'_, '_, '_, 'info, LaunchTrade<'info>>,
+        token_amount: u64,
+        maximum_quote_in: u64,
+    ) -> Result<()> {
+        launchpad::buy(ctx, token_amount, maximum_quote_in)
+    }
+
+    /// Sell `token_amount` launch tokens back to the curve
+    pub fn sell_launch_tokens<'info>(
+        ctx: Context<'_, '_, '_, 'info, LaunchTrade<'info>>,
+        token_amount: u64,
+        minimum_quote_out: u64,
+    ) -> Result<()> {
+        launchpad::sell(ctx, token_amount, minimum_quote_out)
+    }
+
+    /// Move a completed launch's reserved tokens and proceeds into a new
+    /// constant-product pool. Permissionless.
+    pub fn graduate_launch<'info>(ctx: Context<'_, '_, '_, 'info, GraduateLaunch<'info>>) -> Result<()> {
+        launchpad::graduate(ctx)
+    }
+
+    /// Create a concentrated-liquidity pool starting at `initial_sqrt_price_x64`
+    pub fn create_cl_pool(ctx: Context<CreateClPool>, tick_spacing: u16, initial_sqrt_price_x64: u128) -> Result<()> {
+        concentrated::create_cl_pool(ctx, tick_spacing, initial_sqrt_price_x64)
+    }
+
+    /// Allocate the tick array starting at `start_tick_index`
+    pub fn initialize_tick_array(ctx: Context<InitializeTickArray>, start_tick_index: i32) -> Result<()> {
+        concentrated::initialize_tick_array(ctx, start_tick_index)
+    }
+
+    /// Open an empty position between two ticks
+    pub fn open_position(ctx: Context<OpenPosition>, tick_lower: i32, tick_upper: i32) -> Result<()> {
+        concentrated::open_position(ctx, tick_lower, tick_upper)
+    }
+
+    /// Add `liquidity` to a position, paying at most the given amounts
+    pub fn increase_liquidity<'info>(
+        ctx: Context<'_, '_, '_, 'info, ModifyClLiquidity<'info>>,
+        liquidity: u128,
+        maximum_token_a: u64,
+        maximum_token_b: u64,
+    ) -> Result<()> {
+        concentrated::increase_liquidity(ctx, liquidity, maximum_token_a, maximum_token_b)
+    }
+
+    /// Remove `liquidity` from a position, receiving at least the given amounts
+    pub fn decrease_liquidity<'info>(
+        ctx: Context<'_, '_, '_, 'info, ModifyClLiquidity<'info>>,
+        liquidity: u128,
+        minimum_token_a: u64,
+        minimum_token_b: u64,
+    ) -> Result<()> {
+        concentrated::decrease_liquidity(ctx, liquidity, minimum_token_a, minimum_token_b)
+    }
+
+    /// Pay out the fees a position has earned
+    pub fn collect_fees<'info>(ctx: Context<'_, '_, '_, 'info, ModifyClLiquidity<'info>>) -> Result<()> {
+        concentrated::collect_fees(ctx)
+    }
+
+    /// Swap exact input through a concentrated-liquidity pool. Stops early at
+    /// `sqrt_price_limit_x64` (0 for none) or the end of the given tick arrays.
+    pub fn cl_swap<'info>(
+        ctx: Context<'_, '_, '_, 'info, ClSwap<'info>>,
+        amount_in: u64,
+        minimum_amount_out: u64,
+        a_to_b: bool,
+        sqrt_price_limit_x64: u128,
+    ) -> Result<()> {
+        concentrated::cl_swap(ctx, amount_in, minimum_amount_out, a_to_b, sqrt_price_limit_x64)
+    }
+
+    /// Create a basket of three or more mints. `remaining_accounts` holds,
+    /// per member in mint order, the mint, the AMM-owned vault and the
+    /// creator's token account followed by `hook_account_counts[i]` hook accounts.
+    /// `mints_hash` is the sha256 of the sorted member mints, which seeds
+    /// the basket's address.
+    pub fn create_basket<'info>(
+        ctx: Context<'_, '_, '_, 'info, CreateBasket<'info>>,
+        mints_hash: [u8; 32],
+        curve: BasketCurve,
+        weights: Vec<u64>,
+        initial_amounts: Vec<u64>,
+        hook_account_counts: Vec<u8>,
+    ) -> Result<()> {
+        basket::create_basket(ctx, mints_hash, curve, weights, initial_amounts, hook_account_counts)
+    }
+
+    /// Swap `amount_in` of member `in_index` for member `out_index`. The two
+    /// members' account groups come in `remaining_accounts`, input first.
+    pub fn basket_swap<'info>(
+        ctx: Context<'_, '_, '_, 'info, BasketSwap<'info>>,
+        in_index: u8,
+        out_index: u8,
+        amount_in: u64,
+        minimum_amount_out: u64,
+        hook_account_counts: [u8; 2],
+    ) -> Result<()> {
+        basket::basket_swap(ctx, in_index, out_index, amount_in, minimum_amount_out, hook_account_counts)
+    }
+
+    /// Mint `lp_amount` basket LP tokens for a proportional deposit of every member
+    pub fn add_basket_liquidity<'info>(
+        ctx: Context<'_, '_, '_, 'info, BasketLiquidity<'info>>,
+        lp_amount: u64,
+        maximum_amounts: Vec<u64>,
+        hook_account_counts: Vec<u8>,
+    ) -> Result<()> {
+        basket::add_basket_liquidity(ctx, lp_amount, maximum_amounts, hook_account_counts)
+    }
+
+    /// Burn `lp_amount` basket LP tokens for a proportional share of every member
+    pub fn remove_basket_liquidity<'info>(
+        ctx: Context<'_, '_, '_, 'info, BasketLiquidity<'info>>,
+        lp_amount: u64,
+        minimum_amounts: Vec<u64>,
+        hook_account_counts: Vec<u8>,
+    ) -> Result<()> {
+        basket::remove_basket_liquidity(ctx, lp_amount, minimum_amounts, hook_account_counts)
+    }
+
+    /// Create a program-owned price feed for `base_mint` in `quote_mint`
+    pub fn initialize_price_feed(ctx: Context<InitializePriceFeed>, base_mint: Pubkey, quote_mint: Pubkey) -> Result<()> {
+        pmm::initialize_price_feed(ctx, base_mint, quote_mint)
+    }
+
+    /// Publish a new price to a feed the signer controls
+    pub fn update_price_feed(ctx: Context<UpdatePriceFeed>, price_x64: u128, confidence_x64: u128) -> Result<()> {
+        pmm::update_price_feed(ctx, price_x64, confidence_x64)
+    }
+
+    /// Create an oracle-priced pool funded by its owner
+    pub fn create_pmm_pool<'info>(
+        ctx: Context<'_, '_, '_, 'info, CreatePmmPool<'info>>,
+        params: PmmParams,
+        token_a_amount: u64,
+        token_b_amount: u64,
+    ) -> Result<()> {
+        pmm::create_pmm_pool(ctx, params, token_a_amount, token_b_amount)
+    }
+
+    /// Add inventory to an oracle-priced pool. Owner only.
+    pub fn deposit_pmm_liquidity<'info>(
+        ctx: Context<'_, '_, '_, 'info, PmmLiquidity<'info>>,
+        token_a_amount: u64,
+        token_b_amount: u64,
+    ) -> Result<()> {
+        pmm::deposit_pmm_liquidity(ctx, token_a_amount, token_b_amount)
+    }
+
+    /// Take inventory out of an oracle-priced pool. Owner only.
+    pub fn withdraw_pmm_liquidity<'info>(
+        ctx: Context<'_, '_, '_, 'info, PmmLiquidity<'info>>,
+        token_a_amount: u64,
+        token_b_amount: u64,
+    ) -> Result<()> {
+        pmm::withdraw_pmm_liquidity(ctx, token_a_amount, token_b_amount)
+    }
+
+    /// Swap exact input against an oracle-priced pool
+    pub fn pmm_swap<'info>(
+        ctx: Context<'_, '_, '_, 'info, PmmSwap<'info>>,
+        amount_in: u64,
+        minimum_amount_out: u64,
+        a_to_b: bool,
+    ) -> Result<()> {
+        pmm::pmm_swap(ctx, amount_in, minimum_amount_out, a_to_b)
+    }
+
     /// Initialize the hook whitelist
     pub fn initialize_whitelist(ctx: Context<InitializeWhitelist>) -> Result<()> {
         let wl = &mut ctx.accounts.whitelist;
@@ -274,6 +729,11 @@ pub mod token_2022_amm {
         if !wl.allowed.iter().any(|p| p == &program_id) {
             wl.allowed.push(program_id);
         }
+        emit_cpi!(WhitelistChanged {
+            whitelist: wl.key(),
+            program_id,
+            allowed: true,
+        });
         Ok(())
     }
 
@@ -286,6 +746,11 @@ pub mod token_2022_amm {
         );
         let wl = &mut ctx.accounts.whitelist;
         wl.allowed.retain(|p| p != &program_id);
+        emit_cpi!(WhitelistChanged {
+            whitelist: wl.key(),
+            program_id,
+            allowed: false,
+        });
         Ok(())
     }
 }
@@ -315,18 +780,50 @@ pub struct SetTokenPair<'info> {
     pub user: Signer<'info>,
 }
 
+#[event_cpi]
 #[derive(Accounts)]
 pub struct CreatePool<'info> {
     #[account(
         init,
         payer = user,
         space = 8 + Pool::INIT_SPACE,
-        seeds = [b"pool", token_a_mint.key().as_ref(), token_b_mint.key().as_ref()],
+        seeds = [
+            b"pool",
+            std::cmp::min(token_a_mint.key(), token_b_mint.key()).as_ref(),
+            std::cmp::max(token_a_mint.key(), token_b_mint.key()).as_ref()
+        ],
         bump
     )]
     pub pool: Account<'info, Pool>,
     
     pub amm: Account<'info, Amm>,
+
+    #[account(
+        init,
+        payer = user,
+        space = 8 + Observations::INIT_SPACE,
+        seeds = [b"observations", pool.key().as_ref()],
+        bump
+    )]
+    pub observations: Box<Account<'info, Observations>>,
+
+    #[account(
+        init,
+        payer = user,
+        space = 8 + PoolStats::INIT_SPACE,
+        seeds = [b"pool-stats", pool.key().as_ref()],
+        bump
+    )]
+    pub stats: Box<Account<'info, PoolStats>>,
+
+    #[account(
+        init,
+        payer = user,
+        space = 8 + LiquidityProvider::INIT_SPACE,
+        seeds = [b"liquidity-provider", pool.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub liquidity_provider: Box<Account<'info, LiquidityProvider>>,
     
     #[account(mut)]
     pub user: Signer<'info>,
@@ -338,11 +835,36 @@ pub struct CreatePool<'info> {
     
     pub token_a_mint: InterfaceAccount<'info, Mint>,
     pub token_b_mint: InterfaceAccount<'info, Mint>,
+
+    /// CHECK: Launch of token A, if any; see `launchpad::require_graduated`
+    #[account(seeds = [b"launch", token_a_mint.key().as_ref()], bump)]
+    pub token_a_launch: UncheckedAccount<'info>,
+    /// CHECK: Launch of token B, if any; see `launchpad::require_graduated`
+    #[account(seeds = [b"launch", token_b_mint.key().as_ref()], bump)]
+    pub token_b_launch: UncheckedAccount<'info>,
     
-    #[account(mut)]
+    #[account(
+        mut,
+        token::mint = token_a_mint,
+        token::authority = amm
+    )]
     pub token_a_vault: InterfaceAccount<'info, TokenAccount>,
-    #[account(mut)]
+    #[account(
+        mut,
+        token::mint = token_b_mint,
+        token::authority = amm
+    )]
     pub token_b_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = user,
+        seeds = [b"lp-mint", pool.key().as_ref()],
+        bump,
+        mint::decimals = LP_DECIMALS,
+        mint::authority = pool
+    )]
+    pub lp_mint: Box<InterfaceAccount<'info, Mint>>,
     
     /// CHECK: Extra account metas for token A transfer hook
     #[account(
@@ -367,15 +889,29 @@ pub struct CreatePool<'info> {
     pub token_program: Program<'info, Token2022>,
 }
 
+#[event_cpi]
 #[derive(Accounts)]
 pub struct Swap<'info> {
     #[account(
         mut,
+        constraint = !pool.locked @ AmmError::PoolLocked,
         constraint = pool.token_a_mint == if token_in_mint.key() < token_out_mint.key() { token_in_mint.key() } else { token_out_mint.key() } @ AmmError::InvalidTokenPair,
         constraint = pool.token_b_mint == if token_in_mint.key() < token_out_mint.key() { token_out_mint.key() } else { token_in_mint.key() } @ AmmError::InvalidTokenPair
     )]
     pub pool: Account<'info, Pool>,
     pub amm: Account<'info, Amm>,
+    #[account(
+        mut,
+        seeds = [b"observations", pool.key().as_ref()],
+        bump
+    )]
+    pub observations: Box<Account<'info, Observations>>,
+    #[account(
+        mut,
+        seeds = [b"pool-stats", pool.key().as_ref()],
+        bump
+    )]
+    pub stats: Box<Account<'info, PoolStats>>,
     
     #[account(mut)]
     pub user: Signer<'info>,
@@ -388,9 +924,9 @@ pub struct Swap<'info> {
     pub token_in_mint: InterfaceAccount<'info, Mint>,
     pub token_out_mint: InterfaceAccount<'info, Mint>,
     
-    #[account(mut)]
+    #[account(mut, address = pool.vault_for(&token_in_mint.key()) @ AmmError::InvalidTokenPair)]
     pub token_in_vault: InterfaceAccount<'info, TokenAccount>,
-    #[account(mut)]
+    #[account(mut, address = pool.vault_for(&token_out_mint.key()) @ AmmError::InvalidTokenPair)]
     pub token_out_vault: InterfaceAccount<'info, TokenAccount>,
     
     /// CHECK: Extra account metas for token in transfer hook
@@ -415,11 +951,38 @@ pub struct Swap<'info> {
     pub token_program: Program<'info, Token2022>,
 }
 
+#[event_cpi]
 #[derive(Accounts)]
 pub struct AddLiquidity<'info> {
-    #[account(mut)]
+    #[account(
+        mut,
+        has_one = lp_mint @ AmmError::InvalidTokenPair,
+        has_one = token_a_vault @ AmmError::InvalidTokenPair,
+        has_one = token_b_vault @ AmmError::InvalidTokenPair,
+        constraint = !pool.locked @ AmmError::PoolLocked
+    )]
     pub pool: Account<'info, Pool>,
     pub amm: Account<'info, Amm>,
+    #[account(
+        mut,
+        seeds = [b"observations", pool.key().as_ref()],
+        bump
+    )]
+    pub observations: Box<Account<'info, Observations>>,
+    #[account(
+        mut,
+        seeds = [b"pool-stats", pool.key().as_ref()],
+        bump
+    )]
+    pub stats: Box<Account<'info, PoolStats>>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + LiquidityProvider::INIT_SPACE,
+        seeds = [b"liquidity-provider", pool.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub liquidity_provider: Box<Account<'info, LiquidityProvider>>,
     
     #[account(mut)]
     pub user: Signer<'info>,
@@ -428,10 +991,12 @@ pub struct AddLiquidity<'info> {
     pub user_token_a: InterfaceAccount<'info, TokenAccount>,
     #[account(mut)]
     pub user_token_b: InterfaceAccount<'info, TokenAccount>,
-    #[account(mut)]
+    #[account(mut, token::mint = lp_mint)]
     pub user_lp_token: InterfaceAccount<'info, TokenAccount>,
     
+    #[account(address = pool.token_a_mint @ AmmError::InvalidTokenPair)]
     pub token_a_mint: InterfaceAccount<'info, Mint>,
+    #[account(address = pool.token_b_mint @ AmmError::InvalidTokenPair)]
     pub token_b_mint: InterfaceAccount<'info, Mint>,
     
     #[account(mut)]
@@ -442,9 +1007,75 @@ pub struct AddLiquidity<'info> {
     #[account(mut)]
     pub lp_mint: InterfaceAccount<'info, Mint>,
     
+    pub system_program: Program<'info, System>,
     pub token_program: Program<'info, Token2022>,
 }
 
+#[derive(Accounts)]
+pub struct Quote<'info> {
+    pub pool: Account<'info, Pool>,
+    pub amm: Account<'info, Amm>,
+}
+
+#[derive(Accounts)]
+pub struct GetPoolStats<'info> {
+    pub pool: Account<'info, Pool>,
+    #[account(
+        seeds = [b"pool-stats", pool.key().as_ref()],
+        bump
+    )]
+    pub stats: Box<Account<'info, PoolStats>>,
+}
+
+#[derive(Accounts)]
+pub struct Consult<'info> {
+    pub pool: Account<'info, Pool>,
+    #[account(
+        seeds = [b"observations", pool.key().as_ref()],
+        bump
+    )]
+    pub observations: Box<Account<'info, Observations>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RampA<'info> {
+    #[account(
+        mut,
+        constraint = !pool.locked @ AmmError::PoolLocked
+    )]
+    pub pool: Account<'info, Pool>,
+    #[account(has_one = authority @ AmmError::Unauthorized)]
+    pub amm: Account<'info, Amm>,
+    pub authority: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetDynamicFee<'info> {
+    #[account(
+        mut,
+        constraint = !pool.locked @ AmmError::PoolLocked
+    )]
+    pub pool: Account<'info, Pool>,
+    #[account(has_one = authority @ AmmError::Unauthorized)]
+    pub amm: Account<'info, Amm>,
+    pub authority: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetCircuitBreaker<'info> {
+    #[account(
+        mut,
+        constraint = !pool.locked @ AmmError::PoolLocked
+    )]
+    pub pool: Account<'info, Pool>,
+    #[account(has_one = authority @ AmmError::Unauthorized)]
+    pub amm: Account<'info, Amm>,
+    pub authority: Signer<'info>,
+}
+
 #[derive(Accounts)]
 pub struct InitializeWhitelist<'info> {
     #[account(mut)]
@@ -461,6 +1092,7 @@ pub struct InitializeWhitelist<'info> {
     pub system_program: Program<'info, System>,
 }
 
+#[event_cpi]
 #[derive(Accounts)]
 pub struct UpdateWhitelist<'info> {
     #[account(mut)]
@@ -489,15 +1121,278 @@ pub struct Amm {
     pub bump: u8,
 }
 
+impl Amm {
+    /// The AMM-wide static fee
+    pub fn fee_rate(&self) -> FeeRate {
+        FeeRate {
+            fee: self.pool_fee,
+            denominator: self.pool_fee_denominator,
+        }
+    }
+
+    /// Swap fee charged on `amount_in`, rounded down
+    pub fn fee_on(&self, amount_in: u64) -> Result<u64> {
+        self.fee_rate().fee_on(amount_in)
+    }
+
+    /// Smallest `amount_in` that leaves at least `amount_in_after_fee` once
+    /// the fee is taken
+    pub fn gross_up(&self, amount_in_after_fee: u64) -> Result<u64> {
+        self.fee_rate().gross_up(amount_in_after_fee)
+    }
+}
+
+/// Decimals of the LP mint each pool creates
+pub const LP_DECIMALS: u8 = 9;
+
 #[account]
 #[derive(InitSpace)]
 pub struct Pool {
     pub token_a_mint: Pubkey,  // Always the smaller mint key
     pub token_b_mint: Pubkey,  // Always the larger mint key
+    pub token_a_vault: Pubkey,
+    pub token_b_vault: Pubkey,
+    pub lp_mint: Pubkey,       // PDA of the pool, which is its mint authority
     pub token_a_amount: u64,
     pub token_b_amount: u64,
     pub lp_supply: u64,
     pub bump: u8,
+    pub locked: bool,          // Set while transfers (and their hooks) are in flight
+    pub price_a_cumulative: u128, // Sum over slots of Q64.64 price of A in B
+    pub price_b_cumulative: u128, // Sum over slots of Q64.64 price of B in A
+    pub last_update_slot: u64,
+    pub curve: CurveParams,
+    pub owner: Pubkey,         // Creator; the only depositor while an LBP sale runs
+    pub dynamic_fee: DynamicFee,
+    pub circuit_breaker: CircuitBreaker,
+}
+
+impl Pool {
+    /// Take the reentrancy lock, failing with `PoolLocked` if it is held
+    pub fn lock(&mut self) -> Result<()> {
+        require!(!self.locked, AmmError::PoolLocked);
+        self.locked = true;
+        Ok(())
+    }
+
+    /// The pool's curve as priced now
+    pub fn swap_curve(&self) -> Result<Box<dyn SwapCurve>> {
+        Ok(self.curve.swap_curve(Clock::get()?.unix_timestamp))
+    }
+
+    /// Opening state of a new pool. `vaults` and `amounts` follow `mints`,
+    /// which may come in either order.
+    pub fn open(
+        &mut self,
+        mints: (Pubkey, Pubkey),
+        vaults: (Pubkey, Pubkey),
+        amounts: (u64, u64),
+        curve: CurveParams,
+        owner: Pubkey,
+        clock: &Clock,
+    ) -> Result<()> {
+        let ((token_a_mint, token_a_vault, token_a_amount), (token_b_mint, token_b_vault, token_b_amount)) =
+            if mints.0 < mints.1 {
+                ((mints.0, vaults.0, amounts.0), (mints.1, vaults.1, amounts.1))
+            } else {
+                ((mints.1, vaults.1, amounts.1), (mints.0, vaults.0, amounts.0))
+            };
+        self.token_a_mint = token_a_mint;
+        self.token_b_mint = token_b_mint;
+        self.token_a_vault = token_a_vault;
+        self.token_b_vault = token_b_vault;
+        self.token_a_amount = token_a_amount;
+        self.token_b_amount = token_b_amount;
+        self.curve = curve;
+        self.owner = owner;
+        self.lp_supply = curve
+            .swap_curve(clock.unix_timestamp)
+            .deposit(token_a_amount, token_b_amount, 0, 0, 0)?;
+        self.last_update_slot = clock.slot;
+        Ok(())
+    }
+
+    /// Seeds the pool signs with as the LP mint's authority
+    pub fn signer_seeds<'a>(&'a self, bump: &'a [u8; 1]) -> [&'a [u8]; 4] {
+        [b"pool", self.token_a_mint.as_ref(), self.token_b_mint.as_ref(), bump]
+    }
+
+    /// The pool's vault for `mint`, which must be one of its two mints
+    pub fn vault_for(&self, mint: &Pubkey) -> Pubkey {
+        if *mint == self.token_a_mint {
+            self.token_a_vault
+        } else {
+            self.token_b_vault
+        }
+    }
+
+    /// Returns `(a_to_b, reserve_in, reserve_out)` for a swap paying `token_in_mint`
+    fn reserves_for(&self, token_in_mint: Pubkey) -> Result<(bool, u64, u64)> {
+        if token_in_mint == self.token_a_mint {
+            Ok((true, self.token_a_amount, self.token_b_amount))
+        } else if token_in_mint == self.token_b_mint {
+            Ok((false, self.token_b_amount, self.token_a_amount))
+        } else {
+            err!(AmmError::InvalidTokenPair)
+        }
+    }
+
+    /// The fee charged on swaps at `slot`: the dynamic rate if one is
+    /// configured, otherwise the AMM's static fee
+    fn fee_rate(&self, amm: &Amm, slot: u64) -> FeeRate {
+        if self.dynamic_fee.enabled() {
+            self.dynamic_fee.fee_rate(slot)
+        } else {
+            amm.fee_rate()
+        }
+    }
+
+    pub fn quote_exact_in(&self, amm: &Amm, amount_in: u64, token_in_mint: Pubkey, clock: &Clock) -> Result<SwapQuote> {
+        let (a_to_b, reserve_in, reserve_out) = self.reserves_for(token_in_mint)?;
+        let curve = self.curve.swap_curve(clock.unix_timestamp);
+        let fee_rate = self.fee_rate(amm, clock.slot);
+        let fee = fee_rate.fee_on(amount_in)?;
+        let amount_out = curve.swap_exact_in(amount_in - fee, reserve_in, reserve_out, a_to_b)?;
+        build_quote(curve.as_ref(), amount_in, amount_out, fee_rate, reserve_in, reserve_out, a_to_b)
+    }
+
+    pub fn quote_exact_out(&self, amm: &Amm, amount_out: u64, token_in_mint: Pubkey, clock: &Clock) -> Result<SwapQuote> {
+        let (a_to_b, reserve_in, reserve_out) = self.reserves_for(token_in_mint)?;
+        let curve = self.curve.swap_curve(clock.unix_timestamp);
+        let amount_in_after_fee = curve.swap_exact_out(amount_out, reserve_in, reserve_out, a_to_b)?;
+        let fee_rate = self.fee_rate(amm, clock.slot);
+        let amount_in = fee_rate.gross_up(amount_in_after_fee)?;
+        build_quote(curve.as_ref(), amount_in, amount_out, fee_rate, reserve_in, reserve_out, a_to_b)
+    }
+
+    /// Write the post-swap reserves of `quote` back in pool order
+    pub fn apply_quote(&mut self, token_in_mint: Pubkey, quote: &SwapQuote) {
+        if token_in_mint == self.token_a_mint {
+            self.token_a_amount = quote.reserve_in_after;
+            self.token_b_amount = quote.reserve_out_after;
+        } else {
+            self.token_b_amount = quote.reserve_in_after;
+            self.token_a_amount = quote.reserve_out_after;
+        }
+    }
+}
+
+/// Swap pricing returned by `quote_swap` and `quote_exact_out`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct SwapQuote {
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee: u64,              // Part of amount_in kept by the pool
+    pub fee_bps: u64,          // Rate the fee was charged at
+    pub price_impact_bps: u64, // Execution price vs. spot price, excluding the fee
+    pub reserve_in_after: u64,
+    pub reserve_out_after: u64,
+}
+
+/// Number of slots of price history kept per pool
+pub const OBSERVATION_CAPACITY: usize = 64;
+
+/// Ring buffer of cumulative price snapshots, one per slot the pool was touched
+#[account]
+#[derive(InitSpace)]
+pub struct Observations {
+    pub pool: Pubkey,
+    pub index: u16, // Most recently written entry
+    pub len: u16,
+    pub observations: [Observation; OBSERVATION_CAPACITY],
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct Observation {
+    pub slot: u64,
+    pub price_a_cumulative: u128,
+    pub price_b_cumulative: u128,
+}
+
+impl Observations {
+    pub fn write(&mut self, observation: Observation) {
+        if self.len > 0 {
+            self.index = (self.index + 1) % OBSERVATION_CAPACITY as u16;
+        }
+        self.observations[self.index as usize] = observation;
+        self.len = (self.len + 1).min(OBSERVATION_CAPACITY as u16);
+    }
+
+    /// Most recent observation taken at or before `slot`
+    pub fn at_or_before(&self, slot: u64) -> Option<Observation> {
+        (0..self.len)
+            .map(|i| {
+                let index = (self.index as usize + OBSERVATION_CAPACITY - i as usize) % OBSERVATION_CAPACITY;
+                self.observations[index]
+            })
+            .find(|o| o.slot <= slot)
+    }
+}
+
+/// Q64.64 time-weighted average prices returned by `consult`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct TwapPrice {
+    pub price_a: u128, // B per A
+    pub price_b: u128, // A per B
+    pub start_slot: u64,
+    pub end_slot: u64,
+}
+
+/// Running totals for a pool, kept for dashboards and indexers
+#[account]
+#[derive(InitSpace)]
+pub struct PoolStats {
+    pub pool: Pubkey,
+    pub volume_a: u128, // Token A moved through swaps, in either direction
+    pub volume_b: u128,
+    pub lp_fees_a: u128, // Swap fees left in the pool for LPs
+    pub lp_fees_b: u128,
+    pub protocol_fees_a: u128, // No protocol fee is charged yet; kept so the layout stays stable
+    pub protocol_fees_b: u128,
+    pub swap_count: u64,
+    pub unique_lps: u64,
+    pub last_activity_slot: u64,
+}
+
+impl PoolStats {
+    pub fn record_swap(&mut self, a_to_b: bool, quote: &SwapQuote, slot: u64) {
+        let (volume_in, volume_out, fees_in) = if a_to_b {
+            (&mut self.volume_a, &mut self.volume_b, &mut self.lp_fees_a)
+        } else {
+            (&mut self.volume_b, &mut self.volume_a, &mut self.lp_fees_b)
+        };
+        *volume_in = volume_in.saturating_add(quote.amount_in as u128);
+        *volume_out = volume_out.saturating_add(quote.amount_out as u128);
+        *fees_in = fees_in.saturating_add(quote.fee as u128);
+        self.swap_count = self.swap_count.saturating_add(1);
+        self.last_activity_slot = slot;
+    }
+
+    /// Count `owner` as a new LP the first time its marker account is written
+    pub fn record_liquidity_provider(
+        &mut self,
+        provider: &mut LiquidityProvider,
+        pool: Pubkey,
+        owner: Pubkey,
+        slot: u64,
+    ) {
+        if provider.pool == Pubkey::default() {
+            provider.pool = pool;
+            provider.owner = owner;
+            provider.first_deposit_slot = slot;
+            self.unique_lps = self.unique_lps.saturating_add(1);
+        }
+        self.last_activity_slot = slot;
+    }
+}
+
+/// Marker that `owner` has provided liquidity to `pool` at least once
+#[account]
+#[derive(InitSpace)]
+pub struct LiquidityProvider {
+    pub pool: Pubkey,
+    pub owner: Pubkey,
+    pub first_deposit_slot: u64,
 }
 
 #[account]
@@ -506,6 +1401,74 @@ pub struct HookWhitelist {
     pub allowed: Vec<Pubkey>,
 }
 
+#[event]
+pub struct PoolCreated {
+    pub pool: Pubkey,
+    pub creator: Pubkey,
+    pub token_a_mint: Pubkey,
+    pub token_b_mint: Pubkey,
+    pub token_a_amount: u64,
+    pub token_b_amount: u64,
+    pub lp_supply: u64,
+}
+
+#[event]
+pub struct Swapped {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub token_in_mint: Pubkey,
+    pub token_out_mint: Pubkey,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee: u64,
+    pub fee_bps: u64,        // Rate the fee was charged at
+    pub token_a_amount: u64, // Reserves after the swap
+    pub token_b_amount: u64,
+}
+
+#[event]
+pub struct LiquidityAdded {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub token_a_amount: u64,
+    pub token_b_amount: u64,
+    pub lp_minted: u64,
+    pub lp_supply: u64,
+}
+
+#[event]
+pub struct AmpRamped {
+    pub pool: Pubkey,
+    pub initial_amp: u64,
+    pub target_amp: u64,
+    pub ramp_start_ts: i64,
+    pub ramp_stop_ts: i64,
+}
+
+#[event]
+pub struct DynamicFeeConfigured {
+    pub pool: Pubkey,
+    pub min_fee_bps: u16,
+    pub max_fee_bps: u16,
+    pub fee_per_volatility: u16,
+    pub half_life_slots: u64,
+}
+
+#[event]
+pub struct CircuitBreakerConfigured {
+    pub pool: Pubkey,
+    pub max_price_impact_bps: u16,
+    pub max_window_move_bps: u16,
+    pub window_slots: u64,
+}
+
+#[event]
+pub struct WhitelistChanged {
+    pub whitelist: Pubkey,
+    pub program_id: Pubkey,
+    pub allowed: bool, // false when the program was removed
+}
+
 #[error_code]
 pub enum AmmError {
     #[msg("Insufficient output amount")]
@@ -520,65 +1483,197 @@ pub enum AmmError {
     InvalidWhitelist,
     #[msg("Invalid token pair for pool")]
     InvalidTokenPair,
+    #[msg("Pool is locked by an in-progress operation")]
+    PoolLocked,
+    #[msg("TWAP window must be at least one slot")]
+    InvalidTwapWindow,
+    #[msg("Not enough price history for the requested TWAP window")]
+    TwapWindowUnavailable,
+    #[msg("Invalid curve parameters")]
+    InvalidCurveParameters,
+    #[msg("Curve math did not converge")]
+    CurveDidNotConverge,
+    #[msg("Signer is not the AMM authority")]
+    Unauthorized,
+    #[msg("Only the pool owner may deposit while the sale is running")]
+    SaleInProgress,
+    #[msg("Not enough launch tokens left for this trade")]
+    InsufficientLaunchSupply,
+    #[msg("Input amount exceeds the maximum")]
+    ExcessiveInputAmount,
+    #[msg("Launch has reached its graduation threshold")]
+    LaunchComplete,
+    #[msg("Launch has not reached its graduation threshold")]
+    LaunchNotComplete,
+    #[msg("Launch has already graduated")]
+    AlreadyGraduated,
+    #[msg("Invalid tick range")]
+    InvalidTickRange,
+    #[msg("Sqrt price out of range")]
+    InvalidSqrtPrice,
+    #[msg("Tick array does not match the pool or tick")]
+    InvalidTickArray,
+    #[msg("Oracle price is stale")]
+    OracleStale,
+    #[msg("Oracle confidence interval is too wide")]
+    OracleConfidenceTooWide,
+    #[msg("Swap price impact exceeds the pool's limit")]
+    PriceImpactTooHigh,
+    #[msg("Pool price moved too far within the circuit breaker window")]
+    PriceMoveTooHigh,
+    #[msg("Token has a launch that has not graduated")]
+    LaunchInProgress,
+    #[msg("A pool already exists for this pair")]
+    PoolAlreadyExists,
+    #[msg("Stable pools need mints with equal decimals")]
+    MismatchedDecimals,
+    #[msg("Signer is not the mint or transfer-hook authority")]
+    NotMintAuthority,
 }
 
-fn calculate_swap_output(
+fn build_quote(
+    curve: &dyn SwapCurve,
     amount_in: u64,
+    amount_out: u64,
+    fee_rate: FeeRate,
     reserve_in: u64,
     reserve_out: u64,
-    fee: u64,
-    fee_denominator: u64,
-) -> Result<u64> {
-    // Use u128 to prevent overflow in intermediate calculations
-    let amount_in = amount_in as u128;
-    let reserve_in = reserve_in as u128;
-    let reserve_out = reserve_out as u128;
-    let fee = fee as u128;
-    let fee_denominator = fee_denominator as u128;
-    
-    let fee_multiplier = fee_denominator.checked_sub(fee)
-        .ok_or(AmmError::InvalidSwapCalculation)?;
-    
-    let amount_in_with_fee = amount_in.checked_mul(fee_multiplier)
-        .ok_or(AmmError::InvalidSwapCalculation)?;
-    
-    let numerator = amount_in_with_fee.checked_mul(reserve_out)
-        .ok_or(AmmError::InvalidSwapCalculation)?;
-    
-    let denominator = reserve_in.checked_mul(fee_denominator)
-        .ok_or(AmmError::InvalidSwapCalculation)?
-        .checked_add(amount_in_with_fee)
-        .ok_or(AmmError::InvalidSwapCalculation)?;
-    
-    let amount_out = numerator.checked_div(denominator)
-        .ok_or(AmmError::InvalidSwapCalculation)?;
-    
-    // Convert back to u64, checking for overflow
-    u64::try_from(amount_out).map_err(|_| AmmError::InvalidSwapCalculation.into())
-}
-
-fn calculate_lp_tokens(
-    token_a_amount: u64,
-    token_b_amount: u64,
-    pool: &Account<Pool>,
-) -> Result<u64> {
-    if pool.lp_supply == 0 {
-        // First liquidity provider
-        Ok(token_a_amount)
+    a_to_b: bool,
+) -> Result<SwapQuote> {
+    let fee = fee_rate.fee_on(amount_in)?;
+    // Execution price out / in_after_fee compared with the marginal price
+    let amount_in_after_fee = amount_in - fee;
+    let price_impact_bps = if amount_in_after_fee == 0 || reserve_in == 0 {
+        0
     } else {
-        // Calculate proportional LP tokens
-        let lp_tokens_a = token_a_amount
-            .checked_mul(pool.lp_supply)
-            .unwrap()
-            .checked_div(pool.token_a_amount)
-            .unwrap();
-        let lp_tokens_b = token_b_amount
-            .checked_mul(pool.lp_supply)
-            .unwrap()
-            .checked_div(pool.token_b_amount)
-            .unwrap();
-        
-        Ok(lp_tokens_a.min(lp_tokens_b))
+        let spot = curve.spot_price(reserve_in, reserve_out, a_to_b)?;
+        let execution = ((amount_out as u128) << 64) / amount_in_after_fee as u128;
+        bps_below(execution, spot)
+    };
+
+    Ok(SwapQuote {
+        amount_in,
+        amount_out,
+        fee,
+        fee_bps: fee_rate.bps(),
+        price_impact_bps,
+        reserve_in_after: reserve_in
+            .checked_add(amount_in)
+            .ok_or(AmmError::InvalidSwapCalculation)?,
+        reserve_out_after: reserve_out
+            .checked_sub(amount_out)
+            .ok_or(AmmError::InvalidSwapCalculation)?,
+    })
+}
+
+/// How far `value` sits below `reference`, in basis points of `reference`
+fn bps_below(value: u128, reference: u128) -> u64 {
+    if reference == 0 || value >= reference {
+        return 0;
     }
+    let shortfall = reference - value;
+    let bps = match shortfall.checked_mul(10_000) {
+        Some(scaled) => scaled / reference,
+        None => shortfall / (reference / 10_000),
+    };
+    bps as u64
 }
 
+/// Q64.64 spot prices of A in B and of B in A
+fn spot_prices(pool: &Pool) -> (u128, u128) {
+    if pool.token_a_amount == 0 || pool.token_b_amount == 0 {
+        return (0, 0);
+    }
+    let Ok(curve) = pool.swap_curve() else {
+        return (0, 0);
+    };
+    (
+        curve.spot_price(pool.token_a_amount, pool.token_b_amount, true).unwrap_or(0),
+        curve.spot_price(pool.token_b_amount, pool.token_a_amount, false).unwrap_or(0),
+    )
+}
+
+/// Seed a new pool's price oracle with a zero observation at the creation
+/// slot and bind its stats account
+fn seed_pool_accounts(pool: Pubkey, slot: u64, observations: &mut Observations, stats: &mut PoolStats) {
+    observations.pool = pool;
+    observations.write(Observation {
+        slot,
+        price_a_cumulative: 0,
+        price_b_cumulative: 0,
+    });
+    stats.pool = pool;
+}
+
+/// Accumulate the pre-trade price on the first touch of each slot and record
+/// an observation. Accumulators wrap, so only differences are meaningful.
+fn update_price_accumulators(
+    pool: &mut Pool,
+    observations: &mut Observations,
+    slot: u64,
+) {
+    if slot <= pool.last_update_slot {
+        return;
+    }
+    let elapsed = (slot - pool.last_update_slot) as u128;
+    let (price_a, price_b) = spot_prices(pool);
+    pool.price_a_cumulative = pool.price_a_cumulative.wrapping_add(price_a.wrapping_mul(elapsed));
+    pool.price_b_cumulative = pool.price_b_cumulative.wrapping_add(price_b.wrapping_mul(elapsed));
+    pool.last_update_slot = slot;
+
+    observations.write(Observation {
+        slot,
+        price_a_cumulative: pool.price_a_cumulative,
+        price_b_cumulative: pool.price_b_cumulative,
+    });
+}
+
+/// Hook-aware `transfer_checked` for pool types that keep their vault
+/// accounts in typed fields; zero amounts are skipped
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn transfer_with_hooks<'info>(
+    token_program: &Program<'info, Token2022>,
+    from: &InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    to: &InterfaceAccount<'info, TokenAccount>,
+    authority: AccountInfo<'info>,
+    all_accounts: &[AccountInfo<'info>],
+    amount: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+    invoke_transfer_checked(
+        &token_program.key(),
+        from.to_account_info(),
+        mint.to_account_info(),
+        to.to_account_info(),
+        authority,
+        all_accounts,
+        amount,
+        mint.decimals,
+        signer_seeds,
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn empty_pool() -> Pool {
+        Pool::try_deserialize_unchecked(&mut &vec![0u8; 8 + Pool::INIT_SPACE][..]).unwrap()
+    }
+
+    #[test]
+    fn lock_rejects_a_locked_pool() {
+        let mut pool = empty_pool();
+        pool.lock().unwrap();
+        assert!(pool.locked);
+        assert_eq!(pool.lock().unwrap_err(), AmmError::PoolLocked.into());
+
+        pool.locked = false;
+        pool.lock().unwrap();
+    }
+}
@@ -0,0 +1,721 @@
+//! Oracle-anchored proactive market maker. Pools quote around an external
+//! price instead of their reserves. Traders get the oracle price less a
+//! spread, shifted against whichever side the pool already holds too much of.
+//! `PriceFeed` is a minimal program-owned oracle that an authority pushes
+//! prices into.
+
+use anchor_lang::prelude::*;
+use anchor_spl::token_2022::Token2022;
+use anchor_spl::token_interface::{Mint, TokenAccount};
+
+use crate::curve::U256;
+use crate::{transfer_with_hooks, Amm, AmmError};
+
+const BPS: u128 = 10_000;
+pub const MAX_SPREAD_BPS: u16 = 1_000;
+/// Caps the inventory adjustment so the skewed price stays positive
+pub const MAX_SKEW_BPS: u16 = 5_000;
+
+#[account]
+#[derive(InitSpace)]
+pub struct PriceFeed {
+    pub authority: Pubkey,
+    pub base_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    pub price_x64: u128,       // Quote per base unit, Q64.64
+    pub confidence_x64: u128,  // One-sided uncertainty of `price_x64`
+    pub publish_ts: i64,
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
+pub struct PmmParams {
+    pub spread_bps: u16,       // Taken off the skewed price on each side
+    pub skew_bps: u16,         // Price shift at a fully one-sided inventory
+    pub max_confidence_bps: u16, // Widest confidence accepted, relative to price
+    pub max_staleness_secs: u32,
+}
+
+impl PmmParams {
+    fn validate(&self) -> Result<()> {
+        require!(
+            self.spread_bps <= MAX_SPREAD_BPS
+                && self.skew_bps <= MAX_SKEW_BPS
+                && self.max_confidence_bps as u128 <= BPS
+                && self.max_staleness_secs > 0,
+            AmmError::InvalidCurveParameters
+        );
+        Ok(())
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct PmmPool {
+    pub token_a_mint: Pubkey,  // The oracle's base mint
+    pub token_b_mint: Pubkey,  // The oracle's quote mint
+    pub token_a_vault: Pubkey,
+    pub token_b_vault: Pubkey,
+    pub token_a_amount: u64,
+    pub token_b_amount: u64,
+    pub oracle: Pubkey,
+    pub owner: Pubkey,         // The market maker; the only liquidity provider
+    pub params: PmmParams,
+    pub locked: bool,
+    pub bump: u8,
+}
+
+impl PmmPool {
+    /// Oracle price after confidence and staleness checks
+    pub fn oracle_price(&self, feed: &PriceFeed, now: i64) -> Result<u128> {
+        require!(
+            now.saturating_sub(feed.publish_ts) <= self.params.max_staleness_secs as i64,
+            AmmError::OracleStale
+        );
+        require!(
+            feed.price_x64 > 0
+                && U256::from(feed.confidence_x64) * U256::from(BPS)
+                    <= U256::from(feed.price_x64) * U256::from(self.params.max_confidence_bps),
+            AmmError::OracleConfidenceTooWide
+        );
+        Ok(feed.price_x64)
+    }
+
+    /// Oracle price shifted by the inventory imbalance at the given reserves,
+    /// valued at the oracle price
+    fn skewed_price(&self, oracle_price: u128, reserve_a: u64, reserve_b: u64) -> Result<u128> {
+        let value_a = (U256::from(reserve_a) * U256::from(oracle_price)) >> 64;
+        let value_b = U256::from(reserve_b);
+        let total = value_a + value_b;
+        if total.is_zero() {
+            return Ok(oracle_price);
+        }
+        // Excess A makes A cheaper and excess B makes it dearer
+        let skew = U256::from(self.params.skew_bps);
+        let factor = if value_a >= value_b {
+            U256::from(BPS) * total - skew * (value_a - value_b)
+        } else {
+            U256::from(BPS) * total + skew * (value_b - value_a)
+        };
+        u128::try_from(U256::from(oracle_price) * factor / (U256::from(BPS) * total))
+            .map_err(|_| AmmError::InvalidSwapCalculation.into())
+    }
+
+    /// Output for `amount_in` (after fees) at the skewed price less the
+    /// spread. Skew is taken at both the pre- and post-trade inventory and
+    /// the worse price for the trader applies, so splitting or bundling
+    /// trades gains nothing.
+    pub fn swap_exact_in(&self, oracle_price: u128, amount_in: u64, a_to_b: bool) -> Result<(u64, u128)> {
+        let spread = self.params.spread_bps as u128;
+        let quote = |reserve_a: u64, reserve_b: u64| -> Result<(u64, u128)> {
+            let mid = self.skewed_price(oracle_price, reserve_a, reserve_b)?;
+            let (price, out) = if a_to_b {
+                let bid = mid
+                    .checked_mul(BPS - spread)
+                    .ok_or(AmmError::InvalidSwapCalculation)?
+                    / BPS;
+                (bid, (U256::from(amount_in) * U256::from(bid)) >> 64)
+            } else {
+                let ask = mid
+                    .checked_mul(BPS + spread)
+                    .ok_or(AmmError::InvalidSwapCalculation)?
+                    / BPS;
+                require!(ask > 0, AmmError::InvalidSwapCalculation);
+                (ask, (U256::from(amount_in) << 64) / U256::from(ask))
+            };
+            let out = u64::try_from(out).map_err(|_| AmmError::InvalidSwapCalculation)?;
+            Ok((out, price))
+        };
+
+        let (reserve_in, reserve_out) = if a_to_b {
+            (self.token_a_amount, self.token_b_amount)
+        } else {
+            (self.token_b_amount, self.token_a_amount)
+        };
+        let before = quote(self.token_a_amount, self.token_b_amount)?;
+        let estimate = before.0;
+        require!(estimate < reserve_out, AmmError::InvalidSwapCalculation);
+        let reserve_in_after = reserve_in
+            .checked_add(amount_in)
+            .ok_or(AmmError::InvalidSwapCalculation)?;
+        let after = if a_to_b {
+            quote(reserve_in_after, reserve_out - estimate)?
+        } else {
+            quote(reserve_out - estimate, reserve_in_after)?
+        };
+        Ok(if after.0 < estimate { after } else { before })
+    }
+}
+
+pub(crate) fn initialize_price_feed(
+    ctx: Context<InitializePriceFeed>,
+    base_mint: Pubkey,
+    quote_mint: Pubkey,
+) -> Result<()> {
+    let feed = &mut ctx.accounts.feed;
+    feed.authority = ctx.accounts.authority.key();
+    feed.base_mint = base_mint;
+    feed.quote_mint = quote_mint;
+    feed.bump = ctx.bumps.feed;
+    Ok(())
+}
+
+pub(crate) fn update_price_feed(ctx: Context<UpdatePriceFeed>, price_x64: u128, confidence_x64: u128) -> Result<()> {
+    let feed = &mut ctx.accounts.feed;
+    feed.price_x64 = price_x64;
+    feed.confidence_x64 = confidence_x64;
+    feed.publish_ts = Clock::get()?.unix_timestamp;
+    Ok(())
+}
+
+pub(crate) fn create_pmm_pool<'info>(
+    ctx: Context<'_, '_, '_, 'info, CreatePmmPool<'info>>,
+    params: PmmParams,
+    token_a_amount: u64,
+    token_b_amount: u64,
+) -> Result<()> {
+    params.validate()?;
+    let pool = &mut ctx.accounts.pool;
+    pool.token_a_mint = ctx.accounts.token_a_mint.key();
+    pool.token_b_mint = ctx.accounts.token_b_mint.key();
+    pool.token_a_vault = ctx.accounts.token_a_vault.key();
+    pool.token_b_vault = ctx.accounts.token_b_vault.key();
+    pool.oracle = ctx.accounts.oracle.key();
+    pool.owner = ctx.accounts.owner.key();
+    pool.params = params;
+    pool.bump = ctx.bumps.pool;
+    deposit(ctx.accounts, ctx.remaining_accounts, token_a_amount, token_b_amount)?;
+
+    emit_cpi!(PmmPoolCreated {
+        pool: ctx.accounts.pool.key(),
+        oracle: ctx.accounts.pool.oracle,
+        owner: ctx.accounts.pool.owner,
+        token_a_mint: ctx.accounts.pool.token_a_mint,
+        token_b_mint: ctx.accounts.pool.token_b_mint,
+        params,
+        token_a_amount,
+        token_b_amount,
+    });
+    Ok(())
+}
+
+pub(crate) fn deposit_pmm_liquidity<'info>(
+    ctx: Context<'_, '_, '_, 'info, PmmLiquidity<'info>>,
+    token_a_amount: u64,
+    token_b_amount: u64,
+) -> Result<()> {
+    let mut all_accounts = liquidity_accounts(ctx.accounts);
+    all_accounts.extend(ctx.remaining_accounts.iter().cloned());
+    let accounts = &mut *ctx.accounts;
+    lock(&mut accounts.pool)?;
+    for (from, mint, to, amount) in [
+        (&accounts.owner_token_a, &accounts.token_a_mint, &accounts.token_a_vault, token_a_amount),
+        (&accounts.owner_token_b, &accounts.token_b_mint, &accounts.token_b_vault, token_b_amount),
+    ] {
+        transfer_with_hooks(
+            &accounts.token_program,
+            from,
+            mint,
+            to,
+            accounts.owner.to_account_info(),
+            &all_accounts,
+            amount,
+            &[],
+        )?;
+    }
+    let pool = &mut accounts.pool;
+    pool.token_a_amount = pool
+        .token_a_amount
+        .checked_add(token_a_amount)
+        .ok_or(AmmError::InvalidLiquidityCalculation)?;
+    pool.token_b_amount = pool
+        .token_b_amount
+        .checked_add(token_b_amount)
+        .ok_or(AmmError::InvalidLiquidityCalculation)?;
+    pool.locked = false;
+
+    emit_cpi!(PmmLiquidityChanged {
+        pool: ctx.accounts.pool.key(),
+        deposit: true,
+        token_a_amount,
+        token_b_amount,
+    });
+    Ok(())
+}
+
+pub(crate) fn withdraw_pmm_liquidity<'info>(
+    ctx: Context<'_, '_, '_, 'info, PmmLiquidity<'info>>,
+    token_a_amount: u64,
+    token_b_amount: u64,
+) -> Result<()> {
+    let mut all_accounts = liquidity_accounts(ctx.accounts);
+    all_accounts.extend(ctx.remaining_accounts.iter().cloned());
+    let accounts = &mut *ctx.accounts;
+    let pool = &mut accounts.pool;
+    pool.token_a_amount = pool
+        .token_a_amount
+        .checked_sub(token_a_amount)
+        .ok_or(AmmError::InvalidLiquidityCalculation)?;
+    pool.token_b_amount = pool
+        .token_b_amount
+        .checked_sub(token_b_amount)
+        .ok_or(AmmError::InvalidLiquidityCalculation)?;
+    lock(pool)?;
+
+    let amm_seeds = [b"amm".as_ref(), std::slice::from_ref(&accounts.amm.bump)];
+    for (from, mint, to, amount) in [
+        (&accounts.token_a_vault, &accounts.token_a_mint, &accounts.owner_token_a, token_a_amount),
+        (&accounts.token_b_vault, &accounts.token_b_mint, &accounts.owner_token_b, token_b_amount),
+    ] {
+        transfer_with_hooks(
+            &accounts.token_program,
+            from,
+            mint,
+            to,
+            accounts.amm.to_account_info(),
+            &all_accounts,
+            amount,
+            &[&amm_seeds],
+        )?;
+    }
+    accounts.pool.locked = false;
+
+    emit_cpi!(PmmLiquidityChanged {
+        pool: ctx.accounts.pool.key(),
+        deposit: false,
+        token_a_amount,
+        token_b_amount,
+    });
+    Ok(())
+}
+
+pub(crate) fn pmm_swap<'info>(
+    ctx: Context<'_, '_, '_, 'info, PmmSwap<'info>>,
+    amount_in: u64,
+    minimum_amount_out: u64,
+    a_to_b: bool,
+) -> Result<()> {
+    let accounts = &mut *ctx.accounts;
+    let pool = &mut accounts.pool;
+    let oracle_price = pool.oracle_price(&accounts.oracle, Clock::get()?.unix_timestamp)?;
+    let fee = accounts.amm.fee_on(amount_in)?;
+    let (amount_out, price_x64) = pool.swap_exact_in(oracle_price, amount_in - fee, a_to_b)?;
+    require!(
+        amount_out >= minimum_amount_out && amount_out > 0,
+        AmmError::InsufficientOutputAmount
+    );
+
+    // The fee stays in the pool
+    let (reserve_in, reserve_out) = if a_to_b {
+        (pool.token_a_amount, pool.token_b_amount)
+    } else {
+        (pool.token_b_amount, pool.token_a_amount)
+    };
+    let reserve_in = reserve_in
+        .checked_add(amount_in)
+        .ok_or(AmmError::InvalidSwapCalculation)?;
+    let reserve_out = reserve_out - amount_out;
+    if a_to_b {
+        (pool.token_a_amount, pool.token_b_amount) = (reserve_in, reserve_out);
+    } else {
+        (pool.token_b_amount, pool.token_a_amount) = (reserve_in, reserve_out);
+    }
+    lock(pool)?;
+
+    let mut all_accounts = vec![
+        accounts.pool.to_account_info(),
+        accounts.amm.to_account_info(),
+        accounts.user.to_account_info(),
+        accounts.user_token_a.to_account_info(),
+        accounts.user_token_b.to_account_info(),
+        accounts.token_a_mint.to_account_info(),
+        accounts.token_b_mint.to_account_info(),
+        accounts.token_a_vault.to_account_info(),
+        accounts.token_b_vault.to_account_info(),
+        accounts.token_program.to_account_info(),
+    ];
+    all_accounts.extend(ctx.remaining_accounts.iter().cloned());
+
+    let (user_in, mint_in, vault_in, vault_out, mint_out, user_out) = if a_to_b {
+        (&accounts.user_token_a, &accounts.token_a_mint, &accounts.token_a_vault, &accounts.token_b_vault, &accounts.token_b_mint, &accounts.user_token_b)
+    } else {
+        (&accounts.user_token_b, &accounts.token_b_mint, &accounts.token_b_vault, &accounts.token_a_vault, &accounts.token_a_mint, &accounts.user_token_a)
+    };
+    transfer_with_hooks(
+        &accounts.token_program,
+        user_in,
+        mint_in,
+        vault_in,
+        accounts.user.to_account_info(),
+        &all_accounts,
+        amount_in,
+        &[],
+    )?;
+    let amm_seeds = [b"amm".as_ref(), std::slice::from_ref(&accounts.amm.bump)];
+    transfer_with_hooks(
+        &accounts.token_program,
+        vault_out,
+        mint_out,
+        user_out,
+        accounts.amm.to_account_info(),
+        &all_accounts,
+        amount_out,
+        &[&amm_seeds],
+    )?;
+    accounts.pool.locked = false;
+
+    let event = PmmSwapped {
+        pool: accounts.pool.key(),
+        user: accounts.user.key(),
+        a_to_b,
+        amount_in,
+        amount_out,
+        fee,
+        oracle_price_x64: oracle_price,
+        price_x64,
+    };
+    emit_cpi!(event);
+    Ok(())
+}
+
+fn lock(pool: &mut Account<PmmPool>) -> Result<()> {
+    pool.locked = true;
+    pool.exit(&crate::ID)
+}
+
+/// Pull the opening reserves from the owner
+fn deposit<'info>(
+    accounts: &mut CreatePmmPool<'info>,
+    remaining_accounts: &[AccountInfo<'info>],
+    token_a_amount: u64,
+    token_b_amount: u64,
+) -> Result<()> {
+    let mut all_accounts = vec![
+        accounts.pool.to_account_info(),
+        accounts.amm.to_account_info(),
+        accounts.owner.to_account_info(),
+        accounts.owner_token_a.to_account_info(),
+        accounts.owner_token_b.to_account_info(),
+        accounts.token_a_mint.to_account_info(),
+        accounts.token_b_mint.to_account_info(),
+        accounts.token_a_vault.to_account_info(),
+        accounts.token_b_vault.to_account_info(),
+        accounts.token_program.to_account_info(),
+    ];
+    all_accounts.extend(remaining_accounts.iter().cloned());
+
+    lock(&mut accounts.pool)?;
+    for (from, mint, to, amount) in [
+        (&accounts.owner_token_a, &accounts.token_a_mint, &accounts.token_a_vault, token_a_amount),
+        (&accounts.owner_token_b, &accounts.token_b_mint, &accounts.token_b_vault, token_b_amount),
+    ] {
+        transfer_with_hooks(
+            &accounts.token_program,
+            from,
+            mint,
+            to,
+            accounts.owner.to_account_info(),
+            &all_accounts,
+            amount,
+            &[],
+        )?;
+    }
+    let pool = &mut accounts.pool;
+    pool.token_a_amount = token_a_amount;
+    pool.token_b_amount = token_b_amount;
+    pool.locked = false;
+    Ok(())
+}
+
+fn liquidity_accounts<'info>(accounts: &PmmLiquidity<'info>) -> Vec<AccountInfo<'info>> {
+    vec![
+        accounts.pool.to_account_info(),
+        accounts.amm.to_account_info(),
+        accounts.owner.to_account_info(),
+        accounts.owner_token_a.to_account_info(),
+        accounts.owner_token_b.to_account_info(),
+        accounts.token_a_mint.to_account_info(),
+        accounts.token_b_mint.to_account_info(),
+        accounts.token_a_vault.to_account_info(),
+        accounts.token_b_vault.to_account_info(),
+        accounts.token_program.to_account_info(),
+    ]
+}
+
+#[derive(Accounts)]
+#[instruction(base_mint: Pubkey, quote_mint: Pubkey)]
+pub struct InitializePriceFeed<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + PriceFeed::INIT_SPACE,
+        seeds = [b"price-feed", authority.key().as_ref(), base_mint.as_ref(), quote_mint.as_ref()],
+        bump
+    )]
+    pub feed: Account<'info, PriceFeed>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdatePriceFeed<'info> {
+    #[account(mut, has_one = authority @ AmmError::Unauthorized)]
+    pub feed: Account<'info, PriceFeed>,
+    pub authority: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CreatePmmPool<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + PmmPool::INIT_SPACE,
+        seeds = [b"pmm-pool", token_a_mint.key().as_ref(), token_b_mint.key().as_ref(), oracle.key().as_ref()],
+        bump
+    )]
+    pub pool: Box<Account<'info, PmmPool>>,
+    #[account(seeds = [b"amm"], bump = amm.bump)]
+    pub amm: Box<Account<'info, Amm>>,
+    #[account(
+        constraint = oracle.base_mint == token_a_mint.key()
+            && oracle.quote_mint == token_b_mint.key() @ AmmError::InvalidTokenPair
+    )]
+    pub oracle: Box<Account<'info, PriceFeed>>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(mut)]
+    pub owner_token_a: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut)]
+    pub owner_token_b: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_a_mint: Box<InterfaceAccount<'info, Mint>>,
+    pub token_b_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        token::mint = token_a_mint,
+        token::authority = amm
+    )]
+    pub token_a_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::mint = token_b_mint,
+        token::authority = amm
+    )]
+    pub token_b_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token2022>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct PmmLiquidity<'info> {
+    #[account(
+        mut,
+        has_one = owner @ AmmError::Unauthorized,
+        constraint = !pool.locked @ AmmError::PoolLocked
+    )]
+    pub pool: Box<Account<'info, PmmPool>>,
+    #[account(seeds = [b"amm"], bump = amm.bump)]
+    pub amm: Box<Account<'info, Amm>>,
+    pub owner: Signer<'info>,
+
+    #[account(mut)]
+    pub owner_token_a: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut)]
+    pub owner_token_b: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(address = pool.token_a_mint @ AmmError::InvalidTokenPair)]
+    pub token_a_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(address = pool.token_b_mint @ AmmError::InvalidTokenPair)]
+    pub token_b_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(mut, address = pool.token_a_vault @ AmmError::InvalidTokenPair)]
+    pub token_a_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut, address = pool.token_b_vault @ AmmError::InvalidTokenPair)]
+    pub token_b_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token2022>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct PmmSwap<'info> {
+    #[account(
+        mut,
+        has_one = oracle @ AmmError::InvalidTokenPair,
+        constraint = !pool.locked @ AmmError::PoolLocked
+    )]
+    pub pool: Box<Account<'info, PmmPool>>,
+    #[account(seeds = [b"amm"], bump = amm.bump)]
+    pub amm: Box<Account<'info, Amm>>,
+    pub oracle: Box<Account<'info, PriceFeed>>,
+    pub user: Signer<'info>,
+
+    #[account(mut)]
+    pub user_token_a: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut)]
+    pub user_token_b: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(address = pool.token_a_mint @ AmmError::InvalidTokenPair)]
+    pub token_a_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(address = pool.token_b_mint @ AmmError::InvalidTokenPair)]
+    pub token_b_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(mut, address = pool.token_a_vault @ AmmError::InvalidTokenPair)]
+    pub token_a_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut, address = pool.token_b_vault @ AmmError::InvalidTokenPair)]
+    pub token_b_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token2022>,
+}
+
+#[event]
+pub struct PmmPoolCreated {
+    pub pool: Pubkey,
+    pub oracle: Pubkey,
+    pub owner: Pubkey,
+    pub token_a_mint: Pubkey,
+    pub token_b_mint: Pubkey,
+    pub params: PmmParams,
+    pub token_a_amount: u64,
+    pub token_b_amount: u64,
+}
+
+#[event]
+pub struct PmmSwapped {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub a_to_b: bool,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee: u64,
+    pub oracle_price_x64: u128,
+    pub price_x64: u128,       // Price the trade filled at, B per A
+}
+
+#[event]
+pub struct PmmLiquidityChanged {
+    pub pool: Pubkey,
+    pub deposit: bool,         // False for withdrawals
+    pub token_a_amount: u64,
+    pub token_b_amount: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ONE: u128 = 1 << 64;
+    const NOW: i64 = 1_700_000_000;
+
+    /// The oracle price is 2 throughout, so 1_000 A balances 2_000 B
+    fn pool(skew_bps: u16, token_a_amount: u64, token_b_amount: u64) -> PmmPool {
+        PmmPool {
+            token_a_mint: Pubkey::default(),
+            token_b_mint: Pubkey::default(),
+            token_a_vault: Pubkey::default(),
+            token_b_vault: Pubkey::default(),
+            token_a_amount,
+            token_b_amount,
+            oracle: Pubkey::default(),
+            owner: Pubkey::default(),
+            params: PmmParams {
+                spread_bps: 30,
+                skew_bps,
+                max_confidence_bps: 50,
+                max_staleness_secs: 60,
+            },
+            locked: false,
+            bump: 0,
+        }
+    }
+
+    fn feed(price_x64: u128, confidence_x64: u128, publish_ts: i64) -> PriceFeed {
+        PriceFeed {
+            authority: Pubkey::default(),
+            base_mint: Pubkey::default(),
+            quote_mint: Pubkey::default(),
+            price_x64,
+            confidence_x64,
+            publish_ts,
+            bump: 0,
+        }
+    }
+
+    /// Output at the skewed price of the given inventory, less the spread
+    fn quote_at(pool: &PmmPool, amount_in: u64, a_to_b: bool, reserve_a: u64, reserve_b: u64) -> u64 {
+        let mid = pool.skewed_price(2 * ONE, reserve_a, reserve_b).unwrap();
+        let spread = pool.params.spread_bps as u128;
+        if a_to_b {
+            ((amount_in as u128 * (mid * (BPS - spread) / BPS)) >> 64) as u64
+        } else {
+            (((amount_in as u128) << 64) / (mid * (BPS + spread) / BPS)) as u64
+        }
+    }
+
+    #[test]
+    fn oracle_price_rejects_stale_and_uncertain_feeds() {
+        let pool = pool(0, 0, 0);
+        assert_eq!(pool.oracle_price(&feed(2 * ONE, 0, NOW - 60), NOW).unwrap(), 2 * ONE);
+        let err = pool.oracle_price(&feed(2 * ONE, 0, NOW - 61), NOW).unwrap_err();
+        assert_eq!(err, AmmError::OracleStale.into());
+
+        // 50 bps of 2.0 is 0.01
+        assert!(pool.oracle_price(&feed(2 * ONE, ONE / 100, NOW), NOW).is_ok());
+        let err = pool.oracle_price(&feed(2 * ONE, ONE / 100 + 1, NOW), NOW).unwrap_err();
+        assert_eq!(err, AmmError::OracleConfidenceTooWide.into());
+        let err = pool.oracle_price(&feed(0, 0, NOW), NOW).unwrap_err();
+        assert_eq!(err, AmmError::OracleConfidenceTooWide.into());
+    }
+
+    #[test]
+    fn skew_moves_the_price_against_the_heavy_side() {
+        let pool = pool(1_000, 0, 0);
+        assert_eq!(pool.skewed_price(2 * ONE, 0, 0).unwrap(), 2 * ONE);
+        assert_eq!(pool.skewed_price(2 * ONE, 1_000, 2_000).unwrap(), 2 * ONE);
+        // Fully one-sided inventory shifts the price by the whole 10%
+        assert_eq!(pool.skewed_price(2 * ONE, 1_000, 0).unwrap(), 2 * ONE * 9 / 10);
+        assert_eq!(pool.skewed_price(2 * ONE, 0, 2_000).unwrap(), 2 * ONE * 11 / 10);
+        // Three quarters of the value in A shifts it by half
+        assert_eq!(pool.skewed_price(2 * ONE, 3_000, 2_000).unwrap(), 2 * ONE * 19 / 20);
+    }
+
+    #[test]
+    fn swap_fills_at_the_worse_of_pre_and_post_trade_skew() {
+        // Without skew both quotes agree, and the spread alone applies
+        let flat = pool(0, 1_000_000, 2_000_000);
+        let (out, price) = flat.swap_exact_in(2 * ONE, 10_000, true).unwrap();
+        assert_eq!(price, 2 * ONE * 9_970 / 10_000);
+        // 10_000 * 1.994, rounded down
+        assert_eq!(out, 19_939);
+
+        // With skew each trade tips the inventory against itself, so the
+        // post-trade quote applies
+        let skewed = pool(1_000, 1_000_000, 2_000_000);
+        for a_to_b in [true, false] {
+            let amount_in = 100_000;
+            let (out, _) = skewed.swap_exact_in(2 * ONE, amount_in, a_to_b).unwrap();
+            let before = quote_at(&skewed, amount_in, a_to_b, 1_000_000, 2_000_000);
+            let after = if a_to_b {
+                quote_at(&skewed, amount_in, a_to_b, 1_000_000 + amount_in, 2_000_000 - before)
+            } else {
+                quote_at(&skewed, amount_in, a_to_b, 1_000_000 - before, 2_000_000 + amount_in)
+            };
+            assert!(after < before);
+            assert_eq!(out, after);
+        }
+    }
+
+    #[test]
+    fn swap_rejects_overflow_and_draining_the_pool() {
+        let empty = pool(0, 0, 0);
+        let err = empty.swap_exact_in(u128::MAX, 1, true).unwrap_err();
+        assert_eq!(err, AmmError::InvalidSwapCalculation.into());
+        let err = empty.swap_exact_in(u128::MAX, 1, false).unwrap_err();
+        assert_eq!(err, AmmError::InvalidSwapCalculation.into());
+
+        let shallow = pool(0, 1_000, 1_000);
+        let err = shallow.swap_exact_in(2 * ONE, 1_000, true).unwrap_err();
+        assert_eq!(err, AmmError::InvalidSwapCalculation.into());
+    }
+}
@@ -0,0 +1,268 @@
+//! Attestation-gated transfers. A mint with a `CredentialConfig` only moves
+//! between owners that each hold an unexpired, unrevoked `Attestation` from
+//! one of the mint's approved issuers. Attestations are PDAs of the mint and
+//! owner, so Token-2022 resolves both parties' from the token accounts.
+//!
+//! Each approval of an issuer starts a new issuer epoch, and an attestation
+//! only counts under the epoch it was issued in. Removing an issuer and
+//! approving it again therefore does not revive what it issued before.
+
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::Mint;
+use spl_tlv_account_resolution::{account::ExtraAccountMeta, seeds::Seed};
+
+use crate::{
+    find_extra_account, find_mint_account, is_mint_admin, HookConfig, MintAccount, TokenHookError, CONFIG_SEED,
+};
+
+pub const CREDENTIALS_SEED: &[u8] = b"credentials";
+pub const ATTESTATION_SEED: &[u8] = b"attestation";
+pub const MAX_ISSUERS: usize = 8;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
+pub struct Issuer {
+    pub key: Pubkey,
+    pub epoch: u64, // When it was approved
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct CredentialConfig {
+    pub mint: Pubkey,
+    #[max_len(MAX_ISSUERS)]
+    pub issuers: Vec<Issuer>,
+    pub issuer_epoch: u64, // Of the latest issuer approval
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Attestation {
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    pub issuer: Pubkey,
+    pub issuer_epoch: u64, // The issuer's epoch when it issued this
+    pub expires_at: i64,
+    pub revoked: bool,
+    pub bump: u8,
+}
+
+impl CredentialConfig {
+    /// The epoch `key` was approved in, if it is an approved issuer
+    pub fn issuer_epoch_of(&self, key: &Pubkey) -> Option<u64> {
+        self.issuers.iter().find(|issuer| issuer.key == *key).map(|issuer| issuer.epoch)
+    }
+
+    /// The ExtraAccountMetaList entries resolving to the mint's credential
+    /// config and to the attestations of the source and destination owners
+    pub fn extra_account_metas() -> Result<[ExtraAccountMeta; 3]> {
+        let attestation_of = |account_index: u8| {
+            ExtraAccountMeta::new_with_seeds(
+                &[
+                    Seed::Literal {
+                        bytes: ATTESTATION_SEED.to_vec(),
+                    },
+                    Seed::AccountKey { index: 1 },
+                    // The owner field of the token account
+                    Seed::AccountData {
+                        account_index,
+                        data_index: 32,
+                        length: 32,
+                    },
+                ],
+                false,
+                false,
+            )
+        };
+        Ok([
+            ExtraAccountMeta::new_with_seeds(
+                &[
+                    Seed::Literal {
+                        bytes: CREDENTIALS_SEED.to_vec(),
+                    },
+                    Seed::AccountKey { index: 1 }, // The mint
+                ],
+                false,
+                false,
+            )?,
+            attestation_of(0)?,
+            attestation_of(2)?,
+        ])
+    }
+
+    /// Fail unless `owner` holds a valid credential for the mint
+    fn check(&self, accounts: &[AccountInfo], owner: &Pubkey, now: i64) -> Result<()> {
+        let (_, attestation) = find_extra_account::<Attestation>(accounts, |attestation| {
+            attestation.mint == self.mint && attestation.owner == *owner
+        })?
+        .ok_or(TokenHookError::AttestationMissing)?;
+        // Dropping an issuer revokes everything it issued, for good
+        require!(
+            !attestation.revoked && self.issuer_epoch_of(&attestation.issuer) == Some(attestation.issuer_epoch),
+            TokenHookError::AttestationRevoked
+        );
+        require!(now < attestation.expires_at, TokenHookError::AttestationExpired);
+        Ok(())
+    }
+}
+
+impl MintAccount for CredentialConfig {
+    fn mint(&self) -> &Pubkey {
+        &self.mint
+    }
+}
+
+/// Require credentials of both parties if the mint has a credential config
+pub(crate) fn enforce_attestations(
+    accounts: &[AccountInfo],
+    mint: &Pubkey,
+    source_owner: &Pubkey,
+    destination_owner: &Pubkey,
+) -> Result<()> {
+    let Some(config) = find_mint_account::<CredentialConfig>(accounts, mint)? else {
+        return Ok(());
+    };
+    let now = Clock::get()?.unix_timestamp;
+    config.check(accounts, source_owner, now)?;
+    config.check(accounts, destination_owner, now)
+}
+
+pub(crate) fn initialize_credentials(ctx: Context<InitializeCredentials>) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+    config.mint = ctx.accounts.mint.key();
+    config.issuers = Vec::new();
+    config.issuer_epoch = 0;
+    config.bump = ctx.bumps.config;
+    msg!("Credential config initialized for mint: {}", config.mint);
+    Ok(())
+}
+
+pub(crate) fn add_issuer(ctx: Context<UpdateCredentials>, issuer: Pubkey) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+    if config.issuer_epoch_of(&issuer).is_none() {
+        require!(config.issuers.len() < MAX_ISSUERS, TokenHookError::TooManyIssuers);
+        config.issuer_epoch += 1;
+        let epoch = config.issuer_epoch;
+        config.issuers.push(Issuer { key: issuer, epoch });
+    }
+    Ok(())
+}
+
+pub(crate) fn remove_issuer(ctx: Context<UpdateCredentials>, issuer: Pubkey) -> Result<()> {
+    ctx.accounts.config.issuers.retain(|approved| approved.key != issuer);
+    Ok(())
+}
+
+pub(crate) fn issue_attestation(ctx: Context<IssueAttestation>, owner: Pubkey, expires_at: i64) -> Result<()> {
+    let issuer_epoch = ctx
+        .accounts
+        .config
+        .issuer_epoch_of(&ctx.accounts.issuer.key())
+        .ok_or(TokenHookError::Unauthorized)?;
+    let attestation = &mut ctx.accounts.attestation;
+    // Renewing needs the original issuer, and lifting a revocation the
+    // config authority; the authority may also reassign the issuer
+    let issued = attestation.issuer != Pubkey::default();
+    if issued && ctx.accounts.authority.is_none() {
+        require!(!attestation.revoked, TokenHookError::AttestationRevoked);
+        require_keys_eq!(
+            attestation.issuer,
+            ctx.accounts.issuer.key(),
+            TokenHookError::Unauthorized
+        );
+    }
+    attestation.mint = ctx.accounts.config.mint;
+    attestation.owner = owner;
+    attestation.issuer = ctx.accounts.issuer.key();
+    attestation.issuer_epoch = issuer_epoch;
+    attestation.expires_at = expires_at;
+    attestation.revoked = false;
+    attestation.bump = ctx.bumps.attestation;
+    Ok(())
+}
+
+pub(crate) fn revoke_attestation(ctx: Context<RevokeAttestation>) -> Result<()> {
+    ctx.accounts.attestation.revoked = true;
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct InitializeCredentials<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        constraint = is_mint_admin(&mint, &authority.key())? @ TokenHookError::Unauthorized
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + CredentialConfig::INIT_SPACE,
+        seeds = [CREDENTIALS_SEED, mint.key().as_ref()],
+        bump
+    )]
+    pub config: Account<'info, CredentialConfig>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateCredentials<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [CONFIG_SEED, config.mint.as_ref()],
+        bump = hook_config.bump,
+        has_one = authority @ TokenHookError::Unauthorized
+    )]
+    pub hook_config: Account<'info, HookConfig>,
+    #[account(mut)]
+    pub config: Account<'info, CredentialConfig>,
+}
+
+#[derive(Accounts)]
+#[instruction(owner: Pubkey)]
+pub struct IssueAttestation<'info> {
+    #[account(mut)]
+    pub issuer: Signer<'info>,
+    /// The hook config authority, needed to re-issue a revoked attestation
+    /// or one from another issuer
+    #[account(
+        constraint = authority.key() == hook_config.authority @ TokenHookError::Unauthorized
+    )]
+    pub authority: Option<Signer<'info>>,
+    #[account(seeds = [CONFIG_SEED, config.mint.as_ref()], bump = hook_config.bump)]
+    pub hook_config: Account<'info, HookConfig>,
+    #[account(
+        constraint = config.issuer_epoch_of(&issuer.key()).is_some() @ TokenHookError::Unauthorized
+    )]
+    pub config: Account<'info, CredentialConfig>,
+    /// Issuing again renews an existing attestation
+    #[account(
+        init_if_needed,
+        payer = issuer,
+        space = 8 + Attestation::INIT_SPACE,
+        seeds = [ATTESTATION_SEED, config.mint.as_ref(), owner.as_ref()],
+        bump
+    )]
+    pub attestation: Account<'info, Attestation>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeAttestation<'info> {
+    /// The attestation's issuer or the hook config authority
+    pub signer: Signer<'info>,
+    #[account(
+        seeds = [CONFIG_SEED, config.mint.as_ref()],
+        bump = hook_config.bump,
+        constraint = signer.key() == attestation.issuer
+            || signer.key() == hook_config.authority @ TokenHookError::Unauthorized
+    )]
+    pub hook_config: Account<'info, HookConfig>,
+    pub config: Account<'info, CredentialConfig>,
+    #[account(
+        mut,
+        seeds = [ATTESTATION_SEED, config.mint.as_ref(), attestation.owner.as_ref()],
+        bump = attestation.bump
+    )]
+    pub attestation: Account<'info, Attestation>,
+}
@@ -0,0 +1,306 @@
+//! Per-mint hook configuration. Whoever initializes a mint's
+//! ExtraAccountMetaList must administer the mint, and is recorded here as the
+//! authority for later changes to it and to the mint's transfer policy,
+//! velocity limit, launch guard, holder cap and credential config.
+//!
+//! Those modules are created by the mint's admins, its transfer-hook
+//! authority or mint authority, and only apply to transfers once the list
+//! resolves their accounts: create them before the list, or sync the list
+//! after. The same goes for sanctions screening once a root is set.
+
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::Mint;
+use spl_tlv_account_resolution::{account::ExtraAccountMeta, state::ExtraAccountMetaList};
+use spl_token_2022::extension::{
+    transfer_hook::TransferHook, BaseStateWithExtensions, StateWithExtensions,
+};
+use spl_transfer_hook_interface::instruction::ExecuteInstruction;
+
+use crate::{
+    CredentialConfig, MintAccount, HolderCap, LaunchGuard, TokenHookError, TransferPolicy, VelocityLimit, CREDENTIALS_SEED,
+    HOLDER_CAP_SEED, LAUNCH_SEED, POLICY_SEED, VELOCITY_SEED,
+};
+
+pub const CONFIG_SEED: &[u8] = b"hook-config";
+
+/// An `ExtraAccountMeta` as passed in instruction data, field for field
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct ExtraMeta {
+    pub discriminator: u8,
+    pub address_config: [u8; 32],
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+impl From<ExtraMeta> for ExtraAccountMeta {
+    fn from(meta: ExtraMeta) -> Self {
+        ExtraAccountMeta {
+            discriminator: meta.discriminator,
+            address_config: meta.address_config,
+            is_signer: meta.is_signer.into(),
+            is_writable: meta.is_writable.into(),
+        }
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct HookConfig {
+    pub mint: Pubkey,
+    pub authority: Pubkey, // Administers the mint's hook accounts and settings
+    pub sanctions_root: [u8; 32], // Zero while sanctions screening is off
+    pub sanctions_depth: u8, // Of the tree under `sanctions_root`
+    pub bump: u8,
+}
+
+impl MintAccount for HookConfig {
+    fn mint(&self) -> &Pubkey {
+        &self.mint
+    }
+}
+
+/// Whether `signer` is the mint's transfer-hook authority or its mint
+/// authority
+pub fn is_mint_admin(mint: &InterfaceAccount<Mint>, signer: &Pubkey) -> Result<bool> {
+    let info = mint.to_account_info();
+    let data = info.try_borrow_data()?;
+    let state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+    let hook_authority = state
+        .get_extension::<TransferHook>()
+        .ok()
+        .and_then(|hook| Option::<Pubkey>::from(hook.authority));
+    Ok(hook_authority.as_ref() == Some(signer)
+        || Option::<Pubkey>::from(mint.mint_authority).as_ref() == Some(signer))
+}
+
+/// Which of a mint's hook modules its ExtraAccountMetaList resolves
+#[derive(Default)]
+pub(crate) struct WiredModules {
+    pub policy: bool,
+    pub velocity_limit: bool,
+    pub launch_guard: bool,
+    pub holder_cap: bool,
+    pub credentials: bool,
+    pub sanctions: bool,
+}
+
+impl WiredModules {
+    /// The list entries for these modules, in a fixed order
+    pub fn account_metas(&self) -> Result<Vec<ExtraAccountMeta>> {
+        let mut account_metas = vec![];
+        if self.policy {
+            account_metas.push(TransferPolicy::extra_account_meta()?);
+        }
+        if self.velocity_limit {
+            account_metas.extend(VelocityLimit::extra_account_metas()?);
+        }
+        if self.launch_guard {
+            account_metas.extend(LaunchGuard::extra_account_metas()?);
+        }
+        if self.holder_cap {
+            account_metas.push(HolderCap::extra_account_meta()?);
+        }
+        if self.credentials {
+            account_metas.extend(CredentialConfig::extra_account_metas()?);
+        }
+        if self.sanctions {
+            account_metas.extend(HookConfig::sanctions_account_metas()?);
+        }
+        Ok(account_metas)
+    }
+}
+
+/// Replace the entries of `list`, growing it first and topping up its rent
+/// from `payer`, or shrinking it afterwards and refunding the excess
+fn write_account_metas<'info>(
+    list: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    account_metas: &[ExtraAccountMeta],
+) -> Result<()> {
+    let account_size = ExtraAccountMetaList::size_of(account_metas.len())?;
+    let lamports = Rent::get()?.minimum_balance(account_size);
+    if account_size > list.data_len() {
+        if lamports > list.lamports() {
+            anchor_lang::system_program::transfer(
+                CpiContext::new(
+                    system_program.clone(),
+                    anchor_lang::system_program::Transfer {
+                        from: payer.clone(),
+                        to: list.clone(),
+                    },
+                ),
+                lamports - list.lamports(),
+            )?;
+        }
+        list.realloc(account_size, false)?;
+    }
+
+    ExtraAccountMetaList::update::<ExecuteInstruction>(&mut list.try_borrow_mut_data()?, account_metas)?;
+
+    if account_size < list.data_len() {
+        list.realloc(account_size, false)?;
+        let excess = list.lamports().saturating_sub(lamports);
+        **list.try_borrow_mut_lamports()? -= excess;
+        **payer.try_borrow_mut_lamports()? += excess;
+    }
+    Ok(())
+}
+
+pub(crate) fn update_extra_account_meta_list(
+    ctx: Context<UpdateExtraAccountMetaList>,
+    metas: Vec<ExtraMeta>,
+) -> Result<()> {
+    let account_metas: Vec<ExtraAccountMeta> = metas.into_iter().map(Into::into).collect();
+    write_account_metas(
+        &ctx.accounts.extra_account_meta_list,
+        &ctx.accounts.authority.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        &account_metas,
+    )?;
+    msg!("ExtraAccountMetaList updated for mint: {}", ctx.accounts.config.mint);
+    Ok(())
+}
+
+pub(crate) fn sync_extra_account_meta_list(ctx: Context<SyncExtraAccountMetaList>) -> Result<()> {
+    let accounts = &ctx.accounts;
+    let exists = |info: &AccountInfo| info.owner == &crate::ID && !info.data_is_empty();
+    let account_metas = WiredModules {
+        policy: exists(&accounts.policy),
+        velocity_limit: exists(&accounts.velocity_limit),
+        launch_guard: exists(&accounts.launch_guard),
+        holder_cap: exists(&accounts.holder_cap),
+        credentials: exists(&accounts.credentials),
+        sanctions: accounts.config.sanctions_root != [0; 32],
+    }
+    .account_metas()?;
+    write_account_metas(
+        &accounts.extra_account_meta_list,
+        &accounts.authority.to_account_info(),
+        &accounts.system_program.to_account_info(),
+        &account_metas,
+    )?;
+    msg!("ExtraAccountMetaList synced for mint: {}", accounts.config.mint);
+    Ok(())
+}
+
+pub(crate) fn close_extra_account_meta_list(ctx: Context<CloseExtraAccountMetaList>) -> Result<()> {
+    let accounts = &ctx.accounts;
+    let authority = accounts.authority.to_account_info();
+    close_account(&accounts.extra_account_meta_list, &authority)?;
+    // Modules the mint never set up have nothing to close
+    for module in [
+        &accounts.policy,
+        &accounts.velocity_limit,
+        &accounts.launch_guard,
+        &accounts.holder_cap,
+        &accounts.credentials,
+    ] {
+        if module.owner == &crate::ID {
+            close_account(module, &authority)?;
+        }
+    }
+    msg!("ExtraAccountMetaList closed for mint: {}", accounts.config.mint);
+    Ok(())
+}
+
+/// Move an account's rent to `destination` and hand it back to the system
+/// program
+fn close_account(account: &AccountInfo, destination: &AccountInfo) -> Result<()> {
+    **destination.try_borrow_mut_lamports()? += account.lamports();
+    **account.try_borrow_mut_lamports()? = 0;
+    account.assign(&System::id());
+    account.realloc(0, false)?;
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct UpdateExtraAccountMetaList<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [CONFIG_SEED, config.mint.as_ref()],
+        bump = config.bump,
+        has_one = authority @ TokenHookError::Unauthorized
+    )]
+    pub config: Account<'info, HookConfig>,
+    /// CHECK: ExtraAccountMetaList Account, must use these seeds
+    #[account(
+        mut,
+        seeds = [b"extra-account-metas", config.mint.as_ref()],
+        bump
+    )]
+    pub extra_account_meta_list: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SyncExtraAccountMetaList<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [CONFIG_SEED, config.mint.as_ref()],
+        bump = config.bump,
+        has_one = authority @ TokenHookError::Unauthorized
+    )]
+    pub config: Account<'info, HookConfig>,
+    /// CHECK: ExtraAccountMetaList Account, must use these seeds
+    #[account(
+        mut,
+        seeds = [b"extra-account-metas", config.mint.as_ref()],
+        bump
+    )]
+    pub extra_account_meta_list: AccountInfo<'info>,
+    /// CHECK: The mint's transfer policy, wired in if it exists
+    #[account(seeds = [POLICY_SEED, config.mint.as_ref()], bump)]
+    pub policy: UncheckedAccount<'info>,
+    /// CHECK: The mint's velocity limit, wired in if it exists
+    #[account(seeds = [VELOCITY_SEED, config.mint.as_ref()], bump)]
+    pub velocity_limit: UncheckedAccount<'info>,
+    /// CHECK: The mint's launch guard, wired in if it exists
+    #[account(seeds = [LAUNCH_SEED, config.mint.as_ref()], bump)]
+    pub launch_guard: UncheckedAccount<'info>,
+    /// CHECK: The mint's holder cap, wired in if it exists
+    #[account(seeds = [HOLDER_CAP_SEED, config.mint.as_ref()], bump)]
+    pub holder_cap: UncheckedAccount<'info>,
+    /// CHECK: The mint's credential config, wired in if it exists
+    #[account(seeds = [CREDENTIALS_SEED, config.mint.as_ref()], bump)]
+    pub credentials: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseExtraAccountMetaList<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        close = authority,
+        seeds = [CONFIG_SEED, config.mint.as_ref()],
+        bump = config.bump,
+        has_one = authority @ TokenHookError::Unauthorized
+    )]
+    pub config: Account<'info, HookConfig>,
+    /// CHECK: ExtraAccountMetaList Account, must use these seeds
+    #[account(
+        mut,
+        seeds = [b"extra-account-metas", config.mint.as_ref()],
+        bump
+    )]
+    pub extra_account_meta_list: AccountInfo<'info>,
+    /// CHECK: The mint's transfer policy, closed if it exists
+    #[account(mut, seeds = [POLICY_SEED, config.mint.as_ref()], bump)]
+    pub policy: UncheckedAccount<'info>,
+    /// CHECK: The mint's velocity limit, closed if it exists
+    #[account(mut, seeds = [VELOCITY_SEED, config.mint.as_ref()], bump)]
+    pub velocity_limit: UncheckedAccount<'info>,
+    /// CHECK: The mint's launch guard, closed if it exists
+    #[account(mut, seeds = [LAUNCH_SEED, config.mint.as_ref()], bump)]
+    pub launch_guard: UncheckedAccount<'info>,
+    /// CHECK: The mint's holder cap, closed if it exists
+    #[account(mut, seeds = [HOLDER_CAP_SEED, config.mint.as_ref()], bump)]
+    pub holder_cap: UncheckedAccount<'info>,
+    /// CHECK: The mint's credential config, closed if it exists
+    #[account(mut, seeds = [CREDENTIALS_SEED, config.mint.as_ref()], bump)]
+    pub credentials: UncheckedAccount<'info>,
+}
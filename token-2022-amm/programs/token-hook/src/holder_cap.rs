@@ -0,0 +1,114 @@
+//! Maximum holder balance. A mint's `HolderCap` rejects any transfer that
+//! leaves the receiving account holding more than `max_bps` of supply, unless
+//! the account or its owner is exempt, such as AMM vaults or a treasury.
+
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::Mint;
+use spl_tlv_account_resolution::{account::ExtraAccountMeta, seeds::Seed};
+
+use crate::{add_key, is_mint_admin, remove_key, HookConfig, MintAccount, TokenHookError, CONFIG_SEED};
+
+pub const HOLDER_CAP_SEED: &[u8] = b"holder-cap";
+pub const MAX_HOLDER_CAP_EXEMPTIONS: usize = 16;
+const BPS: u128 = 10_000;
+
+#[account]
+#[derive(InitSpace)]
+pub struct HolderCap {
+    pub mint: Pubkey,
+    pub max_bps: u16, // Of supply
+    #[max_len(MAX_HOLDER_CAP_EXEMPTIONS)]
+    pub exempt: Vec<Pubkey>, // Token accounts or owners
+    pub bump: u8,
+}
+
+impl HolderCap {
+    /// Whether `account`, owned by `owner`, may hold `balance` out of `supply`
+    pub fn allows(&self, account: &Pubkey, owner: &Pubkey, balance: u64, supply: u64) -> bool {
+        self.exempt.iter().any(|e| e == account || e == owner)
+            || balance as u128 * BPS <= supply as u128 * self.max_bps as u128
+    }
+
+    /// The ExtraAccountMetaList entry resolving to the mint's cap
+    pub fn extra_account_meta() -> Result<ExtraAccountMeta> {
+        Ok(ExtraAccountMeta::new_with_seeds(
+            &[
+                Seed::Literal {
+                    bytes: HOLDER_CAP_SEED.to_vec(),
+                },
+                Seed::AccountKey { index: 1 }, // The mint
+            ],
+            false,
+            false,
+        )?)
+    }
+}
+
+impl MintAccount for HolderCap {
+    fn mint(&self) -> &Pubkey {
+        &self.mint
+    }
+}
+
+pub(crate) fn initialize_holder_cap(ctx: Context<InitializeHolderCap>, max_bps: u16) -> Result<()> {
+    require!(max_bps as u128 <= BPS, TokenHookError::InvalidHolderCap);
+    let cap = &mut ctx.accounts.cap;
+    cap.mint = ctx.accounts.mint.key();
+    cap.max_bps = max_bps;
+    cap.exempt = Vec::new();
+    cap.bump = ctx.bumps.cap;
+    msg!("Holder cap of {} bps initialized for mint: {}", max_bps, cap.mint);
+    Ok(())
+}
+
+pub(crate) fn set_holder_cap(ctx: Context<UpdateHolderCap>, max_bps: u16) -> Result<()> {
+    require!(max_bps as u128 <= BPS, TokenHookError::InvalidHolderCap);
+    ctx.accounts.cap.max_bps = max_bps;
+    Ok(())
+}
+
+pub(crate) fn add_holder_cap_exemption(ctx: Context<UpdateHolderCap>, account: Pubkey) -> Result<()> {
+    add_key(
+        &mut ctx.accounts.cap.exempt,
+        account,
+        MAX_HOLDER_CAP_EXEMPTIONS,
+        TokenHookError::ExemptionListFull,
+    )
+}
+
+pub(crate) fn remove_holder_cap_exemption(ctx: Context<UpdateHolderCap>, account: Pubkey) -> Result<()> {
+    remove_key(&mut ctx.accounts.cap.exempt, &account);
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct InitializeHolderCap<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        constraint = is_mint_admin(&mint, &authority.key())? @ TokenHookError::Unauthorized
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + HolderCap::INIT_SPACE,
+        seeds = [HOLDER_CAP_SEED, mint.key().as_ref()],
+        bump
+    )]
+    pub cap: Account<'info, HolderCap>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateHolderCap<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [CONFIG_SEED, cap.mint.as_ref()],
+        bump = hook_config.bump,
+        has_one = authority @ TokenHookError::Unauthorized
+    )]
+    pub hook_config: Account<'info, HookConfig>,
+    #[account(mut)]
+    pub cap: Account<'info, HolderCap>,
+}
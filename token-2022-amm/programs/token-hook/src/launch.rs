@@ -0,0 +1,186 @@
+//! Anti-sniper launch protections. Until `end_slot`, a transfer out of one of
+//! the mint's registered AMM vaults counts as a buy: it may not exceed
+//! `max_buy`, may not bring what the owner has bought during the launch past
+//! `max_holding`, and must come at least `cooldown_slots` after the same
+//! owner's previous buy. Buys are tallied per owner rather than read off the
+//! destination balance, so moving bought tokens away does not free up room.
+
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::Mint;
+use spl_tlv_account_resolution::{account::ExtraAccountMeta, seeds::Seed};
+
+use crate::{
+    add_key, find_extra_account, find_mint_account, is_mint_admin, remove_key, HookConfig, MintAccount, TokenHookError,
+    WalletState, CONFIG_SEED, WALLET_STATE_SEED,
+};
+
+pub const LAUNCH_SEED: &[u8] = b"launch";
+pub const MAX_LAUNCH_VAULTS: usize = 8;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
+pub struct LaunchParams {
+    pub end_slot: u64, // Restrictions lapse from this slot on
+    pub max_buy: u64,
+    pub max_holding: u64, // Most one owner may buy over the whole launch
+    pub cooldown_slots: u64,
+}
+
+impl LaunchParams {
+    fn validate(&self) -> Result<()> {
+        require!(
+            self.max_buy > 0 && self.max_holding >= self.max_buy,
+            TokenHookError::InvalidLaunchParams
+        );
+        Ok(())
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct LaunchGuard {
+    pub mint: Pubkey,
+    pub params: LaunchParams,
+    #[max_len(MAX_LAUNCH_VAULTS)]
+    pub vaults: Vec<Pubkey>, // Token accounts that transfers out of are buys
+    pub bump: u8,
+}
+
+impl LaunchGuard {
+    /// The ExtraAccountMetaList entries resolving to the mint's launch guard
+    /// and to the wallet state of the destination account's owner
+    pub fn extra_account_metas() -> Result<[ExtraAccountMeta; 2]> {
+        Ok([
+            ExtraAccountMeta::new_with_seeds(
+                &[
+                    Seed::Literal {
+                        bytes: LAUNCH_SEED.to_vec(),
+                    },
+                    Seed::AccountKey { index: 1 }, // The mint
+                ],
+                false,
+                false,
+            )?,
+            ExtraAccountMeta::new_with_seeds(
+                &[
+                    Seed::Literal {
+                        bytes: WALLET_STATE_SEED.to_vec(),
+                    },
+                    Seed::AccountKey { index: 1 },
+                    // The owner field of the destination token account
+                    Seed::AccountData {
+                        account_index: 2,
+                        data_index: 32,
+                        length: 32,
+                    },
+                ],
+                false,
+                true,
+            )?,
+        ])
+    }
+}
+
+impl MintAccount for LaunchGuard {
+    fn mint(&self) -> &Pubkey {
+        &self.mint
+    }
+}
+
+/// Check a buy against the launch limits while the launch phase lasts
+pub(crate) fn enforce_launch_guard(
+    accounts: &[AccountInfo],
+    mint: &Pubkey,
+    source_token: &Pubkey,
+    buyer: &Pubkey,
+    amount: u64,
+) -> Result<()> {
+    let Some(guard) = find_mint_account::<LaunchGuard>(accounts, mint)? else {
+        return Ok(());
+    };
+    let slot = Clock::get()?.slot;
+    if slot >= guard.params.end_slot || !guard.vaults.contains(source_token) {
+        return Ok(());
+    }
+
+    require!(amount <= guard.params.max_buy, TokenHookError::BuyTooLarge);
+
+    let (info, mut state) = find_extra_account::<WalletState>(accounts, |state| {
+        state.mint == *mint && state.owner == *buyer
+    })?
+    .ok_or(TokenHookError::WalletStateMissing)?;
+    state.bought = state
+        .bought
+        .checked_add(amount)
+        .filter(|bought| *bought <= guard.params.max_holding)
+        .ok_or(TokenHookError::HoldingTooLarge)?;
+    require!(
+        state.last_buy_slot == 0
+            || slot >= state.last_buy_slot.saturating_add(guard.params.cooldown_slots),
+        TokenHookError::BuyCooldown
+    );
+    state.last_buy_slot = slot;
+    state.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])
+}
+
+pub(crate) fn initialize_launch_guard(ctx: Context<InitializeLaunchGuard>, params: LaunchParams) -> Result<()> {
+    params.validate()?;
+    let guard = &mut ctx.accounts.guard;
+    guard.mint = ctx.accounts.mint.key();
+    guard.params = params;
+    guard.vaults = Vec::new();
+    guard.bump = ctx.bumps.guard;
+    msg!("Launch guard initialized for mint: {} until slot {}", guard.mint, params.end_slot);
+    Ok(())
+}
+
+pub(crate) fn set_launch_params(ctx: Context<UpdateLaunchGuard>, params: LaunchParams) -> Result<()> {
+    params.validate()?;
+    ctx.accounts.guard.params = params;
+    Ok(())
+}
+
+pub(crate) fn add_launch_vault(ctx: Context<UpdateLaunchGuard>, vault: Pubkey) -> Result<()> {
+    add_key(
+        &mut ctx.accounts.guard.vaults,
+        vault,
+        MAX_LAUNCH_VAULTS,
+        TokenHookError::TooManyVaults,
+    )
+}
+
+pub(crate) fn remove_launch_vault(ctx: Context<UpdateLaunchGuard>, vault: Pubkey) -> Result<()> {
+    remove_key(&mut ctx.accounts.guard.vaults, &vault);
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct InitializeLaunchGuard<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        constraint = is_mint_admin(&mint, &authority.key())? @ TokenHookError::Unauthorized
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + LaunchGuard::INIT_SPACE,
+        seeds = [LAUNCH_SEED, mint.key().as_ref()],
+        bump
+    )]
+    pub guard: Account<'info, LaunchGuard>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateLaunchGuard<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [CONFIG_SEED, guard.mint.as_ref()],
+        bump = hook_config.bump,
+        has_one = authority @ TokenHookError::Unauthorized
+    )]
+    pub hook_config: Account<'info, HookConfig>,
+    #[account(mut)]
+    pub guard: Account<'info, LaunchGuard>,
+}
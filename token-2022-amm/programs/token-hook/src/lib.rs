@@ -1,4 +1,6 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program_option::COption;
+use anchor_lang::Discriminator;
 use anchor_spl::{
     associated_token::AssociatedToken,
     token_interface::{Mint, TokenAccount, TokenInterface},
@@ -6,20 +8,51 @@ use anchor_spl::{
 use spl_tlv_account_resolution::{
     state::ExtraAccountMetaList,
 };
+use spl_token_2022::extension::{
+    transfer_hook::TransferHookAccount, BaseStateWithExtensions, StateWithExtensions,
+};
 use spl_transfer_hook_interface::instruction::{ExecuteInstruction, TransferHookInstruction};
 
+pub mod attestation;
+pub mod config;
+pub mod holder_cap;
+pub mod launch;
+pub mod policy;
+pub mod sanctions;
+pub mod velocity;
+
+pub use attestation::*;
+pub use config::*;
+pub use holder_cap::*;
+pub use launch::*;
+pub use policy::*;
+pub use sanctions::*;
+pub use velocity::*;
+
 declare_id!("o1ZEvtrSXokknjnyaMkp7xyXfMJr4znptdpba7XKoiT");
 
 #[program]
 pub mod token_hook {
     use super::*;
 
-    /// Initialize the ExtraAccountMetas account for the transfer hook
+    /// Initialize the ExtraAccountMetas account for the transfer hook. Only
+    /// the mint's transfer-hook authority or mint authority may, and becomes
+    /// the authority recorded in the mint's hook config.
     pub fn initialize_extra_account_meta_list(
         ctx: Context<InitializeExtraAccountMetaList>,
     ) -> Result<()> {
-        // Create an empty list of extra accounts (no additional accounts needed for basic hook)
-        let account_metas = vec![];
+        // No extra accounts for the basic hook; a mint with a transfer policy,
+        // velocity limit, launch guard, holder cap or credential config has
+        // its accounts resolved from their seeds
+        let account_metas = WiredModules {
+            policy: ctx.accounts.policy.is_some(),
+            velocity_limit: ctx.accounts.velocity_limit.is_some(),
+            launch_guard: ctx.accounts.launch_guard.is_some(),
+            holder_cap: ctx.accounts.holder_cap.is_some(),
+            credentials: ctx.accounts.credentials.is_some(),
+            ..Default::default()
+        }
+        .account_metas()?;
 
         // Calculate account size
         let account_size = ExtraAccountMetaList::size_of(account_metas.len())? as u64;
@@ -29,7 +62,7 @@ pub mod token_hook {
         let mint = ctx.accounts.mint.key();
         let signer_seeds: &[&[&[u8]]] = &[&[
             b"extra-account-metas",
-            &mint.as_ref(),
+            mint.as_ref(),
             &[ctx.bumps.extra_account_meta_list],
         ]];
 
@@ -54,24 +87,249 @@ pub mod token_hook {
             &account_metas,
         )?;
 
+        let config = &mut ctx.accounts.config;
+        config.mint = mint;
+        config.authority = ctx.accounts.payer.key();
+        config.sanctions_root = [0; 32];
+        config.sanctions_depth = 0;
+        config.bump = ctx.bumps.config;
+
         msg!("ExtraAccountMetaList initialized for mint: {}", ctx.accounts.mint.key());
         Ok(())
     }
 
+    /// Replace the mint's extra accounts with `metas`, resizing the list.
+    /// Only the authority in the mint's hook config may.
+    pub fn update_extra_account_meta_list(
+        ctx: Context<UpdateExtraAccountMetaList>,
+        metas: Vec<ExtraMeta>,
+    ) -> Result<()> {
+        config::update_extra_account_meta_list(ctx, metas)
+    }
+
+    /// Rebuild the mint's extra accounts from the hook modules it has set up
+    /// and, if a sanctions root is set, its sanctions proofs. Only the
+    /// authority in the mint's hook config may.
+    pub fn sync_extra_account_meta_list(ctx: Context<SyncExtraAccountMetaList>) -> Result<()> {
+        config::sync_extra_account_meta_list(ctx)
+    }
+
+    /// Close the mint's ExtraAccountMetaList, hook config and module
+    /// accounts, returning their rent to the config authority. Per-wallet
+    /// accounts (wallet states, attestations and sanctions proofs) are not
+    /// closed and keep their rent.
+    pub fn close_extra_account_meta_list(ctx: Context<CloseExtraAccountMetaList>) -> Result<()> {
+        config::close_extra_account_meta_list(ctx)
+    }
+
     /// Execute transfer hook - called by Token-2022 during transfers
-    pub fn execute(ctx: Context<TransferHook>, amount: u64) -> Result<()> {
+    pub fn execute<'info>(ctx: Context<'_, '_, '_, 'info, TransferHook<'info>>, amount: u64) -> Result<()> {
         msg!("Transfer hook executed!");
         msg!("  From: {}", ctx.accounts.source_token.key());
         msg!("  To: {}", ctx.accounts.destination_token.key());
         msg!("  Amount: {}", amount);
         msg!("  Mint: {}", ctx.accounts.mint.key());
-        
-        // Custom logic can go here
-        // For now, allow all transfers
+
+        // Only Token-2022 sets the flag, and only for the duration of a
+        // transfer, so this rejects direct calls
+        assert_is_transferring(&ctx.accounts.source_token)?;
+        assert_is_transferring(&ctx.accounts.destination_token)?;
+
+        if let Some(policy) = find_mint_account::<TransferPolicy>(ctx.remaining_accounts, &ctx.accounts.mint.key())? {
+            require!(
+                policy.allows(&ctx.accounts.source_token.owner)
+                    && policy.allows(&ctx.accounts.destination_token.owner),
+                TokenHookError::TransferNotAllowed
+            );
+        }
+        attestation::enforce_attestations(
+            ctx.remaining_accounts,
+            &ctx.accounts.mint.key(),
+            &ctx.accounts.source_token.owner,
+            &ctx.accounts.destination_token.owner,
+        )?;
+        sanctions::enforce_sanctions(
+            ctx.remaining_accounts,
+            &ctx.accounts.mint.key(),
+            &ctx.accounts.source_token.owner,
+            &ctx.accounts.destination_token.owner,
+        )?;
+        velocity::enforce_velocity_limit(
+            ctx.remaining_accounts,
+            &ctx.accounts.mint.key(),
+            &ctx.accounts.source_token.key(),
+            &ctx.accounts.source_token.owner,
+            amount,
+        )?;
+        launch::enforce_launch_guard(
+            ctx.remaining_accounts,
+            &ctx.accounts.mint.key(),
+            &ctx.accounts.source_token.key(),
+            &ctx.accounts.destination_token.owner,
+            amount,
+        )?;
+        if let Some(cap) = find_mint_account::<HolderCap>(ctx.remaining_accounts, &ctx.accounts.mint.key())? {
+            // Token-2022 has already credited the destination
+            require!(
+                cap.allows(
+                    &ctx.accounts.destination_token.key(),
+                    &ctx.accounts.destination_token.owner,
+                    ctx.accounts.destination_token.amount,
+                    ctx.accounts.mint.supply,
+                ),
+                TokenHookError::HolderCapExceeded
+            );
+        }
+
         msg!("Transfer allowed");
         Ok(())
     }
 
+    /// Create a mint's transfer policy. Only the mint's admins may.
+    pub fn initialize_policy(ctx: Context<InitializePolicy>, mode: PolicyMode) -> Result<()> {
+        policy::initialize_policy(ctx, mode)
+    }
+
+    /// List an owner on the policy
+    pub fn add_policy_wallet(ctx: Context<UpdatePolicy>, wallet: Pubkey) -> Result<()> {
+        policy::add_policy_wallet(ctx, wallet)
+    }
+
+    /// Take an owner off the policy
+    pub fn remove_policy_wallet(ctx: Context<UpdatePolicy>, wallet: Pubkey) -> Result<()> {
+        policy::remove_policy_wallet(ctx, wallet)
+    }
+
+    /// Switch the policy between allowlist and denylist
+    pub fn set_policy_mode(ctx: Context<UpdatePolicy>, mode: PolicyMode) -> Result<()> {
+        policy::set_policy_mode(ctx, mode)
+    }
+
+    /// Cap what any one owner may send to `max_amount` per `window_secs`.
+    /// Only the mint's admins may.
+    pub fn initialize_velocity_limit(
+        ctx: Context<InitializeVelocityLimit>,
+        max_amount: u64,
+        window_secs: i64,
+    ) -> Result<()> {
+        velocity::initialize_velocity_limit(ctx, max_amount, window_secs)
+    }
+
+    /// Change the cap or its window
+    pub fn set_velocity_limit(ctx: Context<UpdateVelocityLimit>, max_amount: u64, window_secs: i64) -> Result<()> {
+        velocity::set_velocity_limit(ctx, max_amount, window_secs)
+    }
+
+    /// Exempt a token account or owner, such as an AMM vault, from the cap
+    pub fn add_velocity_exemption(ctx: Context<UpdateVelocityLimit>, account: Pubkey) -> Result<()> {
+        velocity::add_velocity_exemption(ctx, account)
+    }
+
+    /// Subject a token account or owner to the cap again
+    pub fn remove_velocity_exemption(ctx: Context<UpdateVelocityLimit>, account: Pubkey) -> Result<()> {
+        velocity::remove_velocity_exemption(ctx, account)
+    }
+
+    /// Create the state tracking `owner`'s recent spend and buys. Anyone may
+    /// pay for it; an owner needs one to send a velocity-limited mint or to
+    /// buy during a launch.
+    pub fn initialize_wallet_state(ctx: Context<InitializeWalletState>, owner: Pubkey) -> Result<()> {
+        velocity::initialize_wallet_state(ctx, owner)
+    }
+
+    /// Limit buys out of the mint's registered AMM vaults until
+    /// `params.end_slot`. Only the mint's admins may.
+    pub fn initialize_launch_guard(ctx: Context<InitializeLaunchGuard>, params: LaunchParams) -> Result<()> {
+        launch::initialize_launch_guard(ctx, params)
+    }
+
+    /// Change the launch limits or end slot
+    pub fn set_launch_params(ctx: Context<UpdateLaunchGuard>, params: LaunchParams) -> Result<()> {
+        launch::set_launch_params(ctx, params)
+    }
+
+    /// Treat transfers out of `vault` as buys
+    pub fn add_launch_vault(ctx: Context<UpdateLaunchGuard>, vault: Pubkey) -> Result<()> {
+        launch::add_launch_vault(ctx, vault)
+    }
+
+    /// Stop treating transfers out of `vault` as buys
+    pub fn remove_launch_vault(ctx: Context<UpdateLaunchGuard>, vault: Pubkey) -> Result<()> {
+        launch::remove_launch_vault(ctx, vault)
+    }
+
+    /// Cap any one account's balance at `max_bps` of supply. Only the mint's
+    /// admins may.
+    pub fn initialize_holder_cap(ctx: Context<InitializeHolderCap>, max_bps: u16) -> Result<()> {
+        holder_cap::initialize_holder_cap(ctx, max_bps)
+    }
+
+    /// Change the holder cap
+    pub fn set_holder_cap(ctx: Context<UpdateHolderCap>, max_bps: u16) -> Result<()> {
+        holder_cap::set_holder_cap(ctx, max_bps)
+    }
+
+    /// Exempt a token account or owner, such as an AMM vault, treasury or
+    /// the LP program, from the cap
+    pub fn add_holder_cap_exemption(ctx: Context<UpdateHolderCap>, account: Pubkey) -> Result<()> {
+        holder_cap::add_holder_cap_exemption(ctx, account)
+    }
+
+    /// Subject a token account or owner to the cap again
+    pub fn remove_holder_cap_exemption(ctx: Context<UpdateHolderCap>, account: Pubkey) -> Result<()> {
+        holder_cap::remove_holder_cap_exemption(ctx, account)
+    }
+
+    /// Require both parties of every transfer to hold a credential from an
+    /// approved issuer. Only the mint's admins may.
+    pub fn initialize_credentials(ctx: Context<InitializeCredentials>) -> Result<()> {
+        attestation::initialize_credentials(ctx)
+    }
+
+    /// Approve an issuer of credentials
+    pub fn add_issuer(ctx: Context<UpdateCredentials>, issuer: Pubkey) -> Result<()> {
+        attestation::add_issuer(ctx, issuer)
+    }
+
+    /// Withdraw an issuer's approval, invalidating what it issued. Approving
+    /// it again does not bring those credentials back.
+    pub fn remove_issuer(ctx: Context<UpdateCredentials>, issuer: Pubkey) -> Result<()> {
+        attestation::remove_issuer(ctx, issuer)
+    }
+
+    /// Issue or renew `owner`'s credential, valid until `expires_at`. Only an
+    /// approved issuer may, and only the original issuer may renew, unless
+    /// the config authority also signs. A revoked credential needs the
+    /// authority to be issued again.
+    pub fn issue_attestation(ctx: Context<IssueAttestation>, owner: Pubkey, expires_at: i64) -> Result<()> {
+        attestation::issue_attestation(ctx, owner, expires_at)
+    }
+
+    /// Revoke a credential. Its issuer or the config authority may.
+    pub fn revoke_attestation(ctx: Context<RevokeAttestation>) -> Result<()> {
+        attestation::revoke_attestation(ctx)
+    }
+
+    /// Screen transfers against the sanctions tree of `depth` with `root`, or
+    /// stop screening with a zero root. Only the config authority may.
+    pub fn set_sanctions_root(ctx: Context<SetSanctionsRoot>, root: [u8; 32], depth: u8) -> Result<()> {
+        sanctions::set_sanctions_root(ctx, root, depth)
+    }
+
+    /// Record that `owner` is not on the sanctions list: `low` and `high`
+    /// are the adjacent leaves at `index` and `index + 1` that bracket it
+    pub fn submit_sanctions_proof(
+        ctx: Context<SubmitSanctionsProof>,
+        owner: Pubkey,
+        index: u32,
+        low: Pubkey,
+        high: Pubkey,
+        low_proof: Vec<[u8; 32]>,
+        high_proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        sanctions::submit_sanctions_proof(ctx, owner, index, low, high, low_proof, high_proof)
+    }
+
     /// Fallback instruction handler (for older Anchor versions)
     pub fn fallback<'info>(
         program_id: &Pubkey,
@@ -88,7 +346,7 @@ pub mod token_hook {
                 // Invoke transfer hook instruction
                 __private::__global::execute(program_id, accounts, &amount_bytes)
             }
-            _ => return Err(ProgramError::InvalidInstructionData.into()),
+            _ => Err(ProgramError::InvalidInstructionData.into()),
         }
     }
 }
@@ -106,7 +364,33 @@ pub struct InitializeExtraAccountMetaList<'info> {
     )]
     pub extra_account_meta_list: AccountInfo<'info>,
     
+    #[account(
+        constraint = is_mint_admin(&mint, &payer.key())? @ TokenHookError::Unauthorized
+    )]
     pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + HookConfig::INIT_SPACE,
+        seeds = [CONFIG_SEED, mint.key().as_ref()],
+        bump
+    )]
+    pub config: Account<'info, HookConfig>,
+    /// The mint's transfer policy, to wire into the list if it has one
+    #[account(constraint = policy.mint == mint.key() @ TokenHookError::Unauthorized)]
+    pub policy: Option<Account<'info, TransferPolicy>>,
+    /// The mint's velocity limit, to wire into the list if it has one
+    #[account(constraint = velocity_limit.mint == mint.key() @ TokenHookError::Unauthorized)]
+    pub velocity_limit: Option<Account<'info, VelocityLimit>>,
+    /// The mint's launch guard, to wire into the list if it has one
+    #[account(constraint = launch_guard.mint == mint.key() @ TokenHookError::Unauthorized)]
+    pub launch_guard: Option<Account<'info, LaunchGuard>>,
+    /// The mint's holder cap, to wire into the list if it has one
+    #[account(constraint = holder_cap.mint == mint.key() @ TokenHookError::Unauthorized)]
+    pub holder_cap: Option<Account<'info, HolderCap>>,
+    /// The mint's credential config, to wire into the list if it has one
+    #[account(constraint = credentials.mint == mint.key() @ TokenHookError::Unauthorized)]
+    pub credentials: Option<Account<'info, CredentialConfig>>,
     pub token_program: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
@@ -116,7 +400,8 @@ pub struct InitializeExtraAccountMetaList<'info> {
 pub struct TransferHook<'info> {
     #[account(
         token::mint = mint,
-        token::authority = owner,
+        constraint = source_token.owner == owner.key()
+            || source_token.delegate == COption::Some(owner.key()) @ TokenHookError::InvalidTransferAuthority,
     )]
     pub source_token: InterfaceAccount<'info, TokenAccount>,
     pub mint: InterfaceAccount<'info, Mint>,
@@ -124,7 +409,8 @@ pub struct TransferHook<'info> {
         token::mint = mint,
     )]
     pub destination_token: InterfaceAccount<'info, TokenAccount>,
-    /// CHECK: source token account owner, can be SystemAccount or PDA owned by another program
+    /// CHECK: transfer authority: the source account's owner (a wallet, PDA
+    /// or multisig) or its approved delegate. Policies check the owner.
     pub owner: UncheckedAccount<'info>,
     /// CHECK: ExtraAccountMetaList Account,
     #[account(
@@ -134,8 +420,120 @@ pub struct TransferHook<'info> {
     pub extra_account_meta_list: UncheckedAccount<'info>,
 }
 
+/// The first account of type `T` among the extra accounts `execute` was
+/// given that satisfies `matches`, with its account info for writing back
+pub(crate) fn find_extra_account<'a, 'info, T>(
+    accounts: &'a [AccountInfo<'info>],
+    matches: impl Fn(&T) -> bool,
+) -> Result<Option<(&'a AccountInfo<'info>, T)>>
+where
+    T: AccountDeserialize + Discriminator + Owner,
+{
+    for info in accounts {
+        if info.owner != &T::owner() || !info.try_borrow_data()?.starts_with(&T::DISCRIMINATOR) {
+            continue;
+        }
+        let account = T::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+        if matches(&account) {
+            return Ok(Some((info, account)));
+        }
+    }
+    Ok(None)
+}
+
+/// A per-mint hook account, such as a module's settings
+pub(crate) trait MintAccount {
+    fn mint(&self) -> &Pubkey;
+}
+
+/// The account of type `T` for `mint` among the extra accounts `execute` was
+/// given, if the mint has one wired in
+pub(crate) fn find_mint_account<T>(accounts: &[AccountInfo], mint: &Pubkey) -> Result<Option<T>>
+where
+    T: AccountDeserialize + Discriminator + Owner + MintAccount,
+{
+    Ok(find_extra_account::<T>(accounts, |account| account.mint() == mint)?.map(|(_, account)| account))
+}
+
+/// Add `key` to `list` unless it is already there, failing with `full` once
+/// the list holds `max` keys
+pub(crate) fn add_key(list: &mut Vec<Pubkey>, key: Pubkey, max: usize, full: TokenHookError) -> Result<()> {
+    if !list.contains(&key) {
+        if list.len() >= max {
+            return Err(full.into());
+        }
+        list.push(key);
+    }
+    Ok(())
+}
+
+/// Take `key` off `list`, if it is there
+pub(crate) fn remove_key(list: &mut Vec<Pubkey>, key: &Pubkey) {
+    list.retain(|k| k != key);
+}
+
+/// Fail unless Token-2022 is in the middle of transferring out of or into
+/// `account`
+fn assert_is_transferring(account: &InterfaceAccount<TokenAccount>) -> Result<()> {
+    let info = account.to_account_info();
+    let data = info.try_borrow_data()?;
+    let state = StateWithExtensions::<spl_token_2022::state::Account>::unpack(&data)?;
+    let extension = state.get_extension::<TransferHookAccount>()?;
+    require!(
+        bool::from(extension.transferring),
+        TokenHookError::NotTransferring
+    );
+    Ok(())
+}
+
 #[error_code]
 pub enum TokenHookError {
     #[msg("Transfer not allowed")]
     TransferNotAllowed,
+    #[msg("Signer may not administer this mint")]
+    Unauthorized,
+    #[msg("Transfer policy is full")]
+    PolicyFull,
+    #[msg("Hook invoked outside of a Token-2022 transfer")]
+    NotTransferring,
+    #[msg("Transfer authority is neither the source owner nor its delegate")]
+    InvalidTransferAuthority,
+    #[msg("Transfer exceeds the owner's velocity limit")]
+    VelocityLimitExceeded,
+    #[msg("Owner has no wallet state for this mint")]
+    WalletStateMissing,
+    #[msg("Velocity window must be positive")]
+    InvalidVelocityWindow,
+    #[msg("Exemption list is full")]
+    ExemptionListFull,
+    #[msg("Buy exceeds the launch limit per transaction")]
+    BuyTooLarge,
+    #[msg("Buy would exceed the launch holding limit")]
+    HoldingTooLarge,
+    #[msg("Buyer is still cooling down from a previous buy")]
+    BuyCooldown,
+    #[msg("Too many launch vaults")]
+    TooManyVaults,
+    #[msg("Transfer would exceed the maximum holder balance")]
+    HolderCapExceeded,
+    #[msg("Holder cap must be at most 10000 bps")]
+    InvalidHolderCap,
+    #[msg("Owner has no credential for this mint")]
+    AttestationMissing,
+    #[msg("Owner's credential has expired")]
+    AttestationExpired,
+    #[msg("Owner's credential has been revoked")]
+    AttestationRevoked,
+    #[msg("Too many credential issuers")]
+    TooManyIssuers,
+    #[msg("Owner has no sanctions proof for this mint")]
+    SanctionsProofMissing,
+    #[msg("Sanctions proof does not hold under the current root")]
+    SanctionsProofInvalid,
+    #[msg("Owner is on the sanctions list")]
+    SanctionedAddress,
+    #[msg("Sanctions tree is deeper than proofs may be")]
+    InvalidSanctionsDepth,
+    #[msg("Launch max buy must be positive and at most the max holding")]
+    InvalidLaunchParams,
 }
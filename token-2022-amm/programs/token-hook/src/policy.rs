@@ -0,0 +1,120 @@
+//! Per-mint wallet policy. A mint's `TransferPolicy` either admits only the
+//! owners it lists or blocks them, and `execute` checks both sides of every
+//! transfer against it. The policy reaches `execute` as a seed-derived entry
+//! in the mint's ExtraAccountMetaList.
+
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::Mint;
+use spl_tlv_account_resolution::{account::ExtraAccountMeta, seeds::Seed};
+
+use crate::{add_key, is_mint_admin, remove_key, HookConfig, MintAccount, TokenHookError, CONFIG_SEED};
+
+pub const POLICY_SEED: &[u8] = b"policy";
+pub const MAX_POLICY_WALLETS: usize = 64;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum PolicyMode {
+    Allowlist, // Only listed owners may send or receive
+    Denylist,  // Listed owners may neither send nor receive
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct TransferPolicy {
+    pub mint: Pubkey,
+    pub mode: PolicyMode,
+    #[max_len(MAX_POLICY_WALLETS)]
+    pub wallets: Vec<Pubkey>,
+    pub bump: u8,
+}
+
+impl TransferPolicy {
+    pub fn allows(&self, owner: &Pubkey) -> bool {
+        let listed = self.wallets.contains(owner);
+        match self.mode {
+            PolicyMode::Allowlist => listed,
+            PolicyMode::Denylist => !listed,
+        }
+    }
+
+    /// The ExtraAccountMetaList entry resolving to the mint's policy PDA
+    pub fn extra_account_meta() -> Result<ExtraAccountMeta> {
+        Ok(ExtraAccountMeta::new_with_seeds(
+            &[
+                Seed::Literal {
+                    bytes: POLICY_SEED.to_vec(),
+                },
+                Seed::AccountKey { index: 1 }, // The mint
+            ],
+            false,
+            false,
+        )?)
+    }
+}
+
+impl MintAccount for TransferPolicy {
+    fn mint(&self) -> &Pubkey {
+        &self.mint
+    }
+}
+
+pub(crate) fn initialize_policy(ctx: Context<InitializePolicy>, mode: PolicyMode) -> Result<()> {
+    let policy = &mut ctx.accounts.policy;
+    policy.mint = ctx.accounts.mint.key();
+    policy.mode = mode;
+    policy.wallets = Vec::new();
+    policy.bump = ctx.bumps.policy;
+    msg!("Transfer policy initialized for mint: {}", policy.mint);
+    Ok(())
+}
+
+pub(crate) fn add_policy_wallet(ctx: Context<UpdatePolicy>, wallet: Pubkey) -> Result<()> {
+    add_key(
+        &mut ctx.accounts.policy.wallets,
+        wallet,
+        MAX_POLICY_WALLETS,
+        TokenHookError::PolicyFull,
+    )
+}
+
+pub(crate) fn remove_policy_wallet(ctx: Context<UpdatePolicy>, wallet: Pubkey) -> Result<()> {
+    remove_key(&mut ctx.accounts.policy.wallets, &wallet);
+    Ok(())
+}
+
+pub(crate) fn set_policy_mode(ctx: Context<UpdatePolicy>, mode: PolicyMode) -> Result<()> {
+    ctx.accounts.policy.mode = mode;
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct InitializePolicy<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        constraint = is_mint_admin(&mint, &authority.key())? @ TokenHookError::Unauthorized
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + TransferPolicy::INIT_SPACE,
+        seeds = [POLICY_SEED, mint.key().as_ref()],
+        bump
+    )]
+    pub policy: Account<'info, TransferPolicy>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdatePolicy<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [CONFIG_SEED, policy.mint.as_ref()],
+        bump = hook_config.bump,
+        has_one = authority @ TokenHookError::Unauthorized
+    )]
+    pub hook_config: Account<'info, HookConfig>,
+    #[account(mut)]
+    pub policy: Account<'info, TransferPolicy>,
+}
@@ -0,0 +1,211 @@
+//! Merkle-root sanctions screening. The hook config holds the root of a
+//! sorted Merkle tree of blocked addresses, bracketed by the all-zero and
+//! all-0xff keys. Each owner proves it is not on the list with the two
+//! adjacent leaves around its key, which a client writes into the owner's
+//! `SanctionsProof` PDA ahead of the transfer for Token-2022 to resolve.
+//!
+//! Leaves hash as `sha256(0x00 || key)` and nodes as
+//! `sha256(0x01 || left || right)`. A tree of depth `d` has exactly `2^d`
+//! leaves: after the zero key and the sorted blocked keys, it is padded with
+//! copies of the all-0xff key up to the next power of two. The config stores
+//! `d` with the root, and every proof must carry exactly `d` siblings.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+use spl_tlv_account_resolution::{account::ExtraAccountMeta, seeds::Seed};
+
+use crate::{find_extra_account, find_mint_account, HookConfig, TokenHookError, CONFIG_SEED};
+
+pub const SANCTIONS_PROOF_SEED: &[u8] = b"sanctions-proof";
+/// Deep enough for a million entries while keeping both proofs well within
+/// the compute budget of a transfer
+pub const MAX_PROOF_DEPTH: usize = 20;
+
+#[account]
+#[derive(InitSpace)]
+pub struct SanctionsProof {
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    pub index: u32, // Leaf index of `low`; `high` is the next leaf
+    pub low: Pubkey,
+    pub high: Pubkey,
+    #[max_len(MAX_PROOF_DEPTH)]
+    pub low_proof: Vec<[u8; 32]>,
+    #[max_len(MAX_PROOF_DEPTH)]
+    pub high_proof: Vec<[u8; 32]>,
+    pub bump: u8,
+}
+
+fn leaf_hash(key: &Pubkey) -> [u8; 32] {
+    hashv(&[&[0], key.as_ref()]).to_bytes()
+}
+
+/// Whether `leaf` sits at `index` under `root`
+fn verify(root: &[u8; 32], leaf: [u8; 32], mut index: u32, proof: &[[u8; 32]]) -> bool {
+    let mut node = leaf;
+    for sibling in proof {
+        node = if index & 1 == 0 {
+            hashv(&[&[1], &node, sibling]).to_bytes()
+        } else {
+            hashv(&[&[1], sibling, &node]).to_bytes()
+        };
+        index >>= 1;
+    }
+    node == *root
+}
+
+impl SanctionsProof {
+    /// Fail unless the proof shows `owner` is absent from the list under the
+    /// config's root. A valid proof with `owner` as one of its leaves shows
+    /// it is listed.
+    fn check(&self, config: &HookConfig, owner: &Pubkey) -> Result<()> {
+        let (root, depth) = (&config.sanctions_root, config.sanctions_depth as usize);
+        require!(
+            self.low_proof.len() == depth
+                && self.high_proof.len() == depth
+                && (self.index as u64) + 1 < 1 << depth
+                && verify(root, leaf_hash(&self.low), self.index, &self.low_proof)
+                && verify(root, leaf_hash(&self.high), self.index + 1, &self.high_proof),
+            TokenHookError::SanctionsProofInvalid
+        );
+        require!(
+            self.low != *owner && self.high != *owner,
+            TokenHookError::SanctionedAddress
+        );
+        require!(
+            self.low < *owner && *owner < self.high,
+            TokenHookError::SanctionsProofInvalid
+        );
+        Ok(())
+    }
+}
+
+impl HookConfig {
+    /// The ExtraAccountMetaList entries resolving to the hook config and to
+    /// the sanctions proofs of the source and destination owners
+    pub fn sanctions_account_metas() -> Result<[ExtraAccountMeta; 3]> {
+        let proof_of = |account_index: u8| {
+            ExtraAccountMeta::new_with_seeds(
+                &[
+                    Seed::Literal {
+                        bytes: SANCTIONS_PROOF_SEED.to_vec(),
+                    },
+                    Seed::AccountKey { index: 1 },
+                    // The owner field of the token account
+                    Seed::AccountData {
+                        account_index,
+                        data_index: 32,
+                        length: 32,
+                    },
+                ],
+                false,
+                false,
+            )
+        };
+        Ok([
+            ExtraAccountMeta::new_with_seeds(
+                &[
+                    Seed::Literal {
+                        bytes: CONFIG_SEED.to_vec(),
+                    },
+                    Seed::AccountKey { index: 1 }, // The mint
+                ],
+                false,
+                false,
+            )?,
+            proof_of(0)?,
+            proof_of(2)?,
+        ])
+    }
+}
+
+/// Screen both parties if the mint's config, carrying a sanctions root, was
+/// passed in
+pub(crate) fn enforce_sanctions(
+    accounts: &[AccountInfo],
+    mint: &Pubkey,
+    source_owner: &Pubkey,
+    destination_owner: &Pubkey,
+) -> Result<()> {
+    let Some(config) = find_mint_account::<HookConfig>(accounts, mint)? else {
+        return Ok(());
+    };
+    if config.sanctions_root == [0; 32] {
+        return Ok(());
+    }
+    for owner in [source_owner, destination_owner] {
+        let (_, proof) = find_extra_account::<SanctionsProof>(accounts, |proof| {
+            proof.mint == *mint && proof.owner == *owner
+        })?
+        .ok_or(TokenHookError::SanctionsProofMissing)?;
+        proof.check(&config, owner)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn set_sanctions_root(ctx: Context<SetSanctionsRoot>, root: [u8; 32], depth: u8) -> Result<()> {
+    require!(depth as usize <= MAX_PROOF_DEPTH, TokenHookError::InvalidSanctionsDepth);
+    ctx.accounts.config.sanctions_root = root;
+    ctx.accounts.config.sanctions_depth = depth;
+    msg!("Sanctions root updated for mint: {}", ctx.accounts.config.mint);
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn submit_sanctions_proof(
+    ctx: Context<SubmitSanctionsProof>,
+    owner: Pubkey,
+    index: u32,
+    low: Pubkey,
+    high: Pubkey,
+    low_proof: Vec<[u8; 32]>,
+    high_proof: Vec<[u8; 32]>,
+) -> Result<()> {
+    let proof = &mut ctx.accounts.proof;
+    proof.mint = ctx.accounts.config.mint;
+    proof.owner = owner;
+    proof.index = index;
+    proof.low = low;
+    proof.high = high;
+    proof.low_proof = low_proof;
+    proof.high_proof = high_proof;
+    proof.bump = ctx.bumps.proof;
+    // Only proofs that hold now may be written, so nobody can overwrite an
+    // owner's proof with a bad one
+    proof.check(&ctx.accounts.config, &owner)
+}
+
+#[derive(Accounts)]
+pub struct SetSanctionsRoot<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [CONFIG_SEED, config.mint.as_ref()],
+        bump = config.bump,
+        has_one = authority @ TokenHookError::Unauthorized
+    )]
+    pub config: Account<'info, HookConfig>,
+}
+
+/// Proofs are checked against the current root, so anyone may write one for
+/// any owner
+#[derive(Accounts)]
+#[instruction(owner: Pubkey)]
+pub struct SubmitSanctionsProof<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        seeds = [CONFIG_SEED, config.mint.as_ref()],
+        bump = config.bump
+    )]
+    pub config: Account<'info, HookConfig>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + SanctionsProof::INIT_SPACE,
+        seeds = [SANCTIONS_PROOF_SEED, config.mint.as_ref(), owner.as_ref()],
+        bump
+    )]
+    pub proof: Account<'info, SanctionsProof>,
+    pub system_program: Program<'info, System>,
+}
@@ -0,0 +1,228 @@
+//! Per-wallet transfer velocity limits. A mint's `VelocityLimit` caps how
+//! much any one owner may send per window. An owner's window opens with
+//! their first transfer and lasts `window_secs`; the next transfer after it
+//! ends opens a new one. Each owner's spend lives in a `WalletState` PDA
+//! that Token-2022 resolves from the source account's owner and passes to
+//! `execute` writable.
+
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::Mint;
+use spl_tlv_account_resolution::{account::ExtraAccountMeta, seeds::Seed};
+
+use crate::{
+    add_key, find_extra_account, find_mint_account, is_mint_admin, remove_key, HookConfig, MintAccount, TokenHookError,
+    CONFIG_SEED,
+};
+
+pub const VELOCITY_SEED: &[u8] = b"velocity";
+pub const WALLET_STATE_SEED: &[u8] = b"wallet-state";
+pub const MAX_VELOCITY_EXEMPTIONS: usize = 16;
+
+#[account]
+#[derive(InitSpace)]
+pub struct VelocityLimit {
+    pub mint: Pubkey,
+    pub max_amount: u64, // Most an owner may send per window
+    pub window_secs: i64,
+    #[max_len(MAX_VELOCITY_EXEMPTIONS)]
+    pub exempt: Vec<Pubkey>, // Token accounts or owners, e.g. AMM vaults
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct WalletState {
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    pub spent: u64, // Sent since `window_start_ts`
+    pub window_start_ts: i64,
+    pub last_buy_slot: u64, // Set by the launch guard
+    pub bought: u64, // Bought during the launch, tallied by the launch guard
+    pub bump: u8,
+}
+
+impl VelocityLimit {
+    pub fn is_exempt(&self, token_account: &Pubkey, owner: &Pubkey) -> bool {
+        self.exempt.iter().any(|e| e == token_account || e == owner)
+    }
+
+    /// Add `amount` to the owner's spend in the current window, opening a
+    /// new window first if the last one has ended
+    pub fn record(&self, state: &mut WalletState, amount: u64, now: i64) -> Result<()> {
+        if now >= state.window_start_ts.saturating_add(self.window_secs) {
+            state.window_start_ts = now;
+            state.spent = 0;
+        }
+        state.spent = state
+            .spent
+            .checked_add(amount)
+            .filter(|spent| *spent <= self.max_amount)
+            .ok_or(TokenHookError::VelocityLimitExceeded)?;
+        Ok(())
+    }
+
+    /// The ExtraAccountMetaList entries resolving to the mint's limit and to
+    /// the wallet state of the source account's owner
+    pub fn extra_account_metas() -> Result<[ExtraAccountMeta; 2]> {
+        Ok([
+            ExtraAccountMeta::new_with_seeds(
+                &[
+                    Seed::Literal {
+                        bytes: VELOCITY_SEED.to_vec(),
+                    },
+                    Seed::AccountKey { index: 1 }, // The mint
+                ],
+                false,
+                false,
+            )?,
+            ExtraAccountMeta::new_with_seeds(
+                &[
+                    Seed::Literal {
+                        bytes: WALLET_STATE_SEED.to_vec(),
+                    },
+                    Seed::AccountKey { index: 1 },
+                    // The owner field of the source token account
+                    Seed::AccountData {
+                        account_index: 0,
+                        data_index: 32,
+                        length: 32,
+                    },
+                ],
+                false,
+                true,
+            )?,
+        ])
+    }
+}
+
+impl MintAccount for VelocityLimit {
+    fn mint(&self) -> &Pubkey {
+        &self.mint
+    }
+}
+
+/// Charge `amount` against the source owner's window, unless the source is
+/// exempt or the mint has no velocity limit
+pub(crate) fn enforce_velocity_limit(
+    accounts: &[AccountInfo],
+    mint: &Pubkey,
+    source_token: &Pubkey,
+    owner: &Pubkey,
+    amount: u64,
+) -> Result<()> {
+    let Some(limit) = find_mint_account::<VelocityLimit>(accounts, mint)? else {
+        return Ok(());
+    };
+    if limit.is_exempt(source_token, owner) {
+        return Ok(());
+    }
+    let (info, mut state) = find_extra_account::<WalletState>(accounts, |state| {
+        state.mint == *mint && state.owner == *owner
+    })?
+    .ok_or(TokenHookError::WalletStateMissing)?;
+    limit.record(&mut state, amount, Clock::get()?.unix_timestamp)?;
+    state.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])
+}
+
+pub(crate) fn initialize_velocity_limit(
+    ctx: Context<InitializeVelocityLimit>,
+    max_amount: u64,
+    window_secs: i64,
+) -> Result<()> {
+    require!(window_secs > 0, TokenHookError::InvalidVelocityWindow);
+    let limit = &mut ctx.accounts.limit;
+    limit.mint = ctx.accounts.mint.key();
+    limit.max_amount = max_amount;
+    limit.window_secs = window_secs;
+    limit.exempt = Vec::new();
+    limit.bump = ctx.bumps.limit;
+    msg!("Velocity limit initialized for mint: {}", limit.mint);
+    Ok(())
+}
+
+pub(crate) fn set_velocity_limit(
+    ctx: Context<UpdateVelocityLimit>,
+    max_amount: u64,
+    window_secs: i64,
+) -> Result<()> {
+    require!(window_secs > 0, TokenHookError::InvalidVelocityWindow);
+    let limit = &mut ctx.accounts.limit;
+    limit.max_amount = max_amount;
+    limit.window_secs = window_secs;
+    Ok(())
+}
+
+pub(crate) fn add_velocity_exemption(ctx: Context<UpdateVelocityLimit>, account: Pubkey) -> Result<()> {
+    add_key(
+        &mut ctx.accounts.limit.exempt,
+        account,
+        MAX_VELOCITY_EXEMPTIONS,
+        TokenHookError::ExemptionListFull,
+    )
+}
+
+pub(crate) fn remove_velocity_exemption(ctx: Context<UpdateVelocityLimit>, account: Pubkey) -> Result<()> {
+    remove_key(&mut ctx.accounts.limit.exempt, &account);
+    Ok(())
+}
+
+pub(crate) fn initialize_wallet_state(ctx: Context<InitializeWalletState>, owner: Pubkey) -> Result<()> {
+    let state = &mut ctx.accounts.wallet_state;
+    state.mint = ctx.accounts.mint.key();
+    state.owner = owner;
+    state.spent = 0;
+    state.window_start_ts = 0;
+    state.last_buy_slot = 0;
+    state.bought = 0;
+    state.bump = ctx.bumps.wallet_state;
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct InitializeVelocityLimit<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        constraint = is_mint_admin(&mint, &authority.key())? @ TokenHookError::Unauthorized
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + VelocityLimit::INIT_SPACE,
+        seeds = [VELOCITY_SEED, mint.key().as_ref()],
+        bump
+    )]
+    pub limit: Account<'info, VelocityLimit>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateVelocityLimit<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [CONFIG_SEED, limit.mint.as_ref()],
+        bump = hook_config.bump,
+        has_one = authority @ TokenHookError::Unauthorized
+    )]
+    pub hook_config: Account<'info, HookConfig>,
+    #[account(mut)]
+    pub limit: Account<'info, VelocityLimit>,
+}
+
+#[derive(Accounts)]
+#[instruction(owner: Pubkey)]
+pub struct InitializeWalletState<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + WalletState::INIT_SPACE,
+        seeds = [WALLET_STATE_SEED, mint.key().as_ref(), owner.as_ref()],
+        bump
+    )]
+    pub wallet_state: Account<'info, WalletState>,
+    pub system_program: Program<'info, System>,
+}
@@ -0,0 +1,233 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { Token2022Amm } from "../target/types/token_2022_amm";
+import { TokenHook } from "../target/types/token_hook";
+import { getAccount, getAssociatedTokenAddressSync, TOKEN_2022_PROGRAM_ID } from "@solana/spl-token";
+import { AccountMeta, PublicKey } from "@solana/web3.js";
+import { expect } from "chai";
+import { createHash } from "crypto";
+import {
+  createHookedMint,
+  createMint,
+  createTokenAccount,
+  extraMetasPda,
+  initializeAmmIfNeeded,
+} from "./helpers";
+
+describe("Multi-asset baskets", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const ammProgram = anchor.workspace.Token2022Amm as Program<Token2022Amm>;
+  const hookProgram = anchor.workspace.TokenHook as Program<TokenHook>;
+  const payer = (provider.wallet as anchor.Wallet).payer;
+
+  interface Member {
+    mint: PublicKey;
+    vault: PublicKey;
+    user: PublicKey;
+    hook: AccountMeta[];
+  }
+
+  let amm: PublicKey;
+  let members: Member[];
+  let lpMint: PublicKey;
+  let userLp: PublicKey;
+  let basket: PublicKey;
+
+  const meta = (pubkey: PublicKey, isWritable = false): AccountMeta => ({ pubkey, isSigner: false, isWritable });
+
+  // Per member: mint, vault, user token account, then its own hook accounts
+  const memberAccounts = (group: Member[]): AccountMeta[] =>
+    group.flatMap((m) => [meta(m.mint), meta(m.vault, true), meta(m.user, true), ...m.hook]);
+  const hookCounts = (group: Member[]) => Buffer.from(group.map((m) => m.hook.length));
+
+  const balance = async (account: PublicKey) =>
+    Number((await getAccount(provider.connection, account, undefined, TOKEN_2022_PROGRAM_ID)).amount);
+
+  const liquidityAccounts = () => ({
+    pool: basket,
+    amm,
+    user: payer.publicKey,
+    lpMint,
+    userLpToken: userLp,
+    tokenProgram: TOKEN_2022_PROGRAM_ID,
+  });
+
+  // The basket is seeded by the sha256 of its sorted mints, and its LP mint by the basket
+  const createBasket = async (curve: object, weights: number[], amounts: number[]) => {
+    const mintsHash = createHash("sha256")
+      .update(Buffer.concat(members.map((m) => m.mint.toBuffer())))
+      .digest();
+    [basket] = PublicKey.findProgramAddressSync([Buffer.from("basket"), mintsHash], ammProgram.programId);
+    [lpMint] = PublicKey.findProgramAddressSync([Buffer.from("basket-lp-mint"), basket.toBuffer()], ammProgram.programId);
+    userLp = getAssociatedTokenAddressSync(lpMint, payer.publicKey, false, TOKEN_2022_PROGRAM_ID);
+    await ammProgram.methods
+      .createBasket(
+        [...mintsHash],
+        curve as any,
+        weights.map((w) => new anchor.BN(w)),
+        amounts.map((a) => new anchor.BN(a)),
+        hookCounts(members)
+      )
+      .accountsPartial({
+        pool: basket,
+        amm,
+        creator: payer.publicKey,
+        lpMint,
+        creatorLpToken: userLp,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .remainingAccounts(memberAccounts(members))
+      .rpc();
+  };
+
+  before(async () => {
+    amm = await initializeAmmIfNeeded(ammProgram);
+    const hookedMint = await createHookedMint(provider, payer, hookProgram.programId);
+    await hookProgram.methods
+      .initializeExtraAccountMetaList()
+      .accounts({ payer: payer.publicKey, mint: hookedMint, tokenProgram: TOKEN_2022_PROGRAM_ID })
+      .rpc();
+
+    const mints = [
+      hookedMint,
+      await createMint(provider, payer, payer.publicKey),
+      await createMint(provider, payer, payer.publicKey),
+    ].sort((a, b) => Buffer.compare(a.toBuffer(), b.toBuffer()));
+
+    members = [];
+    for (const mint of mints) {
+      members.push({
+        mint,
+        vault: await createTokenAccount(provider, payer, mint, amm),
+        user: await createTokenAccount(provider, payer, mint, payer.publicKey, 1_000_000_000_000),
+        hook: mint.equals(hookedMint)
+          ? [meta(extraMetasPda(mint, hookProgram.programId)), meta(hookProgram.programId)]
+          : [],
+      });
+    }
+  });
+
+  it("Rejects weights that do not sum to one", async () => {
+    try {
+      await createBasket({ weighted: {} }, [500_000_000, 250_000_000, 200_000_000], [1_000, 1_000, 1_000]);
+      expect.fail("weights must sum to WEIGHT_ONE");
+    } catch (err) {
+      expect(err.toString()).to.contain("InvalidCurveParameters");
+    }
+  });
+
+  it("Rejects a stable basket of mints with different decimals", async () => {
+    const mint = await createMint(provider, payer, payer.publicKey, 6);
+    const sixDecimals = {
+      mint,
+      vault: await createTokenAccount(provider, payer, mint, amm),
+      user: await createTokenAccount(provider, payer, mint, payer.publicKey, 1_000_000_000),
+      hook: [],
+    };
+    const all = members;
+    members = [...all.slice(1), sixDecimals].sort((a, b) => Buffer.compare(a.mint.toBuffer(), b.mint.toBuffer()));
+    try {
+      await createBasket({ stable: { amp: new anchor.BN(100) } }, [0, 0, 0], [1_000_000, 1_000_000, 1_000_000]);
+      expect.fail("a stable basket must not peg raw units across decimals");
+    } catch (err) {
+      expect(err.toString()).to.contain("MismatchedDecimals");
+    } finally {
+      members = all;
+    }
+  });
+
+  it("Rejects a hash that does not match the members", async () => {
+    const mintsHash = Buffer.alloc(32, 7);
+    const [fakeBasket] = PublicKey.findProgramAddressSync([Buffer.from("basket"), mintsHash], ammProgram.programId);
+    const [fakeLpMint] = PublicKey.findProgramAddressSync(
+      [Buffer.from("basket-lp-mint"), fakeBasket.toBuffer()],
+      ammProgram.programId
+    );
+    try {
+      await ammProgram.methods
+        .createBasket(
+          [...mintsHash],
+          { stable: { amp: new anchor.BN(100) } } as any,
+          [0, 0, 0].map((w) => new anchor.BN(w)),
+          [1_000, 1_000, 1_000].map((a) => new anchor.BN(a)),
+          hookCounts(members)
+        )
+        .accountsPartial({
+          pool: fakeBasket,
+          amm,
+          creator: payer.publicKey,
+          lpMint: fakeLpMint,
+          creatorLpToken: getAssociatedTokenAddressSync(fakeLpMint, payer.publicKey, false, TOKEN_2022_PROGRAM_ID),
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+        })
+        .remainingAccounts(memberAccounts(members))
+        .rpc();
+      expect.fail("the basket address must come from its mints");
+    } catch (err) {
+      expect(err.toString()).to.contain("InvalidTokenPair");
+    }
+  });
+
+  it("Creates a stable basket of three mints", async () => {
+    await createBasket({ stable: { amp: new anchor.BN(100) } }, [0, 0, 0], [1_000_000_000, 1_000_000_000, 1_000_000_000]);
+
+    const state = await ammProgram.account.basketPool.fetch(basket);
+    expect(state.members.map((m) => m.mint.toBase58())).to.deep.equal(members.map((m) => m.mint.toBase58()));
+    expect(state.lpMint.toBase58()).to.equal(lpMint.toBase58());
+    expect(state.lpSupply.toNumber()).to.equal(3_000_000_000);
+    expect(await balance(userLp)).to.equal(3_000_000_000);
+    for (const m of members) {
+      expect(await balance(m.vault)).to.equal(1_000_000_000);
+    }
+  });
+
+  it("Swaps between any two members, resolving each mint's hook separately", async () => {
+    const [first, , last] = members;
+    const before = await balance(last.user);
+    await ammProgram.methods
+      .basketSwap(0, 2, new anchor.BN(1_000_000), new anchor.BN(0), [first.hook.length, last.hook.length])
+      .accountsPartial({ pool: basket, amm, user: payer.publicKey, tokenProgram: TOKEN_2022_PROGRAM_ID })
+      .remainingAccounts(memberAccounts([first, last]))
+      .rpc();
+
+    // 3-coin StableSwap at A = 100 on 997_500 after the 25 bps fee: ~997_498.9
+    expect((await balance(last.user)) - before).to.be.within(997_496, 997_498);
+    const state = await ammProgram.account.basketPool.fetch(basket);
+    expect(state.members[0].balance.toNumber()).to.equal(1_001_000_000);
+    expect(state.members[1].balance.toNumber()).to.equal(1_000_000_000);
+  });
+
+  it("Deposits and withdraws every member in proportion", async () => {
+    const state = await ammProgram.account.basketPool.fetch(basket);
+    const supply = state.lpSupply.toNumber();
+    const lpAmount = supply / 10;
+    const before = await Promise.all(members.map((m) => balance(m.user)));
+
+    await ammProgram.methods
+      .addBasketLiquidity(
+        new anchor.BN(lpAmount),
+        members.map(() => new anchor.BN(200_000_000)),
+        hookCounts(members)
+      )
+      .accountsPartial(liquidityAccounts())
+      .remainingAccounts(memberAccounts(members))
+      .rpc();
+    for (const [i, m] of members.entries()) {
+      const expected = Math.ceil((state.members[i].balance.toNumber() * lpAmount) / supply);
+      expect(before[i] - (await balance(m.user))).to.equal(expected);
+    }
+
+    await ammProgram.methods
+      .removeBasketLiquidity(new anchor.BN(lpAmount), members.map(() => new anchor.BN(0)), hookCounts(members))
+      .accountsPartial(liquidityAccounts())
+      .remainingAccounts(memberAccounts(members))
+      .rpc();
+    // Rounding on the way in and out leaves at most a unit per member with the pool
+    for (const [i, m] of members.entries()) {
+      expect(before[i] - (await balance(m.user))).to.be.within(0, 1);
+    }
+    expect((await ammProgram.account.basketPool.fetch(basket)).lpSupply.toNumber()).to.equal(supply);
+  });
+});
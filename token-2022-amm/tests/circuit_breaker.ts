@@ -0,0 +1,79 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { Token2022Amm } from "../target/types/token_2022_amm";
+import { TokenHook } from "../target/types/token_hook";
+import { Keypair } from "@solana/web3.js";
+import { expect } from "chai";
+import { airdrop, PoolFixture, setupPool, swapAccounts } from "./helpers";
+
+describe("Circuit breaker", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const ammProgram = anchor.workspace.Token2022Amm as Program<Token2022Amm>;
+  const hookProgram = anchor.workspace.TokenHook as Program<TokenHook>;
+
+  let fx: PoolFixture;
+
+  const setCircuitBreaker = (maxImpactBps: number, maxMoveBps: number, windowSlots: number) =>
+    ammProgram.methods
+      .setCircuitBreaker(maxImpactBps, maxMoveBps, new anchor.BN(windowSlots))
+      .accountsPartial({ pool: fx.pool, amm: fx.amm })
+      .rpc();
+
+  const swap = (amountIn: number) =>
+    ammProgram.methods
+      .swap(new anchor.BN(amountIn), new anchor.BN(0))
+      .accountsPartial(swapAccounts(ammProgram, fx, true))
+      .rpc();
+
+  before(async () => {
+    fx = await setupPool(ammProgram, hookProgram, 1_000_000_000, 1_000_000_000);
+  });
+
+  it("Rejects configuration from anyone but the AMM authority", async () => {
+    const stranger = Keypair.generate();
+    await airdrop(provider, stranger.publicKey);
+    try {
+      await ammProgram.methods
+        .setCircuitBreaker(200, 500, new anchor.BN(10_000))
+        .accountsPartial({ pool: fx.pool, amm: fx.amm, authority: stranger.publicKey })
+        .signers([stranger])
+        .rpc();
+      expect.fail("the circuit breaker should require the AMM authority");
+    } catch (err) {
+      expect(err.toString()).to.contain("Unauthorized");
+    }
+  });
+
+  it("Rejects a single swap with too much price impact", async () => {
+    await setCircuitBreaker(200, 500, 10_000);
+    try {
+      // 5% of the reserve executes ~475 bps below spot
+      await swap(50_000_000);
+      expect.fail("swap should trip the price impact limit");
+    } catch (err) {
+      expect(err.toString()).to.contain("PriceImpactTooHigh");
+    }
+  });
+
+  it("Rejects swaps that together move the price too far within the window", async () => {
+    // Each 1% trade has ~99 bps of impact and moves spot ~2%
+    await swap(10_000_000);
+    await swap(10_000_000);
+    const pool = await ammProgram.account.pool.fetch(fx.pool);
+    expect(pool.tokenAAmount.toNumber()).to.be.above(1_019_000_000);
+
+    try {
+      await swap(10_000_000);
+      expect.fail("a third trade would move the price ~570 bps in the window");
+    } catch (err) {
+      expect(err.toString()).to.contain("PriceMoveTooHigh");
+    }
+  });
+
+  it("Lets the same trade through once the bounds are lifted", async () => {
+    await setCircuitBreaker(0, 0, 0);
+    await swap(50_000_000);
+  });
+});
@@ -0,0 +1,225 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { Token2022Amm } from "../target/types/token_2022_amm";
+import { getAccount, TOKEN_2022_PROGRAM_ID } from "@solana/spl-token";
+import { PublicKey } from "@solana/web3.js";
+import { expect } from "chai";
+import {
+  createMint,
+  createTokenAccount,
+  initializeAmmIfNeeded,
+  sortMints,
+} from "./helpers";
+
+const Q64 = new anchor.BN(1).shln(64);
+const TICK_SPACING = 10;
+const TICK_ARRAY_SPAN = 32 * TICK_SPACING;
+
+const i32Bytes = (value: number) => {
+  const buf = Buffer.alloc(4);
+  buf.writeInt32LE(value);
+  return buf;
+};
+
+describe("Concentrated liquidity", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const ammProgram = anchor.workspace.Token2022Amm as Program<Token2022Amm>;
+  const payer = (provider.wallet as anchor.Wallet).payer;
+
+  let amm: PublicKey;
+  let mintA: PublicKey;
+  let mintB: PublicKey;
+  let pool: PublicKey;
+  let position: PublicKey;
+  let vaultA: PublicKey;
+  let vaultB: PublicKey;
+  let userA: PublicKey;
+  let userB: PublicKey;
+
+  const tickArrayPda = (start: number) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("tick-array"), pool.toBuffer(), i32Bytes(start)],
+      ammProgram.programId
+    )[0];
+
+  const balance = async (account: PublicKey) =>
+    Number((await getAccount(provider.connection, account, undefined, TOKEN_2022_PROGRAM_ID)).amount);
+
+  const tokenAccounts = () => ({
+    tokenAMint: mintA,
+    tokenBMint: mintB,
+    tokenAVault: vaultA,
+    tokenBVault: vaultB,
+    tokenProgram: TOKEN_2022_PROGRAM_ID,
+  });
+
+  const positionAccounts = () => ({
+    pool,
+    amm,
+    position,
+    owner: payer.publicKey,
+    tickArrayLower: tickArrayPda(-TICK_ARRAY_SPAN),
+    tickArrayUpper: tickArrayPda(0),
+    ownerTokenA: userA,
+    ownerTokenB: userB,
+    ...tokenAccounts(),
+  });
+
+  // Passes the array holding the current tick and up to two more in the swap direction
+  const swap = async (amountIn: number, aToB: boolean, minimumOut = 0, arrays = 3) => {
+    const { tickCurrent } = await ammProgram.account.clPool.fetch(pool);
+    const start = Math.floor(tickCurrent / TICK_ARRAY_SPAN) * TICK_ARRAY_SPAN;
+    const step = aToB ? -TICK_ARRAY_SPAN : TICK_ARRAY_SPAN;
+    return ammProgram.methods
+      .clSwap(new anchor.BN(amountIn), new anchor.BN(minimumOut), aToB, new anchor.BN(0))
+      .accountsPartial({
+        pool,
+        amm,
+        user: payer.publicKey,
+        userTokenA: userA,
+        userTokenB: userB,
+        tickArray0: tickArrayPda(start),
+        tickArray1: arrays > 1 ? tickArrayPda(start + step) : null,
+        tickArray2: arrays > 2 ? tickArrayPda(start + 2 * step) : null,
+        ...tokenAccounts(),
+      })
+      .rpc();
+  };
+
+  before(async () => {
+    amm = await initializeAmmIfNeeded(ammProgram);
+    [mintA, mintB] = sortMints(
+      await createMint(provider, payer, payer.publicKey),
+      await createMint(provider, payer, payer.publicKey)
+    );
+    [pool] = PublicKey.findProgramAddressSync(
+      [Buffer.from("cl-pool"), mintA.toBuffer(), mintB.toBuffer(), new anchor.BN(TICK_SPACING).toArrayLike(Buffer, "le", 2)],
+      ammProgram.programId
+    );
+    vaultA = await createTokenAccount(provider, payer, mintA, amm);
+    vaultB = await createTokenAccount(provider, payer, mintB, amm);
+    userA = await createTokenAccount(provider, payer, mintA, payer.publicKey, 1_000_000_000_000);
+    userB = await createTokenAccount(provider, payer, mintB, payer.publicKey, 1_000_000_000_000);
+
+    // Start at a price of 1, i.e. tick 0
+    await ammProgram.methods
+      .createClPool(TICK_SPACING, Q64)
+      .accountsPartial({ pool, amm, payer: payer.publicKey, ...tokenAccounts() })
+      .rpc();
+    for (const start of [-4, -3, -2, -1, 0, 1].map((i) => i * TICK_ARRAY_SPAN)) {
+      await ammProgram.methods
+        .initializeTickArray(start)
+        .accountsPartial({ pool, tickArray: tickArrayPda(start), payer: payer.publicKey })
+        .rpc();
+    }
+
+    [position] = PublicKey.findProgramAddressSync(
+      [Buffer.from("position"), pool.toBuffer(), payer.publicKey.toBuffer(), i32Bytes(-100), i32Bytes(100)],
+      ammProgram.programId
+    );
+    await ammProgram.methods
+      .openPosition(-100, 100)
+      .accountsPartial({ pool, position, owner: payer.publicKey })
+      .rpc();
+  });
+
+  it("Rejects ticks that are not multiples of the spacing", async () => {
+    const [badPosition] = PublicKey.findProgramAddressSync(
+      [Buffer.from("position"), pool.toBuffer(), payer.publicKey.toBuffer(), i32Bytes(-105), i32Bytes(100)],
+      ammProgram.programId
+    );
+    try {
+      await ammProgram.methods
+        .openPosition(-105, 100)
+        .accountsPartial({ pool, position: badPosition, owner: payer.publicKey })
+        .rpc();
+      expect.fail("misaligned tick should be rejected");
+    } catch (err) {
+      expect(err.toString()).to.contain("InvalidTickRange");
+    }
+  });
+
+  it("Deposits both tokens for a range around the price", async () => {
+    await ammProgram.methods
+      .increaseLiquidity(new anchor.BN(1_000_000_000), new anchor.BN(10_000_000), new anchor.BN(10_000_000))
+      .accountsPartial(positionAccounts())
+      .rpc();
+
+    // L * (1 - 1 / sqrt(1.0001^100)) and L * (1 - sqrt(1.0001^-100)), rounded up
+    expect(await balance(vaultA)).to.be.within(4_987_273, 4_987_274);
+    expect(await balance(vaultB)).to.be.within(4_987_273, 4_987_274);
+
+    const state = await ammProgram.account.clPool.fetch(pool);
+    expect(state.liquidity.toNumber()).to.equal(1_000_000_000);
+    const lower = (await ammProgram.account.tickArray.fetch(tickArrayPda(-TICK_ARRAY_SPAN))).ticks[22];
+    expect(lower.liquidityNet.toNumber()).to.equal(1_000_000_000);
+  });
+
+  it("Swaps within a range like a constant-product pool", async () => {
+    const before = await balance(userB);
+    await swap(1_000_000, true);
+    // 25 bps fee, then L * (1 - L / (L + 997_500))
+    expect((await balance(userB)) - before).to.be.within(996_504, 996_505);
+
+    const state = await ammProgram.account.clPool.fetch(pool);
+    expect(state.tickCurrent).to.equal(-20);
+    expect(state.feeGrowthGlobalAX64.gt(new anchor.BN(0))).to.equal(true);
+  });
+
+  it("Crosses the lower tick and partially fills once liquidity runs out", async () => {
+    const before = await balance(userA);
+    await swap(100_000_000, true);
+    // Only the input needed to reach tick -100 is charged, ~4.01M plus fee
+    expect(before - (await balance(userA))).to.be.within(4_024_832, 4_024_834);
+
+    // The price runs on through empty ticks to the end of the last array
+    const state = await ammProgram.account.clPool.fetch(pool);
+    expect(state.liquidity.toNumber()).to.equal(0);
+    expect(state.tickCurrent).to.equal(-3 * TICK_ARRAY_SPAN - 1);
+  });
+
+  it("Credits fees to the position and pays them on collect", async () => {
+    // With no liquidity in the three arrays the price moves for free
+    const beforeB = await balance(userB);
+    await swap(5_000_000, false);
+    expect(await balance(userB)).to.equal(beforeB);
+    expect((await ammProgram.account.clPool.fetch(pool)).tickCurrent).to.equal(-TICK_ARRAY_SPAN);
+
+    // The next swap crosses -100 and brings the position back into range
+    await swap(5_000_000, false);
+    expect((await ammProgram.account.clPool.fetch(pool)).liquidity.toNumber()).to.equal(1_000_000_000);
+
+    const before = await balance(userA);
+    await ammProgram.methods.collectFees().accountsPartial(positionAccounts()).rpc();
+    // The 2_500 and 10_063 fees on both A-to-B swaps, less rounding
+    expect((await balance(userA)) - before).to.be.within(12_561, 12_563);
+
+    const state = await ammProgram.account.position.fetch(position);
+    expect(state.tokensOwedA.toNumber()).to.equal(0);
+  });
+
+  it("Withdraws everything and clears the ticks", async () => {
+    await ammProgram.methods
+      .decreaseLiquidity(new anchor.BN(1_000_000_000), new anchor.BN(0), new anchor.BN(0))
+      .accountsPartial(positionAccounts())
+      .rpc();
+
+    expect((await ammProgram.account.position.fetch(position)).liquidity.toNumber()).to.equal(0);
+    expect((await ammProgram.account.clPool.fetch(pool)).liquidity.toNumber()).to.equal(0);
+    const lower = (await ammProgram.account.tickArray.fetch(tickArrayPda(-TICK_ARRAY_SPAN))).ticks[22];
+    expect(lower.liquidityGross.toNumber()).to.equal(0);
+  });
+
+  it("Stops at the end of the only tick array supplied", async () => {
+    const { tickCurrent } = await ammProgram.account.clPool.fetch(pool);
+    const start = Math.floor(tickCurrent / TICK_ARRAY_SPAN) * TICK_ARRAY_SPAN;
+    const before = await balance(userB);
+    await swap(5_000_000, false, 0, 1);
+
+    // No liquidity is left, so the price moves for free
+    expect(await balance(userB)).to.equal(before);
+    expect((await ammProgram.account.clPool.fetch(pool)).tickCurrent).to.equal(start + TICK_ARRAY_SPAN);
+  });
+});
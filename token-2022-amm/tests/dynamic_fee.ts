@@ -0,0 +1,120 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { Token2022Amm } from "../target/types/token_2022_amm";
+import { TokenHook } from "../target/types/token_hook";
+import { Keypair } from "@solana/web3.js";
+import { expect } from "chai";
+import { airdrop, cpiEvents, PoolFixture, setupPool, swapAccounts } from "./helpers";
+
+describe("Dynamic fees", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const ammProgram = anchor.workspace.Token2022Amm as Program<Token2022Amm>;
+  const hookProgram = anchor.workspace.TokenHook as Program<TokenHook>;
+
+  let fx: PoolFixture;
+
+  const setDynamicFee = (min: number, max: number, perVolatility: number, halfLife: number) =>
+    ammProgram.methods
+      .setDynamicFee(min, max, perVolatility, new anchor.BN(halfLife))
+      .accountsPartial({ pool: fx.pool, amm: fx.amm })
+      .rpc();
+
+  const quote = (amountIn: number) =>
+    ammProgram.methods
+      .quoteSwap(new anchor.BN(amountIn), fx.mintA)
+      .accounts({ pool: fx.pool, amm: fx.amm })
+      .view();
+
+  const quoteFee = async (amountIn: number) => (await quote(amountIn)).fee.toNumber();
+
+  const swap = async (amountIn: number) => {
+    const signature = await ammProgram.methods
+      .swap(new anchor.BN(amountIn), new anchor.BN(0))
+      .accountsPartial(swapAccounts(ammProgram, fx, true))
+      .rpc({ commitment: "confirmed" });
+    const [event] = await cpiEvents(ammProgram, signature);
+    return event.data;
+  };
+
+  before(async () => {
+    fx = await setupPool(ammProgram, hookProgram, 1_000_000_000, 1_000_000_000);
+  });
+
+  it("Rejects configuration from anyone but the AMM authority", async () => {
+    const stranger = Keypair.generate();
+    await airdrop(provider, stranger.publicKey);
+    try {
+      await ammProgram.methods
+        .setDynamicFee(10, 100, 50, new anchor.BN(1_000))
+        .accountsPartial({ pool: fx.pool, amm: fx.amm, authority: stranger.publicKey })
+        .signers([stranger])
+        .rpc();
+      expect.fail("dynamic fees should require the AMM authority");
+    } catch (err) {
+      expect(err.toString()).to.contain("Unauthorized");
+    }
+  });
+
+  it("Rejects bounds above the cap or out of order", async () => {
+    for (const [min, max] of [
+      [10, 1_001],
+      [100, 50],
+    ]) {
+      try {
+        await setDynamicFee(min, max, 50, 1_000);
+        expect.fail("bounds should be rejected");
+      } catch (err) {
+        expect(err.toString()).to.contain("InvalidCurveParameters");
+      }
+    }
+  });
+
+  it("Charges the minimum while the pool is calm", async () => {
+    await setDynamicFee(10, 100, 50, 1_000);
+    expect(await quoteFee(1_000_000)).to.equal(1_000);
+    expect((await quote(1_000_000)).feeBps.toNumber()).to.equal(10);
+
+    const swapped = await swap(1_000_000);
+    expect(swapped.fee.toNumber()).to.equal(1_000);
+    expect(swapped.feeBps.toNumber()).to.equal(10);
+  });
+
+  it("Raises the fee after a large price move, up to the maximum", async () => {
+    // A 10% trade moves the price ~17%, which at 50 bps per 100 bps of
+    // volatility would add far more than the 90 bps of headroom
+    await swap(100_000_000);
+    expect(await quoteFee(1_000_000)).to.equal(10_000);
+
+    const swapped = await swap(1_000_000);
+    expect(swapped.feeBps.toNumber()).to.equal(100);
+    const pool = await ammProgram.account.pool.fetch(fx.pool);
+    expect(pool.dynamicFee.volatilityBps.toNumber()).to.be.above(1_700);
+  });
+
+  it("Halves the volatility part of the fee every half-life", async () => {
+    const halfLife = 4;
+    await setDynamicFee(10, 1_000, 10, halfLife);
+    const { volatilityBps, lastUpdateSlot } = (await ammProgram.account.pool.fetch(fx.pool)).dynamicFee;
+    // 10 bps per 100 bps of volatility after `halvings` half-lives
+    const feeAfter = (halvings: number) =>
+      Math.min(1_000, 10 + Math.floor((volatilityBps.toNumber() >> halvings) / 10));
+
+    const start = lastUpdateSlot.toNumber();
+    while ((await provider.connection.getSlot()) < start + 2 * halfLife) {
+      await new Promise((resolve) => setTimeout(resolve, 200));
+    }
+    const feeBps = (await quote(1_000_000)).feeBps.toNumber();
+    const maxHalvings = Math.floor(((await provider.connection.getSlot("processed")) - start) / halfLife);
+
+    expect(feeAfter(0)).to.be.above(feeAfter(2));
+    expect(feeBps).to.be.at.most(feeAfter(2));
+    expect(feeBps).to.be.at.least(feeAfter(maxHalvings));
+  });
+
+  it("Falls back to the AMM's static fee when disabled", async () => {
+    await setDynamicFee(0, 0, 0, 0);
+    expect(await quoteFee(1_000_000)).to.equal(2_500);
+  });
+});
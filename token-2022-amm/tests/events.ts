@@ -0,0 +1,64 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { Token2022Amm } from "../target/types/token_2022_amm";
+import { TokenHook } from "../target/types/token_hook";
+import { expect } from "chai";
+import { cpiEvents, PoolFixture, setupPool, swapAccounts } from "./helpers";
+
+describe("Pool events", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const ammProgram = anchor.workspace.Token2022Amm as Program<Token2022Amm>;
+  const hookProgram = anchor.workspace.TokenHook as Program<TokenHook>;
+
+  let fx: PoolFixture;
+
+  before(async () => {
+    fx = await setupPool(ammProgram, hookProgram, 1_000_000_000, 1_000_000_000);
+  });
+
+  it("Emits Swapped with the pool, user and post-swap reserves", async () => {
+    const signature = await ammProgram.methods
+      .swap(new anchor.BN(1_000_000), new anchor.BN(0))
+      .accountsPartial(swapAccounts(ammProgram, fx, true))
+      .rpc({ commitment: "confirmed" });
+
+    const events = await cpiEvents(ammProgram, signature);
+    expect(events.map((e) => e.name)).to.deep.equal(["swapped"]);
+
+    const swapped = events[0].data;
+    const pool = await ammProgram.account.pool.fetch(fx.pool);
+    expect(swapped.pool.toBase58()).to.equal(fx.pool.toBase58());
+    expect(swapped.user.toBase58()).to.equal(provider.publicKey.toBase58());
+    expect(swapped.tokenInMint.toBase58()).to.equal(fx.mintA.toBase58());
+    expect(swapped.amountIn.toNumber()).to.equal(1_000_000);
+    expect(swapped.fee.toNumber()).to.equal(2_500);
+    expect(swapped.feeBps.toNumber()).to.equal(25);
+    expect(swapped.tokenAAmount.toString()).to.equal(pool.tokenAAmount.toString());
+    expect(swapped.tokenBAmount.toString()).to.equal(pool.tokenBAmount.toString());
+  });
+
+  it("Emits WhitelistChanged when a hook program is added", async () => {
+    const whitelist = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("whitelist"), fx.amm.toBuffer()],
+      ammProgram.programId
+    )[0];
+    if ((await provider.connection.getAccountInfo(whitelist)) === null) {
+      await ammProgram.methods
+        .initializeWhitelist()
+        .accountsPartial({ authority: provider.publicKey, amm: fx.amm })
+        .rpc();
+    }
+
+    const signature = await ammProgram.methods
+      .addHookProgram(hookProgram.programId)
+      .accountsPartial({ authority: provider.publicKey, amm: fx.amm })
+      .rpc({ commitment: "confirmed" });
+
+    const [event] = await cpiEvents(ammProgram, signature);
+    expect(event.name).to.equal("whitelistChanged");
+    expect(event.data.programId.toBase58()).to.equal(hookProgram.programId.toBase58());
+    expect(event.data.allowed).to.equal(true);
+  });
+});
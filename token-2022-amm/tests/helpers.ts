@@ -0,0 +1,319 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { Token2022Amm } from "../target/types/token_2022_amm";
+import { TokenHook } from "../target/types/token_hook";
+import {
+  TOKEN_2022_PROGRAM_ID,
+  ExtensionType,
+  getMintLen,
+  getAssociatedTokenAddressSync,
+  createInitializeMintInstruction,
+  createInitializeTransferHookInstruction,
+  createAssociatedTokenAccountIdempotentInstruction,
+  createMintToInstruction,
+} from "@solana/spl-token";
+import { AccountMeta, PublicKey, Keypair, SystemProgram, Transaction } from "@solana/web3.js";
+
+export const DECIMALS = 9;
+
+export function ammPda(ammProgram: Program<Token2022Amm>): PublicKey {
+  return PublicKey.findProgramAddressSync([Buffer.from("amm")], ammProgram.programId)[0];
+}
+
+export function poolPda(
+  ammProgram: Program<Token2022Amm>,
+  mintA: PublicKey,
+  mintB: PublicKey
+): PublicKey {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from("pool"), mintA.toBuffer(), mintB.toBuffer()],
+    ammProgram.programId
+  )[0];
+}
+
+export function lpMintPda(ammProgram: Program<Token2022Amm>, pool: PublicKey): PublicKey {
+  return PublicKey.findProgramAddressSync([Buffer.from("lp-mint"), pool.toBuffer()], ammProgram.programId)[0];
+}
+
+export function observationsPda(ammProgram: Program<Token2022Amm>, pool: PublicKey): PublicKey {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from("observations"), pool.toBuffer()],
+    ammProgram.programId
+  )[0];
+}
+
+export function statsPda(ammProgram: Program<Token2022Amm>, pool: PublicKey): PublicKey {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from("pool-stats"), pool.toBuffer()],
+    ammProgram.programId
+  )[0];
+}
+
+export function eventAuthorityPda(programId: PublicKey): PublicKey {
+  return PublicKey.findProgramAddressSync([Buffer.from("__event_authority")], programId)[0];
+}
+
+export function extraMetasPda(mint: PublicKey, hookProgramId: PublicKey): PublicKey {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from("extra-account-metas"), mint.toBuffer()],
+    hookProgramId
+  )[0];
+}
+
+export async function airdrop(
+  provider: anchor.AnchorProvider,
+  to: PublicKey,
+  sol = 10
+): Promise<void> {
+  const signature = await provider.connection.requestAirdrop(
+    to,
+    sol * anchor.web3.LAMPORTS_PER_SOL
+  );
+  await provider.connection.confirmTransaction(signature);
+}
+
+/// Create a Token-2022 mint whose TransferHook extension points at `hookProgramId`
+export async function createHookedMint(
+  provider: anchor.AnchorProvider,
+  payer: Keypair,
+  hookProgramId: PublicKey,
+  decimals = DECIMALS
+): Promise<PublicKey> {
+  const mint = Keypair.generate();
+  const mintLen = getMintLen([ExtensionType.TransferHook]);
+  const lamports = await provider.connection.getMinimumBalanceForRentExemption(mintLen);
+
+  const tx = new Transaction().add(
+    SystemProgram.createAccount({
+      fromPubkey: payer.publicKey,
+      newAccountPubkey: mint.publicKey,
+      space: mintLen,
+      lamports,
+      programId: TOKEN_2022_PROGRAM_ID,
+    }),
+    createInitializeTransferHookInstruction(
+      mint.publicKey,
+      payer.publicKey,
+      hookProgramId,
+      TOKEN_2022_PROGRAM_ID
+    ),
+    createInitializeMintInstruction(
+      mint.publicKey,
+      decimals,
+      payer.publicKey,
+      null,
+      TOKEN_2022_PROGRAM_ID
+    )
+  );
+  await provider.sendAndConfirm(tx, [payer, mint]);
+  return mint.publicKey;
+}
+
+/// Create a plain Token-2022 mint, e.g. an LP mint whose authority is the AMM PDA
+export async function createMint(
+  provider: anchor.AnchorProvider,
+  payer: Keypair,
+  authority: PublicKey,
+  decimals = DECIMALS
+): Promise<PublicKey> {
+  const mint = Keypair.generate();
+  const mintLen = getMintLen([]);
+  const lamports = await provider.connection.getMinimumBalanceForRentExemption(mintLen);
+  const tx = new Transaction().add(
+    SystemProgram.createAccount({
+      fromPubkey: payer.publicKey,
+      newAccountPubkey: mint.publicKey,
+      space: mintLen,
+      lamports,
+      programId: TOKEN_2022_PROGRAM_ID,
+    }),
+    createInitializeMintInstruction(mint.publicKey, decimals, authority, null, TOKEN_2022_PROGRAM_ID)
+  );
+  await provider.sendAndConfirm(tx, [payer, mint]);
+  return mint.publicKey;
+}
+
+/// Create (idempotently) the associated Token-2022 account of `owner` and
+/// optionally mint `amount` into it
+export async function createTokenAccount(
+  provider: anchor.AnchorProvider,
+  payer: Keypair,
+  mint: PublicKey,
+  owner: PublicKey,
+  amount = 0
+): Promise<PublicKey> {
+  const ata = getAssociatedTokenAddressSync(mint, owner, true, TOKEN_2022_PROGRAM_ID);
+  const tx = new Transaction().add(
+    createAssociatedTokenAccountIdempotentInstruction(
+      payer.publicKey,
+      ata,
+      owner,
+      mint,
+      TOKEN_2022_PROGRAM_ID
+    )
+  );
+  if (amount > 0) {
+    tx.add(createMintToInstruction(mint, ata, payer.publicKey, amount, [], TOKEN_2022_PROGRAM_ID));
+  }
+  await provider.sendAndConfirm(tx, [payer]);
+  return ata;
+}
+
+/// The AMM account is a singleton PDA, so only the first test file creates it.
+/// The provider wallet is always its authority.
+export async function initializeAmmIfNeeded(
+  ammProgram: Program<Token2022Amm>
+): Promise<PublicKey> {
+  const amm = ammPda(ammProgram);
+  const info = await ammProgram.provider.connection.getAccountInfo(amm);
+  if (info === null) {
+    await ammProgram.methods
+      .initializeAmm(new anchor.BN(25), new anchor.BN(10000))
+      .accountsPartial({ authority: ammProgram.provider.publicKey })
+      .rpc();
+  }
+  return amm;
+}
+
+/// Sort two mints the way the AMM orders pool tokens
+export function sortMints(a: PublicKey, b: PublicKey): [PublicKey, PublicKey] {
+  return Buffer.compare(a.toBuffer(), b.toBuffer()) < 0 ? [a, b] : [b, a];
+}
+
+export interface PoolFixture {
+  amm: PublicKey;
+  pool: PublicKey;
+  mintA: PublicKey;
+  mintB: PublicKey;
+  userTokenA: PublicKey;
+  userTokenB: PublicKey;
+  vaultA: PublicKey;
+  vaultB: PublicKey;
+  lpMint: PublicKey;
+  hookProgramId: PublicKey;
+}
+
+/// Create two `token_hook` mints, fund the provider wallet and open a pool
+export async function setupPool(
+  ammProgram: Program<Token2022Amm>,
+  hookProgram: Program<TokenHook>,
+  amountA: number,
+  amountB: number,
+  curve: any = { constantProduct: {} },
+  decimals: [number, number] = [DECIMALS, DECIMALS]
+): Promise<PoolFixture> {
+  const provider = ammProgram.provider as anchor.AnchorProvider;
+  const payer = (provider.wallet as anchor.Wallet).payer;
+  const amm = await initializeAmmIfNeeded(ammProgram);
+
+  const [mintA, mintB] = sortMints(
+    await createHookedMint(provider, payer, hookProgram.programId, decimals[0]),
+    await createHookedMint(provider, payer, hookProgram.programId, decimals[1])
+  );
+  for (const mint of [mintA, mintB]) {
+    await hookProgram.methods
+      .initializeExtraAccountMetaList()
+      .accounts({ payer: payer.publicKey, mint, tokenProgram: TOKEN_2022_PROGRAM_ID })
+      .rpc();
+  }
+
+  const fx: PoolFixture = {
+    amm,
+    pool: poolPda(ammProgram, mintA, mintB),
+    mintA,
+    mintB,
+    userTokenA: await createTokenAccount(provider, payer, mintA, payer.publicKey, 1_000_000_000_000),
+    userTokenB: await createTokenAccount(provider, payer, mintB, payer.publicKey, 1_000_000_000_000),
+    vaultA: await createTokenAccount(provider, payer, mintA, amm),
+    vaultB: await createTokenAccount(provider, payer, mintB, amm),
+    lpMint: lpMintPda(ammProgram, poolPda(ammProgram, mintA, mintB)),
+    hookProgramId: hookProgram.programId,
+  };
+
+  await ammProgram.methods
+    .createPool(new anchor.BN(amountA), new anchor.BN(amountB), curve)
+    .accountsPartial({
+      pool: fx.pool,
+      amm,
+      observations: observationsPda(ammProgram, fx.pool),
+      user: payer.publicKey,
+      userTokenA: fx.userTokenA,
+      userTokenB: fx.userTokenB,
+      tokenAMint: mintA,
+      tokenBMint: mintB,
+      tokenAVault: fx.vaultA,
+      tokenBVault: fx.vaultB,
+      lpMint: fx.lpMint,
+      transferHookProgram: hookProgram.programId,
+      tokenProgram: TOKEN_2022_PROGRAM_ID,
+    })
+    .rpc();
+  return fx;
+}
+
+/// Accounts for `swap` in either direction on a fixture pool
+export function swapAccounts(
+  ammProgram: Program<Token2022Amm>,
+  fx: PoolFixture,
+  aToB: boolean
+) {
+  const [inMint, outMint] = aToB ? [fx.mintA, fx.mintB] : [fx.mintB, fx.mintA];
+  return {
+    pool: fx.pool,
+    amm: fx.amm,
+    observations: observationsPda(ammProgram, fx.pool),
+    stats: statsPda(ammProgram, fx.pool),
+    user: ammProgram.provider.publicKey,
+    userTokenIn: aToB ? fx.userTokenA : fx.userTokenB,
+    userTokenOut: aToB ? fx.userTokenB : fx.userTokenA,
+    tokenInMint: inMint,
+    tokenOutMint: outMint,
+    tokenInVault: aToB ? fx.vaultA : fx.vaultB,
+    tokenOutVault: aToB ? fx.vaultB : fx.vaultA,
+    tokenInExtraMetas: extraMetasPda(inMint, fx.hookProgramId),
+    tokenOutExtraMetas: extraMetasPda(outMint, fx.hookProgramId),
+    transferHookProgram: fx.hookProgramId,
+    tokenProgram: TOKEN_2022_PROGRAM_ID,
+  };
+}
+
+/// Extra metas and hook program Token-2022 needs for hooked transfers of
+/// both pool tokens, for instructions that take them as remaining accounts
+export function hookAccounts(fx: PoolFixture): AccountMeta[] {
+  return [
+    { pubkey: extraMetasPda(fx.mintA, fx.hookProgramId), isSigner: false, isWritable: false },
+    { pubkey: extraMetasPda(fx.mintB, fx.hookProgramId), isSigner: false, isWritable: false },
+    { pubkey: fx.hookProgramId, isSigner: false, isWritable: false },
+  ];
+}
+
+/// Wait until the cluster has advanced by at least `slots` slots
+export async function waitSlots(provider: anchor.AnchorProvider, slots: number): Promise<void> {
+  const target = (await provider.connection.getSlot()) + slots;
+  while ((await provider.connection.getSlot()) < target) {
+    await new Promise((resolve) => setTimeout(resolve, 100));
+  }
+}
+
+/// Decode the events a program emitted with `emit_cpi!` in a confirmed transaction
+export async function cpiEvents(
+  program: Program<any>,
+  signature: string
+): Promise<anchor.Event[]> {
+  const tx = await program.provider.connection.getTransaction(signature, {
+    commitment: "confirmed",
+    maxSupportedTransactionVersion: 0,
+  });
+  const keys = tx.transaction.message.staticAccountKeys;
+  const events: anchor.Event[] = [];
+  for (const inner of tx.meta.innerInstructions ?? []) {
+    for (const ix of inner.instructions) {
+      if (!keys[ix.programIdIndex].equals(program.programId)) continue;
+      // Skip the 8-byte event instruction tag
+      const data = anchor.utils.bytes.bs58.decode(ix.data).subarray(8);
+      const event = program.coder.events.decode(anchor.utils.bytes.base64.encode(data));
+      if (event) events.push(event);
+    }
+  }
+  return events;
+}
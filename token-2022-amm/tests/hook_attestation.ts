@@ -0,0 +1,177 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { TokenHook } from "../target/types/token_hook";
+import { createTransferCheckedWithTransferHookInstruction, TOKEN_2022_PROGRAM_ID } from "@solana/spl-token";
+import { Keypair, PublicKey, Transaction } from "@solana/web3.js";
+import { expect } from "chai";
+import { airdrop, createHookedMint, createTokenAccount, DECIMALS } from "./helpers";
+
+describe("Transfer hook attestations", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const hookProgram = anchor.workspace.TokenHook as Program<TokenHook>;
+  const payer = (provider.wallet as anchor.Wallet).payer;
+
+  const issuer = Keypair.generate();
+  const otherIssuer = Keypair.generate();
+  const stranger = Keypair.generate();
+  const alice = Keypair.generate().publicKey;
+  const bob = Keypair.generate().publicKey;
+
+  let mint: PublicKey;
+  let config: PublicKey;
+  let payerAccount: PublicKey;
+  let aliceAccount: PublicKey;
+  let bobAccount: PublicKey;
+
+  const now = async () => provider.connection.getBlockTime(await provider.connection.getSlot());
+
+  const attestation = (owner: PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("attestation"), mint.toBuffer(), owner.toBuffer()],
+      hookProgram.programId
+    )[0];
+
+  const issue = (owner: PublicKey, expiresAt: number, signer = issuer, authority: PublicKey | null = null) =>
+    hookProgram.methods
+      .issueAttestation(owner, new anchor.BN(expiresAt))
+      .accountsPartial({ issuer: signer.publicKey, authority, config, attestation: attestation(owner) })
+      .signers([signer])
+      .rpc();
+
+  const send = async (destination: PublicKey) => {
+    const ix = await createTransferCheckedWithTransferHookInstruction(
+      provider.connection,
+      payerAccount,
+      mint,
+      destination,
+      payer.publicKey,
+      BigInt(1_000),
+      DECIMALS,
+      [],
+      "confirmed",
+      TOKEN_2022_PROGRAM_ID
+    );
+    await provider.sendAndConfirm(new Transaction().add(ix));
+  };
+
+  const expectError = async (promise: Promise<unknown>, error: string) => {
+    try {
+      await promise;
+      expect.fail(`expected ${error}`);
+    } catch (err) {
+      expect(err.toString()).to.contain(error);
+    }
+  };
+
+  before(async () => {
+    await airdrop(provider, issuer.publicKey);
+    await airdrop(provider, otherIssuer.publicKey);
+    await airdrop(provider, stranger.publicKey);
+    mint = await createHookedMint(provider, payer, hookProgram.programId);
+    [config] = PublicKey.findProgramAddressSync([Buffer.from("credentials"), mint.toBuffer()], hookProgram.programId);
+
+    await hookProgram.methods.initializeCredentials().accountsPartial({ authority: payer.publicKey, mint, config }).rpc();
+    await hookProgram.methods
+      .initializeExtraAccountMetaList()
+      .accountsPartial({ payer: payer.publicKey, mint, credentials: config, tokenProgram: TOKEN_2022_PROGRAM_ID })
+      .rpc();
+    await hookProgram.methods.addIssuer(issuer.publicKey).accountsPartial({ config }).rpc();
+    await hookProgram.methods.addIssuer(otherIssuer.publicKey).accountsPartial({ config }).rpc();
+
+    payerAccount = await createTokenAccount(provider, payer, mint, payer.publicKey, 1_000_000);
+    aliceAccount = await createTokenAccount(provider, payer, mint, alice);
+    bobAccount = await createTokenAccount(provider, payer, mint, bob);
+
+    const hourFromNow = (await now()) + 3_600;
+    await issue(payer.publicKey, hourFromNow);
+    await issue(alice, hourFromNow);
+  });
+
+  it("Moves tokens between credentialed owners", async () => {
+    await send(aliceAccount);
+  });
+
+  it("Rejects a receiver without a credential", async () => {
+    await expectError(send(bobAccount), "AttestationMissing");
+  });
+
+  it("Rejects an expired credential until it is renewed", async () => {
+    await issue(bob, (await now()) - 1);
+    await expectError(send(bobAccount), "AttestationExpired");
+    await issue(bob, (await now()) + 3_600);
+    await send(bobAccount);
+  });
+
+  it("Rejects a revoked credential", async () => {
+    await hookProgram.methods
+      .revokeAttestation()
+      .accountsPartial({ signer: issuer.publicKey, config, attestation: attestation(alice) })
+      .signers([issuer])
+      .rpc();
+    await expectError(send(aliceAccount), "AttestationRevoked");
+  });
+
+  it("Keeps a revoked credential revoked until the authority re-issues it", async () => {
+    const hourFromNow = (await now()) + 3_600;
+    await expectError(issue(alice, hourFromNow), "AttestationRevoked");
+    await expectError(issue(alice, hourFromNow, otherIssuer), "AttestationRevoked");
+
+    await issue(alice, hourFromNow, issuer, payer.publicKey);
+    await send(aliceAccount);
+  });
+
+  it("Only lets the original issuer renew a credential", async () => {
+    const hourFromNow = (await now()) + 3_600;
+    await expectError(issue(bob, hourFromNow, otherIssuer), "Unauthorized");
+    await issue(bob, hourFromNow);
+
+    await issue(bob, hourFromNow, otherIssuer, payer.publicKey);
+    const state = await hookProgram.account.attestation.fetch(attestation(bob));
+    expect(state.issuer.toBase58()).to.equal(otherIssuer.publicKey.toBase58());
+  });
+
+  it("Only lets the hook config authority co-sign a re-issue", async () => {
+    await expectError(
+      hookProgram.methods
+        .issueAttestation(alice, new anchor.BN((await now()) + 3_600))
+        .accountsPartial({
+          issuer: otherIssuer.publicKey,
+          authority: stranger.publicKey,
+          config,
+          attestation: attestation(alice),
+        })
+        .signers([otherIssuer, stranger])
+        .rpc(),
+      "Unauthorized"
+    );
+  });
+
+  it("Does not revive credentials when an issuer is approved again", async () => {
+    await hookProgram.methods.removeIssuer(issuer.publicKey).accountsPartial({ config }).rpc();
+    await expectError(send(aliceAccount), "AttestationRevoked");
+    await hookProgram.methods.addIssuer(issuer.publicKey).accountsPartial({ config }).rpc();
+    await expectError(send(aliceAccount), "AttestationRevoked");
+
+    const hourFromNow = (await now()) + 3_600;
+    await issue(payer.publicKey, hourFromNow);
+    await issue(alice, hourFromNow);
+    await send(aliceAccount);
+  });
+
+  it("Only lets approved issuers issue credentials", async () => {
+    await expectError(issue(alice, (await now()) + 3_600, stranger), "Unauthorized");
+  });
+
+  it("Only lets the hook config authority approve issuers", async () => {
+    await expectError(
+      hookProgram.methods
+        .addIssuer(stranger.publicKey)
+        .accountsPartial({ authority: stranger.publicKey, config })
+        .signers([stranger])
+        .rpc(),
+      "Unauthorized"
+    );
+  });
+});
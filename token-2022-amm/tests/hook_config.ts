@@ -0,0 +1,119 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { TokenHook } from "../target/types/token_hook";
+import { createTransferCheckedWithTransferHookInstruction, TOKEN_2022_PROGRAM_ID } from "@solana/spl-token";
+import { Keypair, PublicKey, Transaction } from "@solana/web3.js";
+import { expect } from "chai";
+import { airdrop, createHookedMint, createTokenAccount, DECIMALS, extraMetasPda } from "./helpers";
+
+describe("Transfer hook configuration", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const hookProgram = anchor.workspace.TokenHook as Program<TokenHook>;
+  const payer = (provider.wallet as anchor.Wallet).payer;
+
+  const stranger = Keypair.generate();
+
+  let mint: PublicKey;
+  let config: PublicKey;
+
+  before(async () => {
+    await airdrop(provider, stranger.publicKey);
+    mint = await createHookedMint(provider, payer, hookProgram.programId);
+    [config] = PublicKey.findProgramAddressSync([Buffer.from("hook-config"), mint.toBuffer()], hookProgram.programId);
+  });
+
+  it("Rejects initialization by anyone who does not administer the mint", async () => {
+    try {
+      await hookProgram.methods
+        .initializeExtraAccountMetaList()
+        .accounts({ payer: stranger.publicKey, mint, tokenProgram: TOKEN_2022_PROGRAM_ID })
+        .signers([stranger])
+        .rpc();
+      expect.fail("a stranger should not be able to fix the mint's extra accounts");
+    } catch (err) {
+      expect(err.toString()).to.contain("Unauthorized");
+    }
+  });
+
+  it("Records the initializing authority in the mint's hook config", async () => {
+    await hookProgram.methods
+      .initializeExtraAccountMetaList()
+      .accounts({ payer: payer.publicKey, mint, tokenProgram: TOKEN_2022_PROGRAM_ID })
+      .rpc();
+
+    const state = await hookProgram.account.hookConfig.fetch(config);
+    expect(state.mint.toBase58()).to.equal(mint.toBase58());
+    expect(state.authority.toBase58()).to.equal(payer.publicKey.toBase58());
+  });
+
+  it("Wires a policy into an existing list", async () => {
+    const [policy] = PublicKey.findProgramAddressSync([Buffer.from("policy"), mint.toBuffer()], hookProgram.programId);
+    await hookProgram.methods
+      .initializePolicy({ denylist: {} })
+      .accountsPartial({ authority: payer.publicKey, mint, policy })
+      .rpc();
+    await hookProgram.methods.addPolicyWallet(stranger.publicKey).accountsPartial({ policy }).rpc();
+
+    try {
+      await hookProgram.methods
+        .syncExtraAccountMetaList()
+        .accountsPartial({ authority: stranger.publicKey, config })
+        .signers([stranger])
+        .rpc();
+      expect.fail("only the config authority may sync the list");
+    } catch (err) {
+      expect(err.toString()).to.contain("Unauthorized");
+    }
+    await hookProgram.methods.syncExtraAccountMetaList().accountsPartial({ authority: payer.publicKey, config }).rpc();
+
+    const source = await createTokenAccount(provider, payer, mint, payer.publicKey, 1_000_000);
+    const destination = await createTokenAccount(provider, payer, mint, stranger.publicKey);
+    const ix = await createTransferCheckedWithTransferHookInstruction(
+      provider.connection,
+      source,
+      mint,
+      destination,
+      payer.publicKey,
+      BigInt(1_000),
+      DECIMALS,
+      [],
+      "confirmed",
+      TOKEN_2022_PROGRAM_ID
+    );
+    try {
+      await provider.sendAndConfirm(new Transaction().add(ix));
+      expect.fail("the newly wired policy should block the transfer");
+    } catch (err) {
+      expect(err.toString()).to.contain("TransferNotAllowed");
+    }
+  });
+
+  it("Shrinks the list and refunds its excess rent", async () => {
+    const list = extraMetasPda(mint, hookProgram.programId);
+    const wired = await provider.connection.getAccountInfo(list);
+    await hookProgram.methods
+      .updateExtraAccountMetaList([])
+      .accountsPartial({ authority: payer.publicKey, config })
+      .rpc();
+
+    const emptied = await provider.connection.getAccountInfo(list);
+    expect(emptied.data.length).to.be.below(wired.data.length);
+    expect(emptied.lamports).to.equal(
+      await provider.connection.getMinimumBalanceForRentExemption(emptied.data.length)
+    );
+  });
+
+  it("Closes the list, config and modules, returning their rent", async () => {
+    const list = extraMetasPda(mint, hookProgram.programId);
+    const [policy] = PublicKey.findProgramAddressSync([Buffer.from("policy"), mint.toBuffer()], hookProgram.programId);
+    const before = await provider.connection.getBalance(payer.publicKey);
+    await hookProgram.methods.closeExtraAccountMetaList().accountsPartial({ authority: payer.publicKey, config }).rpc();
+
+    expect(await provider.connection.getAccountInfo(list)).to.equal(null);
+    expect(await provider.connection.getAccountInfo(config)).to.equal(null);
+    expect(await provider.connection.getAccountInfo(policy)).to.equal(null);
+    expect(await provider.connection.getBalance(payer.publicKey)).to.be.above(before);
+  });
+});
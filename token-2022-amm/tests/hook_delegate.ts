@@ -0,0 +1,103 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { TokenHook } from "../target/types/token_hook";
+import {
+  approve,
+  createMultisig,
+  createTransferCheckedWithTransferHookInstruction,
+  getAccount,
+  TOKEN_2022_PROGRAM_ID,
+} from "@solana/spl-token";
+import { Keypair, PublicKey, Signer, Transaction } from "@solana/web3.js";
+import { expect } from "chai";
+import { airdrop, createHookedMint, createTokenAccount, DECIMALS } from "./helpers";
+
+describe("Transfer hook with delegates and multisig owners", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const hookProgram = anchor.workspace.TokenHook as Program<TokenHook>;
+  const payer = (provider.wallet as anchor.Wallet).payer;
+
+  const router = Keypair.generate();
+  const blocked = Keypair.generate();
+  const receiver = Keypair.generate().publicKey;
+
+  let mint: PublicKey;
+  let payerAccount: PublicKey;
+  let blockedAccount: PublicKey;
+  let receiverAccount: PublicKey;
+
+  const balance = async (account: PublicKey) =>
+    Number((await getAccount(provider.connection, account, undefined, TOKEN_2022_PROGRAM_ID)).amount);
+
+  const transfer = async (source: PublicKey, authority: PublicKey, signers: Signer[], multiSigners: Signer[] = []) => {
+    const ix = await createTransferCheckedWithTransferHookInstruction(
+      provider.connection,
+      source,
+      mint,
+      receiverAccount,
+      authority,
+      BigInt(1_000),
+      DECIMALS,
+      multiSigners,
+      "confirmed",
+      TOKEN_2022_PROGRAM_ID
+    );
+    await provider.sendAndConfirm(new Transaction().add(ix), signers);
+  };
+
+  before(async () => {
+    await airdrop(provider, router.publicKey);
+    await airdrop(provider, blocked.publicKey);
+    mint = await createHookedMint(provider, payer, hookProgram.programId);
+
+    const [policy] = PublicKey.findProgramAddressSync([Buffer.from("policy"), mint.toBuffer()], hookProgram.programId);
+    await hookProgram.methods
+      .initializePolicy({ denylist: {} })
+      .accountsPartial({ authority: payer.publicKey, mint, policy })
+      .rpc();
+    await hookProgram.methods
+      .initializeExtraAccountMetaList()
+      .accountsPartial({ payer: payer.publicKey, mint, policy, tokenProgram: TOKEN_2022_PROGRAM_ID })
+      .rpc();
+    await hookProgram.methods.addPolicyWallet(blocked.publicKey).accountsPartial({ policy }).rpc();
+
+    payerAccount = await createTokenAccount(provider, payer, mint, payer.publicKey, 1_000_000);
+    blockedAccount = await createTokenAccount(provider, payer, mint, blocked.publicKey, 1_000_000);
+    receiverAccount = await createTokenAccount(provider, payer, mint, receiver);
+  });
+
+  it("Accepts a transfer signed by the source account's delegate", async () => {
+    await approve(provider.connection, payer, payerAccount, router.publicKey, payer, 10_000, [], undefined, TOKEN_2022_PROGRAM_ID);
+    await transfer(payerAccount, router.publicKey, [router]);
+    expect(await balance(receiverAccount)).to.equal(1_000);
+  });
+
+  it("Checks the policy against the owner, not the delegate", async () => {
+    await approve(provider.connection, payer, blockedAccount, router.publicKey, blocked, 10_000, [], undefined, TOKEN_2022_PROGRAM_ID);
+    try {
+      await transfer(blockedAccount, router.publicKey, [router]);
+      expect.fail("a denylisted owner should stay blocked behind a delegate");
+    } catch (err) {
+      expect(err.toString()).to.contain("TransferNotAllowed");
+    }
+  });
+
+  it("Accepts a transfer out of a multisig-owned account", async () => {
+    const signers = [Keypair.generate(), Keypair.generate()];
+    const multisig = await createMultisig(
+      provider.connection,
+      payer,
+      signers.map((s) => s.publicKey),
+      2,
+      undefined,
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+    const multisigAccount = await createTokenAccount(provider, payer, mint, multisig, 1_000_000);
+    const before = await balance(receiverAccount);
+    await transfer(multisigAccount, multisig, signers, signers);
+    expect((await balance(receiverAccount)) - before).to.equal(1_000);
+  });
+});
@@ -0,0 +1,65 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { TokenHook } from "../target/types/token_hook";
+import { createTransferCheckedWithTransferHookInstruction, getAccount, TOKEN_2022_PROGRAM_ID } from "@solana/spl-token";
+import { Keypair, PublicKey, Transaction } from "@solana/web3.js";
+import { expect } from "chai";
+import { createHookedMint, createTokenAccount, DECIMALS, extraMetasPda } from "./helpers";
+
+describe("Transfer hook direct invocation guard", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const hookProgram = anchor.workspace.TokenHook as Program<TokenHook>;
+  const payer = (provider.wallet as anchor.Wallet).payer;
+
+  let mint: PublicKey;
+  let source: PublicKey;
+  let destination: PublicKey;
+
+  before(async () => {
+    mint = await createHookedMint(provider, payer, hookProgram.programId);
+    await hookProgram.methods
+      .initializeExtraAccountMetaList()
+      .accounts({ payer: payer.publicKey, mint, tokenProgram: TOKEN_2022_PROGRAM_ID })
+      .rpc();
+    source = await createTokenAccount(provider, payer, mint, payer.publicKey, 1_000_000);
+    destination = await createTokenAccount(provider, payer, mint, Keypair.generate().publicKey);
+  });
+
+  it("Rejects execute called outside of a transfer", async () => {
+    try {
+      await hookProgram.methods
+        .execute(new anchor.BN(1_000))
+        .accountsPartial({
+          sourceToken: source,
+          mint,
+          destinationToken: destination,
+          owner: payer.publicKey,
+          extraAccountMetaList: extraMetasPda(mint, hookProgram.programId),
+        })
+        .rpc();
+      expect.fail("a direct call should not pass for a transfer");
+    } catch (err) {
+      expect(err.toString()).to.contain("NotTransferring");
+    }
+  });
+
+  it("Runs during a real Token-2022 transfer", async () => {
+    const ix = await createTransferCheckedWithTransferHookInstruction(
+      provider.connection,
+      source,
+      mint,
+      destination,
+      payer.publicKey,
+      BigInt(1_000),
+      DECIMALS,
+      [],
+      "confirmed",
+      TOKEN_2022_PROGRAM_ID
+    );
+    await provider.sendAndConfirm(new Transaction().add(ix));
+    const account = await getAccount(provider.connection, destination, undefined, TOKEN_2022_PROGRAM_ID);
+    expect(Number(account.amount)).to.equal(1_000);
+  });
+});
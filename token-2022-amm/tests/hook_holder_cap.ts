@@ -0,0 +1,87 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { TokenHook } from "../target/types/token_hook";
+import { createTransferCheckedWithTransferHookInstruction, TOKEN_2022_PROGRAM_ID } from "@solana/spl-token";
+import { Keypair, PublicKey, Transaction } from "@solana/web3.js";
+import { expect } from "chai";
+import { airdrop, createHookedMint, createTokenAccount, DECIMALS } from "./helpers";
+
+describe("Transfer hook holder cap", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const hookProgram = anchor.workspace.TokenHook as Program<TokenHook>;
+  const payer = (provider.wallet as anchor.Wallet).payer;
+
+  const stranger = Keypair.generate();
+
+  let mint: PublicKey;
+  let cap: PublicKey;
+  let treasury: PublicKey;
+  let holder: PublicKey;
+  let vault: PublicKey;
+
+  // The treasury holds the whole supply of 1_000_000
+  const send = async (destination: PublicKey, amount: number) => {
+    const ix = await createTransferCheckedWithTransferHookInstruction(
+      provider.connection,
+      treasury,
+      mint,
+      destination,
+      payer.publicKey,
+      BigInt(amount),
+      DECIMALS,
+      [],
+      "confirmed",
+      TOKEN_2022_PROGRAM_ID
+    );
+    await provider.sendAndConfirm(new Transaction().add(ix));
+  };
+
+  const expectError = async (promise: Promise<unknown>, error: string) => {
+    try {
+      await promise;
+      expect.fail(`expected ${error}`);
+    } catch (err) {
+      expect(err.toString()).to.contain(error);
+    }
+  };
+
+  before(async () => {
+    await airdrop(provider, stranger.publicKey);
+    mint = await createHookedMint(provider, payer, hookProgram.programId);
+    [cap] = PublicKey.findProgramAddressSync([Buffer.from("holder-cap"), mint.toBuffer()], hookProgram.programId);
+
+    await hookProgram.methods.initializeHolderCap(500).accountsPartial({ authority: payer.publicKey, mint, cap }).rpc();
+    treasury = await createTokenAccount(provider, payer, mint, payer.publicKey, 1_000_000);
+    await hookProgram.methods
+      .initializeExtraAccountMetaList()
+      .accountsPartial({ payer: payer.publicKey, mint, holderCap: cap, tokenProgram: TOKEN_2022_PROGRAM_ID })
+      .rpc();
+    await hookProgram.methods.addHolderCapExemption(payer.publicKey).accountsPartial({ cap }).rpc();
+
+    holder = await createTokenAccount(provider, payer, mint, Keypair.generate().publicKey);
+    vault = await createTokenAccount(provider, payer, mint, Keypair.generate().publicKey);
+  });
+
+  it("Lets a wallet fill up to the cap but not past it", async () => {
+    await send(holder, 50_000);
+    await expectError(send(holder, 1), "HolderCapExceeded");
+  });
+
+  it("Exempts configured accounts such as AMM vaults", async () => {
+    await expectError(send(vault, 200_000), "HolderCapExceeded");
+    await hookProgram.methods.addHolderCapExemption(vault).accountsPartial({ cap }).rpc();
+    await send(vault, 200_000);
+  });
+
+  it("Only lets the hook config authority change the cap", async () => {
+    await expectError(
+      hookProgram.methods.setHolderCap(10_000).accountsPartial({ authority: stranger.publicKey, cap }).signers([stranger]).rpc(),
+      "Unauthorized"
+    );
+    await expectError(hookProgram.methods.setHolderCap(10_001).accountsPartial({ cap }).rpc(), "InvalidHolderCap");
+    await hookProgram.methods.setHolderCap(1_000).accountsPartial({ cap }).rpc();
+    await send(holder, 50_000);
+  });
+});
@@ -0,0 +1,148 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { TokenHook } from "../target/types/token_hook";
+import { createTransferCheckedWithTransferHookInstruction, TOKEN_2022_PROGRAM_ID } from "@solana/spl-token";
+import { Keypair, PublicKey, Signer, Transaction } from "@solana/web3.js";
+import { expect } from "chai";
+import { airdrop, createHookedMint, createTokenAccount, DECIMALS } from "./helpers";
+
+describe("Transfer hook launch guard", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const hookProgram = anchor.workspace.TokenHook as Program<TokenHook>;
+  const payer = (provider.wallet as anchor.Wallet).payer;
+
+  const vaultOwner = Keypair.generate();
+  const buyer = Keypair.generate();
+  const sniper = Keypair.generate();
+
+  let mint: PublicKey;
+  let guard: PublicKey;
+  let vault: PublicKey;
+  let payerAccount: PublicKey;
+  let buyerAccount: PublicKey;
+  let sniperAccount: PublicKey;
+
+  const transfer = async (source: PublicKey, destination: PublicKey, owner: Signer, amount: number) => {
+    const ix = await createTransferCheckedWithTransferHookInstruction(
+      provider.connection,
+      source,
+      mint,
+      destination,
+      owner.publicKey,
+      BigInt(amount),
+      DECIMALS,
+      [],
+      "confirmed",
+      TOKEN_2022_PROGRAM_ID
+    );
+    await provider.sendAndConfirm(new Transaction().add(ix), [owner]);
+  };
+  const buy = (destination: PublicKey, amount: number) => transfer(vault, destination, vaultOwner, amount);
+
+  const expectError = async (promise: Promise<unknown>, error: string) => {
+    try {
+      await promise;
+      expect.fail(`expected ${error}`);
+    } catch (err) {
+      expect(err.toString()).to.contain(error);
+    }
+  };
+
+  const params = async (overrides: object = {}) => ({
+    endSlot: new anchor.BN((await provider.connection.getSlot()) + 100_000),
+    maxBuy: new anchor.BN(1_000),
+    maxHolding: new anchor.BN(1_500),
+    cooldownSlots: new anchor.BN(1_000_000),
+    ...overrides,
+  });
+
+  before(async () => {
+    await airdrop(provider, vaultOwner.publicKey);
+    mint = await createHookedMint(provider, payer, hookProgram.programId);
+    [guard] = PublicKey.findProgramAddressSync([Buffer.from("launch"), mint.toBuffer()], hookProgram.programId);
+
+    await hookProgram.methods
+      .initializeLaunchGuard(await params())
+      .accountsPartial({ authority: payer.publicKey, mint, guard })
+      .rpc();
+    await hookProgram.methods
+      .initializeExtraAccountMetaList()
+      .accountsPartial({ payer: payer.publicKey, mint, launchGuard: guard, tokenProgram: TOKEN_2022_PROGRAM_ID })
+      .rpc();
+
+    // Stands in for a pool vault owned by the AMM
+    vault = await createTokenAccount(provider, payer, mint, vaultOwner.publicKey, 1_000_000);
+    await hookProgram.methods.addLaunchVault(vault).accountsPartial({ guard }).rpc();
+
+    payerAccount = await createTokenAccount(provider, payer, mint, payer.publicKey, 1_000_000);
+    buyerAccount = await createTokenAccount(provider, payer, mint, buyer.publicKey);
+    sniperAccount = await createTokenAccount(provider, payer, mint, sniper.publicKey);
+    await hookProgram.methods.initializeWalletState(buyer.publicKey).accounts({ payer: payer.publicKey, mint }).rpc();
+  });
+
+  it("Caps a single buy", async () => {
+    await expectError(buy(buyerAccount, 2_000), "BuyTooLarge");
+    await buy(buyerAccount, 1_000);
+  });
+
+  it("Caps the buyer's holdings", async () => {
+    await expectError(buy(buyerAccount, 600), "HoldingTooLarge");
+  });
+
+  it("Makes the buyer wait out the cooldown", async () => {
+    await expectError(buy(buyerAccount, 400), "BuyCooldown");
+    await hookProgram.methods
+      .setLaunchParams(await params({ cooldownSlots: new anchor.BN(0) }))
+      .accountsPartial({ guard })
+      .rpc();
+    await buy(buyerAccount, 400);
+  });
+
+  it("Counts what the buyer bought, not what they still hold", async () => {
+    // 1_400 bought so far; sending it on does not free up the holding limit
+    await transfer(buyerAccount, payerAccount, buyer, 1_400);
+    await expectError(buy(buyerAccount, 200), "HoldingTooLarge");
+    await buy(buyerAccount, 100);
+  });
+
+  it("Rejects launch limits that could never allow a buy", async () => {
+    for (const overrides of [{ maxBuy: new anchor.BN(0) }, { maxBuy: new anchor.BN(2_000) }]) {
+      await expectError(
+        hookProgram.methods
+          .setLaunchParams(await params(overrides))
+          .accountsPartial({ guard })
+          .rpc(),
+        "InvalidLaunchParams"
+      );
+    }
+  });
+
+  it("Requires a wallet state to buy", async () => {
+    await expectError(buy(sniperAccount, 100), "WalletStateMissing");
+  });
+
+  it("Leaves transfers that are not buys alone", async () => {
+    await transfer(payerAccount, sniperAccount, payer, 5_000);
+  });
+
+  it("Only lets the hook config authority change the launch", async () => {
+    await expectError(
+      hookProgram.methods
+        .addLaunchVault(sniperAccount)
+        .accountsPartial({ authority: vaultOwner.publicKey, guard })
+        .signers([vaultOwner])
+        .rpc(),
+      "Unauthorized"
+    );
+  });
+
+  it("Lapses at the end slot", async () => {
+    await hookProgram.methods
+      .setLaunchParams(await params({ endSlot: new anchor.BN(await provider.connection.getSlot()) }))
+      .accountsPartial({ guard })
+      .rpc();
+    await buy(sniperAccount, 10_000);
+  });
+});
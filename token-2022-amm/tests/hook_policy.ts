@@ -0,0 +1,120 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { TokenHook } from "../target/types/token_hook";
+import { createTransferCheckedWithTransferHookInstruction, TOKEN_2022_PROGRAM_ID } from "@solana/spl-token";
+import { Keypair, PublicKey, Transaction } from "@solana/web3.js";
+import { expect } from "chai";
+import { airdrop, createHookedMint, createTokenAccount, DECIMALS } from "./helpers";
+
+describe("Transfer hook wallet policy", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const hookProgram = anchor.workspace.TokenHook as Program<TokenHook>;
+  const payer = (provider.wallet as anchor.Wallet).payer;
+
+  const friend = Keypair.generate();
+  const blocked = Keypair.generate();
+
+  let mint: PublicKey;
+  let policy: PublicKey;
+  let payerAccount: PublicKey;
+  let friendAccount: PublicKey;
+  let blockedAccount: PublicKey;
+
+  const transfer = async (source: PublicKey, destination: PublicKey, owner: Keypair, amount: number) => {
+    const ix = await createTransferCheckedWithTransferHookInstruction(
+      provider.connection,
+      source,
+      mint,
+      destination,
+      owner.publicKey,
+      BigInt(amount),
+      DECIMALS,
+      [],
+      "confirmed",
+      TOKEN_2022_PROGRAM_ID
+    );
+    await provider.sendAndConfirm(new Transaction().add(ix), [owner]);
+  };
+
+  const expectBlocked = async (source: PublicKey, destination: PublicKey, owner: Keypair) => {
+    try {
+      await transfer(source, destination, owner, 1_000);
+      expect.fail("transfer should be blocked by the policy");
+    } catch (err) {
+      expect(err.toString()).to.contain("TransferNotAllowed");
+    }
+  };
+
+  before(async () => {
+    await airdrop(provider, friend.publicKey);
+    await airdrop(provider, blocked.publicKey);
+    mint = await createHookedMint(provider, payer, hookProgram.programId);
+    [policy] = PublicKey.findProgramAddressSync([Buffer.from("policy"), mint.toBuffer()], hookProgram.programId);
+
+    payerAccount = await createTokenAccount(provider, payer, mint, payer.publicKey, 1_000_000_000);
+    friendAccount = await createTokenAccount(provider, payer, mint, friend.publicKey);
+    blockedAccount = await createTokenAccount(provider, payer, mint, blocked.publicKey);
+  });
+
+  it("Only lets the mint authority create a policy", async () => {
+    try {
+      await hookProgram.methods
+        .initializePolicy({ denylist: {} })
+        .accountsPartial({ authority: friend.publicKey, mint, policy })
+        .signers([friend])
+        .rpc();
+      expect.fail("policy creation should require the mint authority");
+    } catch (err) {
+      expect(err.toString()).to.contain("Unauthorized");
+    }
+  });
+
+  it("Wires the policy into the ExtraAccountMetaList", async () => {
+    await hookProgram.methods
+      .initializePolicy({ denylist: {} })
+      .accountsPartial({ authority: payer.publicKey, mint, policy })
+      .rpc();
+    await hookProgram.methods
+      .initializeExtraAccountMetaList()
+      .accountsPartial({ payer: payer.publicKey, mint, policy, tokenProgram: TOKEN_2022_PROGRAM_ID })
+      .rpc();
+    await hookProgram.methods.addPolicyWallet(blocked.publicKey).accountsPartial({ policy }).rpc();
+
+    const state = await hookProgram.account.transferPolicy.fetch(policy);
+    expect(state.wallets.map((w) => w.toBase58())).to.deep.equal([blocked.publicKey.toBase58()]);
+  });
+
+  it("Rejects transfers to or from a denylisted owner", async () => {
+    await transfer(payerAccount, friendAccount, payer, 1_000);
+    await expectBlocked(payerAccount, blockedAccount, payer);
+
+    await hookProgram.methods.removePolicyWallet(blocked.publicKey).accountsPartial({ policy }).rpc();
+    await transfer(payerAccount, blockedAccount, payer, 1_000);
+    await hookProgram.methods.addPolicyWallet(blocked.publicKey).accountsPartial({ policy }).rpc();
+    await expectBlocked(blockedAccount, friendAccount, blocked);
+  });
+
+  it("Admits only listed owners in allowlist mode", async () => {
+    await hookProgram.methods.setPolicyMode({ allowlist: {} }).accountsPartial({ policy }).rpc();
+    await hookProgram.methods.addPolicyWallet(payer.publicKey).accountsPartial({ policy }).rpc();
+    await expectBlocked(payerAccount, friendAccount, payer);
+
+    await hookProgram.methods.addPolicyWallet(friend.publicKey).accountsPartial({ policy }).rpc();
+    await transfer(payerAccount, friendAccount, payer, 1_000);
+  });
+
+  it("Rejects list changes from anyone but the hook config authority", async () => {
+    try {
+      await hookProgram.methods
+        .removePolicyWallet(blocked.publicKey)
+        .accountsPartial({ authority: blocked.publicKey, policy })
+        .signers([blocked])
+        .rpc();
+      expect.fail("only the hook config authority may edit the lists");
+    } catch (err) {
+      expect(err.toString()).to.contain("Unauthorized");
+    }
+  });
+});
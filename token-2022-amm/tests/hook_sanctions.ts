@@ -0,0 +1,203 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { TokenHook } from "../target/types/token_hook";
+import { createTransferCheckedWithTransferHookInstruction, TOKEN_2022_PROGRAM_ID } from "@solana/spl-token";
+import { Keypair, PublicKey, Transaction } from "@solana/web3.js";
+import { createHash } from "crypto";
+import { expect } from "chai";
+import { createHookedMint, createTokenAccount, DECIMALS } from "./helpers";
+
+const sha256 = (...parts: Buffer[]) => createHash("sha256").update(Buffer.concat(parts)).digest();
+
+/// Sorted Merkle tree of blocked keys between the all-zero and all-0xff
+/// sentinels, padded to a power of two with the upper sentinel
+class SanctionsTree {
+  readonly leaves: Buffer[];
+  readonly levels: Buffer[][];
+
+  constructor(blocked: PublicKey[]) {
+    const keys = [Buffer.alloc(32), ...blocked.map((key) => key.toBuffer()).sort(Buffer.compare)];
+    while (keys.length < 2 || (keys.length & (keys.length - 1)) !== 0) {
+      keys.push(Buffer.alloc(32, 0xff));
+    }
+    this.leaves = keys;
+    this.levels = [keys.map((key) => sha256(Buffer.from([0]), key))];
+    while (this.levels[this.levels.length - 1].length > 1) {
+      const below = this.levels[this.levels.length - 1];
+      const level = [];
+      for (let i = 0; i < below.length; i += 2) {
+        level.push(sha256(Buffer.from([1]), below[i], below[i + 1]));
+      }
+      this.levels.push(level);
+    }
+  }
+
+  get depth(): number {
+    return this.levels.length - 1;
+  }
+
+  get root(): number[] {
+    return [...this.levels[this.levels.length - 1][0]];
+  }
+
+  proof(index: number): number[][] {
+    return this.levels.slice(0, -1).map((level, depth) => [...level[(index >> depth) ^ 1]]);
+  }
+
+  /// Index of the leaf at or just below `owner`
+  lowIndex(owner: PublicKey): number {
+    const key = owner.toBuffer();
+    let index = 0;
+    while (Buffer.compare(this.leaves[index + 1], key) <= 0) {
+      index++;
+    }
+    return index;
+  }
+}
+
+describe("Transfer hook sanctions screening", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const hookProgram = anchor.workspace.TokenHook as Program<TokenHook>;
+  const payer = (provider.wallet as anchor.Wallet).payer;
+
+  const alice = Keypair.generate().publicKey;
+  const bob = Keypair.generate().publicKey;
+  const carol = Keypair.generate().publicKey;
+
+  let mint: PublicKey;
+  let config: PublicKey;
+  let payerAccount: PublicKey;
+  let aliceAccount: PublicKey;
+  let bobAccount: PublicKey;
+  let carolAccount: PublicKey;
+  let listed: SanctionsTree;
+
+  const setRoot = (tree: SanctionsTree) =>
+    hookProgram.methods
+      .setSanctionsRoot(tree.root, tree.depth)
+      .accountsPartial({ authority: payer.publicKey, config })
+      .rpc();
+
+  const submitProof = (tree: SanctionsTree, owner: PublicKey, depth = tree.depth) => {
+    const index = tree.lowIndex(owner);
+    return hookProgram.methods
+      .submitSanctionsProof(
+        owner,
+        index,
+        new PublicKey(tree.leaves[index]),
+        new PublicKey(tree.leaves[index + 1]),
+        tree.proof(index).slice(0, depth),
+        tree.proof(index + 1).slice(0, depth)
+      )
+      .accountsPartial({
+        payer: payer.publicKey,
+        config,
+        proof: PublicKey.findProgramAddressSync(
+          [Buffer.from("sanctions-proof"), mint.toBuffer(), owner.toBuffer()],
+          hookProgram.programId
+        )[0],
+      })
+      .rpc();
+  };
+
+  const send = async (destination: PublicKey) => {
+    const ix = await createTransferCheckedWithTransferHookInstruction(
+      provider.connection,
+      payerAccount,
+      mint,
+      destination,
+      payer.publicKey,
+      BigInt(1_000),
+      DECIMALS,
+      [],
+      "confirmed",
+      TOKEN_2022_PROGRAM_ID
+    );
+    await provider.sendAndConfirm(new Transaction().add(ix));
+  };
+
+  const expectError = async (promise: Promise<unknown>, error: string) => {
+    try {
+      await promise;
+      expect.fail(`expected ${error}`);
+    } catch (err) {
+      expect(err.toString()).to.contain(error);
+    }
+  };
+
+  before(async () => {
+    mint = await createHookedMint(provider, payer, hookProgram.programId);
+    [config] = PublicKey.findProgramAddressSync([Buffer.from("hook-config"), mint.toBuffer()], hookProgram.programId);
+
+    await hookProgram.methods
+      .initializeExtraAccountMetaList()
+      .accounts({ payer: payer.publicKey, mint, tokenProgram: TOKEN_2022_PROGRAM_ID })
+      .rpc();
+
+    payerAccount = await createTokenAccount(provider, payer, mint, payer.publicKey, 1_000_000);
+    aliceAccount = await createTokenAccount(provider, payer, mint, alice);
+    bobAccount = await createTokenAccount(provider, payer, mint, bob);
+    carolAccount = await createTokenAccount(provider, payer, mint, carol);
+  });
+
+  it("Lets transfers through while no root is set", async () => {
+    await send(aliceAccount);
+  });
+
+  it("Moves tokens between owners proven absent from the list", async () => {
+    listed = new SanctionsTree([bob, Keypair.generate().publicKey, Keypair.generate().publicKey]);
+    await setRoot(listed);
+    await hookProgram.methods.syncExtraAccountMetaList().accountsPartial({ authority: payer.publicKey, config }).rpc();
+    await submitProof(listed, payer.publicKey);
+    await submitProof(listed, alice);
+    await send(aliceAccount);
+  });
+
+  it("Rejects a receiver without a proof", async () => {
+    await expectError(send(carolAccount), "SanctionsProofMissing");
+  });
+
+  it("Refuses to record a proof for a listed owner", async () => {
+    await expectError(submitProof(listed, bob), "SanctionedAddress");
+    await expectError(send(bobAccount), "SanctionsProofMissing");
+  });
+
+  it("Rejects proofs made stale by a new root until they are resubmitted", async () => {
+    const tree = new SanctionsTree([bob, carol]);
+    await setRoot(tree);
+    await expectError(send(aliceAccount), "SanctionsProofInvalid");
+    await submitProof(tree, payer.publicKey);
+    await submitProof(tree, alice);
+    await send(aliceAccount);
+  });
+
+  it("Rejects proofs shorter than the tree is deep", async () => {
+    const tree = new SanctionsTree([bob, carol]);
+    await submitProof(tree, alice);
+    await expectError(submitProof(tree, alice, tree.depth - 1), "SanctionsProofInvalid");
+  });
+
+  it("Rejects a tree deeper than proofs may be", async () => {
+    await expectError(
+      hookProgram.methods
+        .setSanctionsRoot(new SanctionsTree([]).root, 21)
+        .accountsPartial({ authority: payer.publicKey, config })
+        .rpc(),
+      "InvalidSanctionsDepth"
+    );
+  });
+
+  it("Only lets the config authority set the root", async () => {
+    const stranger = Keypair.generate();
+    await expectError(
+      hookProgram.methods
+        .setSanctionsRoot(new SanctionsTree([]).root, 1)
+        .accountsPartial({ authority: stranger.publicKey, config })
+        .signers([stranger])
+        .rpc(),
+      "Unauthorized"
+    );
+  });
+});
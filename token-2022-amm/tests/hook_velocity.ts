@@ -0,0 +1,115 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { TokenHook } from "../target/types/token_hook";
+import { createTransferCheckedWithTransferHookInstruction, TOKEN_2022_PROGRAM_ID } from "@solana/spl-token";
+import { Keypair, PublicKey, Signer, Transaction } from "@solana/web3.js";
+import { expect } from "chai";
+import { airdrop, createHookedMint, createTokenAccount, DECIMALS } from "./helpers";
+
+const DAY = 86_400;
+
+describe("Transfer hook velocity limits", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const hookProgram = anchor.workspace.TokenHook as Program<TokenHook>;
+  const payer = (provider.wallet as anchor.Wallet).payer;
+
+  const newcomer = Keypair.generate();
+  const vaultOwner = Keypair.generate();
+
+  let mint: PublicKey;
+  let limit: PublicKey;
+  let payerAccount: PublicKey;
+  let newcomerAccount: PublicKey;
+  let vault: PublicKey;
+  let receiver: PublicKey;
+
+  const transfer = async (source: PublicKey, owner: Signer, amount: number) => {
+    const ix = await createTransferCheckedWithTransferHookInstruction(
+      provider.connection,
+      source,
+      mint,
+      receiver,
+      owner.publicKey,
+      BigInt(amount),
+      DECIMALS,
+      [],
+      "confirmed",
+      TOKEN_2022_PROGRAM_ID
+    );
+    await provider.sendAndConfirm(new Transaction().add(ix), [owner]);
+  };
+
+  const expectError = async (promise: Promise<unknown>, error: string) => {
+    try {
+      await promise;
+      expect.fail(`expected ${error}`);
+    } catch (err) {
+      expect(err.toString()).to.contain(error);
+    }
+  };
+
+  before(async () => {
+    await airdrop(provider, newcomer.publicKey);
+    await airdrop(provider, vaultOwner.publicKey);
+    mint = await createHookedMint(provider, payer, hookProgram.programId);
+    [limit] = PublicKey.findProgramAddressSync([Buffer.from("velocity"), mint.toBuffer()], hookProgram.programId);
+
+    await hookProgram.methods
+      .initializeVelocityLimit(new anchor.BN(10_000), new anchor.BN(DAY))
+      .accountsPartial({ authority: payer.publicKey, mint, limit })
+      .rpc();
+    await hookProgram.methods
+      .initializeExtraAccountMetaList()
+      .accountsPartial({ payer: payer.publicKey, mint, velocityLimit: limit, tokenProgram: TOKEN_2022_PROGRAM_ID })
+      .rpc();
+    await hookProgram.methods.initializeWalletState(payer.publicKey).accounts({ payer: payer.publicKey, mint }).rpc();
+
+    payerAccount = await createTokenAccount(provider, payer, mint, payer.publicKey, 1_000_000);
+    newcomerAccount = await createTokenAccount(provider, payer, mint, newcomer.publicKey, 1_000_000);
+    vault = await createTokenAccount(provider, payer, mint, vaultOwner.publicKey, 1_000_000);
+    receiver = await createTokenAccount(provider, payer, mint, Keypair.generate().publicKey);
+  });
+
+  it("Rejects a transfer that would exceed the owner's window", async () => {
+    await transfer(payerAccount, payer, 6_000);
+    await expectError(transfer(payerAccount, payer, 5_000), "VelocityLimitExceeded");
+    // The rest of the cap is still free within the window
+    await transfer(payerAccount, payer, 4_000);
+    await expectError(transfer(payerAccount, payer, 1), "VelocityLimitExceeded");
+  });
+
+  it("Requires the sender to have a wallet state", async () => {
+    await expectError(transfer(newcomerAccount, newcomer, 1_000), "WalletStateMissing");
+    await hookProgram.methods.initializeWalletState(newcomer.publicKey).accounts({ payer: payer.publicKey, mint }).rpc();
+    await transfer(newcomerAccount, newcomer, 1_000);
+  });
+
+  it("Lets exempt accounts such as AMM vaults send past the cap", async () => {
+    await expectError(transfer(vault, vaultOwner, 50_000), "WalletStateMissing");
+    await hookProgram.methods.addVelocityExemption(vault).accountsPartial({ limit }).rpc();
+    await transfer(vault, vaultOwner, 50_000);
+  });
+
+  it("Only lets the hook config authority tune the limit", async () => {
+    await expectError(
+      hookProgram.methods
+        .setVelocityLimit(new anchor.BN(1_000_000), new anchor.BN(DAY))
+        .accountsPartial({ authority: newcomer.publicKey, limit })
+        .signers([newcomer])
+        .rpc(),
+      "Unauthorized"
+    );
+    await hookProgram.methods.setVelocityLimit(new anchor.BN(20_000), new anchor.BN(DAY)).accountsPartial({ limit }).rpc();
+    await transfer(payerAccount, payer, 5_000);
+  });
+
+  it("Opens a new window once the last one has ended", async () => {
+    await hookProgram.methods.setVelocityLimit(new anchor.BN(20_000), new anchor.BN(3)).accountsPartial({ limit }).rpc();
+    await new Promise((resolve) => setTimeout(resolve, 4_000));
+    // The 15_000 sent so far no longer counts
+    await transfer(payerAccount, payer, 20_000);
+    await expectError(transfer(payerAccount, payer, 1), "VelocityLimitExceeded");
+  });
+});
@@ -0,0 +1,297 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { Token2022Amm } from "../target/types/token_2022_amm";
+import { TokenHook } from "../target/types/token_hook";
+import { getAccount, TOKEN_2022_PROGRAM_ID } from "@solana/spl-token";
+import { AccountMeta, Keypair, PublicKey } from "@solana/web3.js";
+import { expect } from "chai";
+import {
+  airdrop,
+  createHookedMint,
+  createMint,
+  createTokenAccount,
+  extraMetasPda,
+  initializeAmmIfNeeded,
+  observationsPda,
+  poolPda,
+  setupPool,
+  sortMints,
+  statsPda,
+} from "./helpers";
+
+const Q64 = new anchor.BN(1).shln(64);
+
+describe("Bonding-curve launchpad", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const ammProgram = anchor.workspace.Token2022Amm as Program<Token2022Amm>;
+  const hookProgram = anchor.workspace.TokenHook as Program<TokenHook>;
+  const payer = (provider.wallet as anchor.Wallet).payer;
+
+  let amm: PublicKey;
+  let tokenMint: PublicKey;
+  let quoteMint: PublicKey;
+  let launch: PublicKey;
+  let userToken: PublicKey;
+  let userQuote: PublicKey;
+  let tokenVault: PublicKey;
+  let quoteVault: PublicKey;
+
+  // Price runs linearly from 0.001 to 0.01 quote units per token unit over
+  // the 800e9 units for sale; 1e12 units exist
+  const curve = {
+    linear: {
+      basePrice: Q64.divn(1_000),
+      slope: Q64.muln(9).div(new anchor.BN("800000000000000")),
+    },
+  };
+
+  const hookMetas = (): AccountMeta[] => [
+    { pubkey: extraMetasPda(tokenMint, hookProgram.programId), isSigner: false, isWritable: false },
+    { pubkey: hookProgram.programId, isSigner: false, isWritable: false },
+  ];
+
+  const balance = async (account: PublicKey) =>
+    Number((await getAccount(provider.connection, account, undefined, TOKEN_2022_PROGRAM_ID)).amount);
+
+  const trade = (method: "buyLaunchTokens" | "sellLaunchTokens", tokenAmount: number, quoteLimit: number) =>
+    ammProgram.methods[method](new anchor.BN(tokenAmount), new anchor.BN(quoteLimit))
+      .accountsPartial({
+        launch,
+        user: payer.publicKey,
+        userTokenAccount: userToken,
+        userQuoteAccount: userQuote,
+        tokenMint,
+        quoteMint,
+        tokenVault,
+        quoteVault,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .remainingAccounts(hookMetas())
+      .rpc();
+
+  before(async () => {
+    amm = await initializeAmmIfNeeded(ammProgram);
+    tokenMint = await createHookedMint(provider, payer, hookProgram.programId);
+    await hookProgram.methods
+      .initializeExtraAccountMetaList()
+      .accounts({ payer: payer.publicKey, mint: tokenMint, tokenProgram: TOKEN_2022_PROGRAM_ID })
+      .rpc();
+    quoteMint = await createMint(provider, payer, payer.publicKey);
+
+    [launch] = PublicKey.findProgramAddressSync(
+      [Buffer.from("launch"), tokenMint.toBuffer()],
+      ammProgram.programId
+    );
+    userToken = await createTokenAccount(provider, payer, tokenMint, payer.publicKey, 1_000_000_000_000);
+    userQuote = await createTokenAccount(provider, payer, quoteMint, payer.publicKey, 1_000_000_000_000);
+    tokenVault = await createTokenAccount(provider, payer, tokenMint, launch);
+    quoteVault = await createTokenAccount(provider, payer, quoteMint, launch);
+
+    await ammProgram.methods
+      .createLaunch(
+        curve,
+        new anchor.BN(800_000_000_000),
+        new anchor.BN(200_000_000_000),
+        new anchor.BN(5_000_000_000) // Graduates at a price of 0.005
+      )
+      .accountsPartial({
+        launch,
+        creator: payer.publicKey,
+        creatorTokenAccount: userToken,
+        tokenMint,
+        quoteMint,
+        tokenVault,
+        quoteVault,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .remainingAccounts(hookMetas())
+      .rpc();
+  });
+
+  it("Escrows the sale and liquidity supply", async () => {
+    expect(await balance(tokenVault)).to.equal(1_000_000_000_000);
+  });
+
+  // Pool of the launch token against `otherMint`, seeded from the payer
+  const createTokenPool = async (otherMint: PublicKey, userOther: PublicKey) => {
+    const [mintA, mintB] = sortMints(tokenMint, otherMint);
+    const pool = poolPda(ammProgram, mintA, mintB);
+    const [userTokenA, userTokenB] = mintA.equals(tokenMint) ? [userToken, userOther] : [userOther, userToken];
+    await ammProgram.methods
+      .createPool(new anchor.BN(1_000_000), new anchor.BN(1_000_000), { constantProduct: {} })
+      .accountsPartial({
+        pool,
+        amm,
+        observations: observationsPda(ammProgram, pool),
+        user: payer.publicKey,
+        userTokenA,
+        userTokenB,
+        tokenAMint: mintA,
+        tokenBMint: mintB,
+        tokenAVault: await createTokenAccount(provider, payer, mintA, amm),
+        tokenBVault: await createTokenAccount(provider, payer, mintB, amm),
+        transferHookProgram: hookProgram.programId,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .remainingAccounts(hookMetas())
+      .rpc();
+    return pool;
+  };
+
+  it("Keeps the pair's pool for graduation", async () => {
+    try {
+      await createTokenPool(quoteMint, userQuote);
+      expect.fail("the pool should be reserved for the launch");
+    } catch (err) {
+      expect(err.toString()).to.contain("LaunchInProgress");
+    }
+  });
+
+  it("Leaves pairs with other mints open", async () => {
+    const otherMint = await createMint(provider, payer, payer.publicKey);
+    const userOther = await createTokenAccount(provider, payer, otherMint, payer.publicKey, 1_000_000_000);
+    const pool = await createTokenPool(otherMint, userOther);
+    expect((await ammProgram.account.pool.fetch(pool)).tokenAAmount.toNumber()).to.equal(1_000_000);
+  });
+
+  it("Only lets the mint or hook authority launch a token", async () => {
+    const foreignMint = await createMint(provider, payer, Keypair.generate().publicKey);
+    const [foreignLaunch] = PublicKey.findProgramAddressSync(
+      [Buffer.from("launch"), foreignMint.toBuffer()],
+      ammProgram.programId
+    );
+    try {
+      await ammProgram.methods
+        .createLaunch(curve, new anchor.BN(1_000_000), new anchor.BN(1_000_000), new anchor.BN(1_000_000))
+        .accountsPartial({
+          launch: foreignLaunch,
+          creator: payer.publicKey,
+          creatorTokenAccount: await createTokenAccount(provider, payer, foreignMint, payer.publicKey),
+          tokenMint: foreignMint,
+          quoteMint,
+          tokenVault: await createTokenAccount(provider, payer, foreignMint, foreignLaunch),
+          quoteVault: await createTokenAccount(provider, payer, quoteMint, foreignLaunch),
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+        })
+        .rpc();
+      expect.fail("the payer has no authority over the mint");
+    } catch (err) {
+      expect(err.toString()).to.contain("NotMintAuthority");
+    }
+  });
+
+  it("Refuses to launch a token whose pool already exists", async () => {
+    const fx = await setupPool(ammProgram, hookProgram, 1_000_000, 1_000_000);
+    const [otherLaunch] = PublicKey.findProgramAddressSync(
+      [Buffer.from("launch"), fx.mintA.toBuffer()],
+      ammProgram.programId
+    );
+    try {
+      await ammProgram.methods
+        .createLaunch(curve, new anchor.BN(1_000_000), new anchor.BN(1_000_000), new anchor.BN(1_000_000))
+        .accountsPartial({
+          launch: otherLaunch,
+          creator: payer.publicKey,
+          creatorTokenAccount: fx.userTokenA,
+          tokenMint: fx.mintA,
+          quoteMint: fx.mintB,
+          pool: fx.pool,
+          tokenVault: await createTokenAccount(provider, payer, fx.mintA, otherLaunch),
+          quoteVault: await createTokenAccount(provider, payer, fx.mintB, otherLaunch),
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+        })
+        .rpc();
+      expect.fail("the pair already has a pool");
+    } catch (err) {
+      expect(err.toString()).to.contain("PoolAlreadyExists");
+    }
+  });
+
+  it("Charges the area under the curve and refunds it on sells", async () => {
+    let before = await balance(userQuote);
+    await trade("buyLaunchTokens", 100_000_000_000, 200_000_000);
+    // 0.001 * 1e11 + (0.009 / 8e11) * (1e11)^2 / 2
+    expect(before - (await balance(userQuote))).to.be.within(156_250_000, 156_250_001);
+
+    before = await balance(userQuote);
+    await trade("sellLaunchTokens", 50_000_000_000, 0);
+    // 0.001 * 5e10 + (0.009 / 8e11) * ((1e11)^2 - (5e10)^2) / 2
+    expect((await balance(userQuote)) - before).to.be.within(92_187_499, 92_187_500);
+
+    const state = await ammProgram.account.launch.fetch(launch);
+    expect(state.tokensSold.toNumber()).to.equal(50_000_000_000);
+    expect(state.quoteRaised.toNumber()).to.equal(await balance(quoteVault));
+  });
+
+  it("Enforces the buyer's price limit", async () => {
+    try {
+      await trade("buyLaunchTokens", 10_000_000_000, 1_000);
+      expect.fail("buy above the limit should fail");
+    } catch (err) {
+      expect(err.toString()).to.contain("ExcessiveInputAmount");
+    }
+  });
+
+  it("Stops trading once the market cap threshold is reached", async () => {
+    await trade("buyLaunchTokens", 400_000_000_000, 10_000_000_000);
+    const state = await ammProgram.account.launch.fetch(launch);
+    expect(state.complete).to.equal(true);
+
+    try {
+      await trade("buyLaunchTokens", 1_000, 1_000_000);
+      expect.fail("completed launch should not trade");
+    } catch (err) {
+      expect(err.toString()).to.contain("LaunchComplete");
+    }
+  });
+
+  it("Graduates permissionlessly into a pool with locked liquidity", async () => {
+    const cranker = Keypair.generate();
+    await airdrop(provider, cranker.publicKey);
+
+    const [mintA, mintB] = sortMints(tokenMint, quoteMint);
+    const pool = poolPda(ammProgram, mintA, mintB);
+    const raised = (await ammProgram.account.launch.fetch(launch)).quoteRaised;
+    const graduate = async () =>
+      ammProgram.methods
+        .graduateLaunch()
+        .accountsPartial({
+          launch,
+          payer: cranker.publicKey,
+          pool,
+          amm,
+          observations: observationsPda(ammProgram, pool),
+          stats: statsPda(ammProgram, pool),
+          tokenMint,
+          quoteMint,
+          tokenVault,
+          quoteVault,
+          poolTokenVault: await createTokenAccount(provider, payer, tokenMint, amm),
+          poolQuoteVault: await createTokenAccount(provider, payer, quoteMint, amm),
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+        })
+        .remainingAccounts(hookMetas())
+        .signers([cranker])
+        .rpc();
+    await graduate();
+
+    const state = await ammProgram.account.pool.fetch(pool);
+    const [tokenReserve, quoteReserve] = mintA.equals(tokenMint)
+      ? [state.tokenAAmount, state.tokenBAmount]
+      : [state.tokenBAmount, state.tokenAAmount];
+    expect(tokenReserve.toNumber()).to.equal(200_000_000_000);
+    expect(quoteReserve.toString()).to.equal(raised.toString());
+    expect(state.owner.toBase58()).to.equal(launch.toBase58());
+    expect(state.lpSupply.toNumber()).to.be.above(0);
+    expect((await ammProgram.account.launch.fetch(launch)).pool.toBase58()).to.equal(pool.toBase58());
+
+    try {
+      await graduate();
+      expect.fail("a launch graduates only once");
+    } catch (err) {
+      expect(err.toString()).to.contain("AlreadyGraduated");
+    }
+  });
+});
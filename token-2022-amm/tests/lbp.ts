@@ -0,0 +1,133 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { Token2022Amm } from "../target/types/token_2022_amm";
+import { TokenHook } from "../target/types/token_hook";
+import { TOKEN_2022_PROGRAM_ID } from "@solana/spl-token";
+import { Keypair } from "@solana/web3.js";
+import { expect } from "chai";
+import {
+  airdrop,
+  createTokenAccount,
+  hookAccounts,
+  observationsPda,
+  PoolFixture,
+  setupPool,
+  statsPda,
+} from "./helpers";
+
+const HOUR = 3_600;
+
+const lbpCurve = (startWeightA: number, endWeightA: number, startTs: number, endTs: number) => ({
+  lbp: {
+    startWeightA: new anchor.BN(startWeightA),
+    endWeightA: new anchor.BN(endWeightA),
+    startTs: new anchor.BN(startTs),
+    endTs: new anchor.BN(endTs),
+  },
+});
+
+describe("Liquidity bootstrapping pools", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const ammProgram = anchor.workspace.Token2022Amm as Program<Token2022Amm>;
+  const hookProgram = anchor.workspace.TokenHook as Program<TokenHook>;
+  const payer = (provider.wallet as anchor.Wallet).payer;
+
+  // Sale starting in an hour: still at its 80/20 start weights
+  let upcoming: PoolFixture;
+  // Sale that ended an hour ago: settled at 50/50
+  let ended: PoolFixture;
+
+  const now = async () => provider.connection.getBlockTime(await provider.connection.getSlot());
+
+  const quote = (fx: PoolFixture, amountIn: number) =>
+    ammProgram.methods
+      .quoteSwap(new anchor.BN(amountIn), fx.mintA)
+      .accounts({ pool: fx.pool, amm: fx.amm })
+      .view();
+
+  const deposit = async (fx: PoolFixture, depositor: Keypair) => {
+    return ammProgram.methods
+      .addLiquidity(new anchor.BN(1_000_000), new anchor.BN(4_000_000))
+      .accountsPartial({
+        pool: fx.pool,
+        amm: fx.amm,
+        observations: observationsPda(ammProgram, fx.pool),
+        stats: statsPda(ammProgram, fx.pool),
+        user: depositor.publicKey,
+        userTokenA: await createTokenAccount(provider, payer, fx.mintA, depositor.publicKey, 1_000_000),
+        userTokenB: await createTokenAccount(provider, payer, fx.mintB, depositor.publicKey, 4_000_000),
+        userLpToken: await createTokenAccount(provider, payer, fx.lpMint, depositor.publicKey),
+        tokenAMint: fx.mintA,
+        tokenBMint: fx.mintB,
+        tokenAVault: fx.vaultA,
+        tokenBVault: fx.vaultB,
+        lpMint: fx.lpMint,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .remainingAccounts(hookAccounts(fx))
+      .signers([depositor])
+      .rpc();
+  };
+
+  before(async () => {
+    const t = await now();
+    upcoming = await setupPool(
+      ammProgram,
+      hookProgram,
+      1_000_000_000,
+      4_000_000_000,
+      lbpCurve(800_000_000, 200_000_000, t + HOUR, t + 2 * HOUR)
+    );
+    ended = await setupPool(
+      ammProgram,
+      hookProgram,
+      1_000_000_000,
+      4_000_000_000,
+      lbpCurve(900_000_000, 500_000_000, t - 2 * HOUR, t - HOUR)
+    );
+  });
+
+  it("Records the creator as pool owner", async () => {
+    const pool = await ammProgram.account.pool.fetch(upcoming.pool);
+    expect(pool.owner.toBase58()).to.equal(payer.publicKey.toBase58());
+  });
+
+  it("Prices at the start weights before the sale opens", async () => {
+    // Same as an 80/20 weighted pool; see weighted.ts for the reference
+    const q = await quote(upcoming, 10_000_000);
+    expect(q.amountOut.toNumber()).to.be.within(155_698_011, 155_698_012);
+  });
+
+  it("Prices at the end weights once the sale is over", async () => {
+    // 50/50 is constant product: floor(4e9 * 9_975_000 / 1_009_975_000)
+    const q = await quote(ended, 10_000_000);
+    expect(q.amountOut.toNumber()).to.be.within(39_505_927, 39_505_928);
+  });
+
+  it("Accepts deposits only from the owner until the sale ends", async () => {
+    const stranger = Keypair.generate();
+    await airdrop(provider, stranger.publicKey);
+
+    try {
+      await deposit(upcoming, stranger);
+      expect.fail("non-owner deposit should be rejected during the sale");
+    } catch (err) {
+      expect(err.toString()).to.contain("SaleInProgress");
+    }
+
+    await deposit(upcoming, payer);
+    await deposit(ended, stranger);
+  });
+
+  it("Rejects a sale window that ends before it starts", async () => {
+    const t = await now();
+    try {
+      await setupPool(ammProgram, hookProgram, 1_000_000, 1_000_000, lbpCurve(800_000_000, 200_000_000, t, t));
+      expect.fail("empty sale window should be rejected");
+    } catch (err) {
+      expect(err.toString()).to.contain("InvalidCurveParameters");
+    }
+  });
+});
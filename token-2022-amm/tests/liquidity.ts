@@ -0,0 +1,85 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { Token2022Amm } from "../target/types/token_2022_amm";
+import { TokenHook } from "../target/types/token_hook";
+import { getAccount, TOKEN_2022_PROGRAM_ID } from "@solana/spl-token";
+import { PublicKey } from "@solana/web3.js";
+import { expect } from "chai";
+import {
+  createMint,
+  createTokenAccount,
+  hookAccounts,
+  observationsPda,
+  PoolFixture,
+  setupPool,
+  statsPda,
+} from "./helpers";
+
+describe("Liquidity through the swap curve", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const ammProgram = anchor.workspace.Token2022Amm as Program<Token2022Amm>;
+  const hookProgram = anchor.workspace.TokenHook as Program<TokenHook>;
+  const payer = (provider.wallet as anchor.Wallet).payer;
+
+  let fx: PoolFixture;
+  let lpMint: PublicKey;
+  let userLp: PublicKey;
+
+  const liquidityAccounts = () => ({
+    pool: fx.pool,
+    amm: fx.amm,
+    observations: observationsPda(ammProgram, fx.pool),
+    stats: statsPda(ammProgram, fx.pool),
+    user: payer.publicKey,
+    userTokenA: fx.userTokenA,
+    userTokenB: fx.userTokenB,
+    userLpToken: userLp,
+    tokenAMint: fx.mintA,
+    tokenBMint: fx.mintB,
+    tokenAVault: fx.vaultA,
+    tokenBVault: fx.vaultB,
+    lpMint,
+    tokenProgram: TOKEN_2022_PROGRAM_ID,
+  });
+
+  const lpBalance = async () =>
+    Number((await getAccount(provider.connection, userLp, undefined, TOKEN_2022_PROGRAM_ID)).amount);
+
+  before(async () => {
+    fx = await setupPool(ammProgram, hookProgram, 1_000_000_000, 2_000_000_000);
+    lpMint = fx.lpMint;
+    userLp = await createTokenAccount(provider, payer, lpMint, payer.publicKey);
+  });
+
+  it("Mints proportional LP tokens for a deposit", async () => {
+    const before = await ammProgram.account.pool.fetch(fx.pool);
+    await ammProgram.methods
+      .addLiquidity(new anchor.BN(100_000_000), new anchor.BN(200_000_000))
+      .accountsPartial(liquidityAccounts())
+      .remainingAccounts(hookAccounts(fx))
+      .rpc();
+
+    // 10% of both reserves mints 10% of the supply
+    expect(await lpBalance()).to.equal(before.lpSupply.toNumber() / 10);
+    const stats = await ammProgram.account.poolStats.fetch(statsPda(ammProgram, fx.pool));
+    expect(stats.uniqueLps.toNumber()).to.equal(1); // Same wallet as the creator
+  });
+
+  it("Rejects an LP mint other than the pool's", async () => {
+    // A mint the caller controls must not stand in for the pool's
+    const fakeMint = await createMint(provider, payer, payer.publicKey);
+    const fakeLp = await createTokenAccount(provider, payer, fakeMint, payer.publicKey);
+    try {
+      await ammProgram.methods
+        .addLiquidity(new anchor.BN(10_000_000), new anchor.BN(20_000_000))
+        .accountsPartial({ ...liquidityAccounts(), userLpToken: fakeLp, lpMint: fakeMint })
+        .remainingAccounts(hookAccounts(fx))
+        .rpc();
+      expect.fail("a foreign LP mint should be rejected");
+    } catch (err) {
+      expect(err.toString()).to.contain("InvalidTokenPair");
+    }
+  });
+});
@@ -0,0 +1,152 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { Token2022Amm } from "../target/types/token_2022_amm";
+import { getAccount, TOKEN_2022_PROGRAM_ID } from "@solana/spl-token";
+import { PublicKey } from "@solana/web3.js";
+import { expect } from "chai";
+import { createMint, createTokenAccount, initializeAmmIfNeeded } from "./helpers";
+
+const Q64 = new anchor.BN(1).shln(64);
+
+describe("Oracle-anchored PMM pools", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const ammProgram = anchor.workspace.Token2022Amm as Program<Token2022Amm>;
+  const payer = (provider.wallet as anchor.Wallet).payer;
+
+  let amm: PublicKey;
+  let mintA: PublicKey;
+  let mintB: PublicKey;
+  let feed: PublicKey;
+  let pool: PublicKey;
+  let vaultA: PublicKey;
+  let vaultB: PublicKey;
+  let userA: PublicKey;
+  let userB: PublicKey;
+
+  // 30 bps spread, 10% skew at a one-sided inventory, 50 bps confidence, 2 s staleness
+  const params = { spreadBps: 30, skewBps: 1_000, maxConfidenceBps: 50, maxStalenessSecs: 2 };
+
+  const balance = async (account: PublicKey) =>
+    Number((await getAccount(provider.connection, account, undefined, TOKEN_2022_PROGRAM_ID)).amount);
+
+  const publish = (price: anchor.BN, confidence: anchor.BN) =>
+    ammProgram.methods.updatePriceFeed(price, confidence).accountsPartial({ feed, authority: payer.publicKey }).rpc();
+
+  const tokenAccounts = () => ({
+    tokenAMint: mintA,
+    tokenBMint: mintB,
+    tokenAVault: vaultA,
+    tokenBVault: vaultB,
+    tokenProgram: TOKEN_2022_PROGRAM_ID,
+  });
+
+  const swap = (amountIn: number, aToB: boolean) =>
+    ammProgram.methods
+      .pmmSwap(new anchor.BN(amountIn), new anchor.BN(0), aToB)
+      .accountsPartial({
+        pool,
+        amm,
+        oracle: feed,
+        user: payer.publicKey,
+        userTokenA: userA,
+        userTokenB: userB,
+        ...tokenAccounts(),
+      })
+      .rpc();
+
+  before(async () => {
+    amm = await initializeAmmIfNeeded(ammProgram);
+    mintA = await createMint(provider, payer, payer.publicKey);
+    mintB = await createMint(provider, payer, payer.publicKey);
+    vaultA = await createTokenAccount(provider, payer, mintA, amm);
+    vaultB = await createTokenAccount(provider, payer, mintB, amm);
+    userA = await createTokenAccount(provider, payer, mintA, payer.publicKey, 1_000_000_000_000);
+    userB = await createTokenAccount(provider, payer, mintB, payer.publicKey, 1_000_000_000_000);
+
+    [feed] = PublicKey.findProgramAddressSync(
+      [Buffer.from("price-feed"), payer.publicKey.toBuffer(), mintA.toBuffer(), mintB.toBuffer()],
+      ammProgram.programId
+    );
+    await ammProgram.methods
+      .initializePriceFeed(mintA, mintB)
+      .accountsPartial({ feed, authority: payer.publicKey })
+      .rpc();
+    await publish(Q64.muln(2), Q64.divn(1_000));
+
+    [pool] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pmm-pool"), mintA.toBuffer(), mintB.toBuffer(), feed.toBuffer()],
+      ammProgram.programId
+    );
+    // Balanced at the oracle price of 2 B per A
+    await ammProgram.methods
+      .createPmmPool(params, new anchor.BN(1_000_000_000), new anchor.BN(2_000_000_000))
+      .accountsPartial({
+        pool,
+        amm,
+        oracle: feed,
+        owner: payer.publicKey,
+        ownerTokenA: userA,
+        ownerTokenB: userB,
+        ...tokenAccounts(),
+      })
+      .rpc();
+  });
+
+  it("Fills at the oracle price less spread and inventory skew", async () => {
+    await publish(Q64.muln(2), Q64.divn(1_000));
+    const before = await balance(userB);
+    await swap(1_000_000, true);
+    // 997_500 after the 25 bps fee at 2 * 0.997 would pay 1_989_014; the sale
+    // leaves the pool long A, which lowers the price it pays
+    expect((await balance(userB)) - before).to.equal(1_988_816);
+  });
+
+  it("Takes the worse of the pre- and post-trade skew", async () => {
+    await publish(Q64.muln(2), Q64.divn(1_000));
+    const before = await balance(userA);
+    await swap(2_000_000, false);
+    // The pool is long A, so A starts cheap (994_615), but the buy flattens
+    // the inventory and the unskewed ask of 2 * 1.003 applies
+    expect((await balance(userA)) - before).to.equal(994_516);
+  });
+
+  it("Rejects a wide confidence interval", async () => {
+    await publish(Q64.muln(2), Q64.divn(100));
+    try {
+      await swap(1_000_000, true);
+      expect.fail("1% confidence exceeds the 50 bps limit");
+    } catch (err) {
+      expect(err.toString()).to.contain("OracleConfidenceTooWide");
+    }
+  });
+
+  it("Rejects a stale price", async () => {
+    await publish(Q64.muln(2), Q64.divn(1_000));
+    await new Promise((resolve) => setTimeout(resolve, 4_000));
+    try {
+      await swap(1_000_000, true);
+      expect.fail("price older than 2 s should be rejected");
+    } catch (err) {
+      expect(err.toString()).to.contain("OracleStale");
+    }
+  });
+
+  it("Returns the inventory to the owner", async () => {
+    const state = await ammProgram.account.pmmPool.fetch(pool);
+    await ammProgram.methods
+      .withdrawPmmLiquidity(state.tokenAAmount, state.tokenBAmount)
+      .accountsPartial({
+        pool,
+        amm,
+        owner: payer.publicKey,
+        ownerTokenA: userA,
+        ownerTokenB: userB,
+        ...tokenAccounts(),
+      })
+      .rpc();
+    expect(await balance(vaultA)).to.equal(0);
+    expect(await balance(vaultB)).to.equal(0);
+  });
+});
@@ -0,0 +1,207 @@
+//! Test-only transfer hook that calls back into `token_2022_amm` while the AMM
+//! is in the middle of a transfer. Used to check the pool reentrancy lock.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{
+    instruction::{AccountMeta, Instruction},
+    program::invoke,
+};
+use anchor_lang::InstructionData;
+use spl_tlv_account_resolution::{account::ExtraAccountMeta, state::ExtraAccountMetaList};
+use spl_transfer_hook_interface::instruction::{ExecuteInstruction, TransferHookInstruction};
+
+declare_id!("9NH6b9a7tz3n8KM8wMmVopLXTzgWoBQzq64oMGmVhxab");
+
+#[program]
+pub mod reentrant_hook {
+    use super::*;
+
+    /// Create the arm switch. Reentry only happens while it is armed.
+    pub fn initialize_arm(ctx: Context<InitializeArm>) -> Result<()> {
+        ctx.accounts.arm.armed = false;
+        Ok(())
+    }
+
+    /// Arm or disarm the callback into the AMM
+    pub fn set_armed(ctx: Context<SetArmed>, armed: bool) -> Result<()> {
+        ctx.accounts.arm.armed = armed;
+        Ok(())
+    }
+
+    /// Initialize the ExtraAccountMetas account. `reentry_accounts` is the
+    /// account list of the AMM instruction to call back into; when it is not
+    /// empty the arm account and the AMM program are prepended to it.
+    pub fn initialize_extra_account_meta_list(
+        ctx: Context<InitializeExtraAccountMetaList>,
+        amm_program: Pubkey,
+        reentry_accounts: Vec<ReentryAccount>,
+    ) -> Result<()> {
+        let mut account_metas = vec![];
+        if !reentry_accounts.is_empty() {
+            account_metas.push(ExtraAccountMeta::new_with_pubkey(&ctx.accounts.arm.key(), false, false)?);
+            account_metas.push(ExtraAccountMeta::new_with_pubkey(&amm_program, false, false)?);
+            for account in reentry_accounts.iter() {
+                account_metas.push(ExtraAccountMeta::new_with_pubkey(
+                    &account.pubkey,
+                    account.is_signer,
+                    account.is_writable,
+                )?);
+            }
+        }
+
+        let account_size = ExtraAccountMetaList::size_of(account_metas.len())? as u64;
+        let lamports = Rent::get()?.minimum_balance(account_size as usize);
+
+        let mint = ctx.accounts.mint.key();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"extra-account-metas",
+            mint.as_ref(),
+            &[ctx.bumps.extra_account_meta_list],
+        ]];
+
+        anchor_lang::system_program::create_account(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::CreateAccount {
+                    from: ctx.accounts.payer.to_account_info(),
+                    to: ctx.accounts.extra_account_meta_list.to_account_info(),
+                },
+            )
+            .with_signer(signer_seeds),
+            lamports,
+            account_size,
+            ctx.program_id,
+        )?;
+
+        ExtraAccountMetaList::init::<ExecuteInstruction>(
+            &mut ctx.accounts.extra_account_meta_list.try_borrow_mut_data()?,
+            &account_metas,
+        )?;
+        Ok(())
+    }
+
+    /// Execute transfer hook - calls `token_2022_amm::swap` with the extra
+    /// accounts when armed
+    pub fn execute<'info>(
+        ctx: Context<'_, '_, 'info, 'info, TransferHook<'info>>,
+        _amount: u64,
+    ) -> Result<()> {
+        let [arm, amm_program, reentry_accounts @ ..] = ctx.remaining_accounts else {
+            return Ok(());
+        };
+        if !Account::<Arm>::try_from(arm)?.armed {
+            return Ok(());
+        }
+
+        msg!("Reentering AMM program {}", amm_program.key());
+        let ix = Instruction {
+            program_id: amm_program.key(),
+            accounts: reentry_accounts
+                .iter()
+                .map(|a| {
+                    if a.is_writable {
+                        AccountMeta::new(a.key(), a.is_signer)
+                    } else {
+                        AccountMeta::new_readonly(a.key(), a.is_signer)
+                    }
+                })
+                .collect(),
+            data: token_2022_amm::instruction::Swap {
+                amount_in: 1,
+                minimum_amount_out: 0,
+            }
+            .data(),
+        };
+        invoke(&ix, ctx.remaining_accounts)?;
+        Ok(())
+    }
+
+    /// Fallback instruction handler for the transfer hook interface
+    pub fn fallback<'info>(
+        program_id: &Pubkey,
+        accounts: &'info [AccountInfo<'info>],
+        data: &[u8],
+    ) -> Result<()> {
+        let instruction = TransferHookInstruction::unpack(data)?;
+
+        match instruction {
+            TransferHookInstruction::Execute { amount } => {
+                let amount_bytes = amount.to_le_bytes();
+                __private::__global::execute(program_id, accounts, &amount_bytes)
+            }
+            _ => Err(ProgramError::InvalidInstructionData.into()),
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ReentryAccount {
+    pub pubkey: Pubkey,
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+#[derive(Accounts)]
+pub struct InitializeArm<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Arm::INIT_SPACE,
+        seeds = [b"arm"],
+        bump
+    )]
+    pub arm: Account<'info, Arm>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetArmed<'info> {
+    #[account(mut, seeds = [b"arm"], bump)]
+    pub arm: Account<'info, Arm>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeExtraAccountMetaList<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: ExtraAccountMetaList Account, must use these seeds
+    #[account(
+        mut,
+        seeds = [b"extra-account-metas", mint.key().as_ref()],
+        bump
+    )]
+    pub extra_account_meta_list: AccountInfo<'info>,
+
+    /// CHECK: Only used for the ExtraAccountMetaList seeds
+    pub mint: UncheckedAccount<'info>,
+    #[account(seeds = [b"arm"], bump)]
+    pub arm: Account<'info, Arm>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct TransferHook<'info> {
+    /// CHECK: source token account
+    pub source_token: UncheckedAccount<'info>,
+    /// CHECK: mint
+    pub mint: UncheckedAccount<'info>,
+    /// CHECK: destination token account
+    pub destination_token: UncheckedAccount<'info>,
+    /// CHECK: source token account authority
+    pub owner: UncheckedAccount<'info>,
+    /// CHECK: ExtraAccountMetaList Account
+    #[account(
+        seeds = [b"extra-account-metas", mint.key().as_ref()],
+        bump
+    )]
+    pub extra_account_meta_list: UncheckedAccount<'info>,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Arm {
+    pub armed: bool,
+}
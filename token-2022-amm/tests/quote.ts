@@ -0,0 +1,89 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { Token2022Amm } from "../target/types/token_2022_amm";
+import { TokenHook } from "../target/types/token_hook";
+import { getAccount, TOKEN_2022_PROGRAM_ID } from "@solana/spl-token";
+import { expect } from "chai";
+import { PoolFixture, setupPool, swapAccounts } from "./helpers";
+
+describe("Swap quotes", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const ammProgram = anchor.workspace.Token2022Amm as Program<Token2022Amm>;
+  const hookProgram = anchor.workspace.TokenHook as Program<TokenHook>;
+
+  let fx: PoolFixture;
+
+  const balance = async (account: anchor.web3.PublicKey) =>
+    new anchor.BN(
+      (await getAccount(provider.connection, account, undefined, TOKEN_2022_PROGRAM_ID)).amount.toString()
+    );
+
+  before(async () => {
+    fx = await setupPool(ammProgram, hookProgram, 1_000_000_000, 4_000_000_000);
+  });
+
+  it("Quotes exactly what swap pays out, without touching the pool", async () => {
+    const before = await ammProgram.account.pool.fetch(fx.pool);
+    const quote = await ammProgram.methods
+      .quoteSwap(new anchor.BN(10_000_000), fx.mintA)
+      .accounts({ pool: fx.pool, amm: fx.amm })
+      .view();
+
+    const unchanged = await ammProgram.account.pool.fetch(fx.pool);
+    expect(unchanged.tokenAAmount.toString()).to.equal(before.tokenAAmount.toString());
+
+    expect(quote.fee.toNumber()).to.equal(25_000); // 0.25% of 10M
+    expect(quote.priceImpactBps.toNumber()).to.be.within(90, 110); // ~1% of the A side
+    expect(quote.reserveInAfter.toString()).to.equal(before.tokenAAmount.addn(10_000_000).toString());
+
+    const received = await balance(fx.userTokenB);
+    await ammProgram.methods
+      .swap(new anchor.BN(10_000_000), quote.amountOut)
+      .accountsPartial(swapAccounts(ammProgram, fx, true))
+      .rpc();
+    expect((await balance(fx.userTokenB)).sub(received).toString()).to.equal(
+      quote.amountOut.toString()
+    );
+
+    const after = await ammProgram.account.pool.fetch(fx.pool);
+    expect(after.tokenAAmount.toString()).to.equal(quote.reserveInAfter.toString());
+    expect(after.tokenBAmount.toString()).to.equal(quote.reserveOutAfter.toString());
+  });
+
+  it("Quotes the input needed for an exact output in the B to A direction", async () => {
+    const wanted = new anchor.BN(1_000_000);
+    const quote = await ammProgram.methods
+      .quoteExactOut(wanted, fx.mintB)
+      .accounts({ pool: fx.pool, amm: fx.amm })
+      .view();
+    expect(quote.amountOut.toString()).to.equal(wanted.toString());
+
+    // One token less must fall short of the requested output
+    const short = await ammProgram.methods
+      .quoteSwap(quote.amountIn.subn(1), fx.mintB)
+      .accounts({ pool: fx.pool, amm: fx.amm })
+      .view();
+    expect(short.amountOut.lt(wanted)).to.equal(true);
+
+    const received = await balance(fx.userTokenA);
+    await ammProgram.methods
+      .swap(quote.amountIn, wanted)
+      .accountsPartial(swapAccounts(ammProgram, fx, false))
+      .rpc();
+    expect((await balance(fx.userTokenA)).sub(received).toString()).to.equal(wanted.toString());
+  });
+
+  it("Rejects a mint that is not in the pool", async () => {
+    try {
+      await ammProgram.methods
+        .quoteSwap(new anchor.BN(1_000), anchor.web3.Keypair.generate().publicKey)
+        .accounts({ pool: fx.pool, amm: fx.amm })
+        .view();
+      expect.fail("quote should reject a foreign mint");
+    } catch (err) {
+      expect(err.toString()).to.contain("InvalidTokenPair");
+    }
+  });
+});
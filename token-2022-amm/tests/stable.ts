@@ -0,0 +1,155 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { Token2022Amm } from "../target/types/token_2022_amm";
+import { TokenHook } from "../target/types/token_hook";
+import { getAccount, TOKEN_2022_PROGRAM_ID } from "@solana/spl-token";
+import { Keypair, PublicKey } from "@solana/web3.js";
+import { expect } from "chai";
+import { airdrop, PoolFixture, setupPool, swapAccounts } from "./helpers";
+
+const DAY = 86_400;
+
+const stableCurve = (amp: number) => ({
+  stable: {
+    initialAmp: new anchor.BN(amp),
+    targetAmp: new anchor.BN(amp),
+    rampStartTs: new anchor.BN(0),
+    rampStopTs: new anchor.BN(0),
+  },
+});
+
+describe("StableSwap pools", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const ammProgram = anchor.workspace.Token2022Amm as Program<Token2022Amm>;
+  const hookProgram = anchor.workspace.TokenHook as Program<TokenHook>;
+
+  let stable: PoolFixture;
+  let constantProduct: PoolFixture;
+
+  const quote = (fx: PoolFixture, amountIn: number) =>
+    ammProgram.methods
+      .quoteSwap(new anchor.BN(amountIn), fx.mintA)
+      .accounts({ pool: fx.pool, amm: fx.amm })
+      .view();
+
+  const balance = async (account: PublicKey) =>
+    Number((await getAccount(provider.connection, account, undefined, TOKEN_2022_PROGRAM_ID)).amount);
+
+  const now = async () => provider.connection.getBlockTime(await provider.connection.getSlot());
+
+  before(async () => {
+    stable = await setupPool(ammProgram, hookProgram, 1_000_000_000, 1_000_000_000, stableCurve(100));
+    constantProduct = await setupPool(ammProgram, hookProgram, 1_000_000_000, 1_000_000_000);
+  });
+
+  it("Prices a balanced pool close to 1:1", async () => {
+    const stableQuote = await quote(stable, 100_000_000);
+    const cpQuote = await quote(constantProduct, 100_000_000);
+
+    // 10% of the reserve moves the constant-product price ~9%, the stable one barely
+    expect(stableQuote.priceImpactBps.toNumber()).to.be.below(10);
+    expect(cpQuote.priceImpactBps.toNumber()).to.be.above(800);
+    expect(stableQuote.amountOut.gt(cpQuote.amountOut)).to.equal(true);
+    expect(stableQuote.amountOut.lte(stableQuote.amountIn.sub(stableQuote.fee))).to.equal(true);
+  });
+
+  it("Rejects a pair whose mints have different decimals", async () => {
+    // One raw unit of a 6-decimal mint is worth 1000 of a 9-decimal one
+    try {
+      await setupPool(ammProgram, hookProgram, 1_000_000, 1_000_000_000, stableCurve(100), [6, 9]);
+      expect.fail("a stable pool must not peg raw units across decimals");
+    } catch (err) {
+      expect(err.toString()).to.contain("MismatchedDecimals");
+    }
+  });
+
+  it("Converges on a heavily imbalanced pool and executes the quote", async () => {
+    const fx = await setupPool(ammProgram, hookProgram, 1_000_000, 100_000_000_000, stableCurve(10));
+    const q = await quote(fx, 50_000_000);
+    expect(q.amountOut.toNumber()).to.be.above(50_000_000);
+
+    await ammProgram.methods
+      .swap(q.amountIn, q.amountOut)
+      .accountsPartial(swapAccounts(ammProgram, fx, true))
+      .rpc();
+    const pool = await ammProgram.account.pool.fetch(fx.pool);
+    expect(pool.tokenBAmount.toString()).to.equal(q.reserveOutAfter.toString());
+  });
+
+  it("Quotes an exact output that swap honours", async () => {
+    const wanted = new anchor.BN(5_000_000);
+    const q = await ammProgram.methods
+      .quoteExactOut(wanted, stable.mintA)
+      .accounts({ pool: stable.pool, amm: stable.amm })
+      .view();
+    const expected = (await quote(stable, q.amountIn.toNumber())).amountOut.toNumber();
+    const before = await balance(stable.userTokenB);
+    await ammProgram.methods
+      .swap(q.amountIn, wanted)
+      .accountsPartial(swapAccounts(ammProgram, stable, true))
+      .rpc();
+    const received = (await balance(stable.userTokenB)) - before;
+    expect(received).to.equal(expected);
+    expect(received).to.be.at.least(wanted.toNumber());
+  });
+
+  it("Rejects amplification ramps from anyone but the AMM authority", async () => {
+    const stranger = Keypair.generate();
+    await airdrop(provider, stranger.publicKey);
+    try {
+      await ammProgram.methods
+        .rampA(new anchor.BN(200), new anchor.BN((await now()) + 2 * DAY))
+        .accountsPartial({ pool: stable.pool, amm: stable.amm, authority: stranger.publicKey })
+        .signers([stranger])
+        .rpc();
+      expect.fail("ramp should require the AMM authority");
+    } catch (err) {
+      expect(err.toString()).to.contain("Unauthorized");
+    }
+  });
+
+  it("Rejects ramps that are too fast or too large", async () => {
+    for (const [target, duration] of [
+      [200, DAY / 2],
+      [5_000, 2 * DAY],
+    ]) {
+      try {
+        await ammProgram.methods
+          .rampA(new anchor.BN(target), new anchor.BN((await now()) + duration))
+          .accountsPartial({ pool: stable.pool, amm: stable.amm })
+          .rpc();
+        expect.fail("ramp should be rejected");
+      } catch (err) {
+        expect(err.toString()).to.contain("InvalidCurveParameters");
+      }
+    }
+  });
+
+  it("Starts a ramp from the current amplification", async () => {
+    const stop = (await now()) + 7 * DAY;
+    await ammProgram.methods
+      .rampA(new anchor.BN(1_000), new anchor.BN(stop))
+      .accountsPartial({ pool: stable.pool, amm: stable.amm })
+      .rpc();
+
+    const pool = await ammProgram.account.pool.fetch(stable.pool);
+    const curve = pool.curve.stable;
+    expect(curve.initialAmp.toNumber()).to.equal(100);
+    expect(curve.targetAmp.toNumber()).to.equal(1_000);
+    expect(curve.rampStopTs.toNumber()).to.equal(stop);
+  });
+
+  it("Rejects ramping a constant-product pool", async () => {
+    try {
+      await ammProgram.methods
+        .rampA(new anchor.BN(100), new anchor.BN((await now()) + 2 * DAY))
+        .accountsPartial({ pool: constantProduct.pool, amm: constantProduct.amm })
+        .rpc();
+      expect.fail("constant-product pools have no amplification");
+    } catch (err) {
+      expect(err.toString()).to.contain("InvalidCurveParameters");
+    }
+  });
+});
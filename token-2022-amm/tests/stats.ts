@@ -0,0 +1,52 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { Token2022Amm } from "../target/types/token_2022_amm";
+import { TokenHook } from "../target/types/token_hook";
+import { expect } from "chai";
+import { PoolFixture, setupPool, statsPda, swapAccounts } from "./helpers";
+
+describe("Pool statistics", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const ammProgram = anchor.workspace.Token2022Amm as Program<Token2022Amm>;
+  const hookProgram = anchor.workspace.TokenHook as Program<TokenHook>;
+
+  let fx: PoolFixture;
+
+  const stats = () =>
+    ammProgram.methods.getPoolStats().accounts({ pool: fx.pool }).view();
+
+  before(async () => {
+    fx = await setupPool(ammProgram, hookProgram, 1_000_000_000, 1_000_000_000);
+  });
+
+  it("Counts the pool creator as the first LP", async () => {
+    const s = await stats();
+    expect(s.pool.toBase58()).to.equal(fx.pool.toBase58());
+    expect(s.uniqueLps.toNumber()).to.equal(1);
+    expect(s.swapCount.toNumber()).to.equal(0);
+  });
+
+  it("Accumulates volume and LP fees per token across swaps", async () => {
+    await ammProgram.methods
+      .swap(new anchor.BN(4_000_000), new anchor.BN(0))
+      .accountsPartial(swapAccounts(ammProgram, fx, true))
+      .rpc();
+    await ammProgram.methods
+      .swap(new anchor.BN(2_000_000), new anchor.BN(0))
+      .accountsPartial(swapAccounts(ammProgram, fx, false))
+      .rpc();
+
+    const s = await stats();
+    const account = await ammProgram.account.poolStats.fetch(statsPda(ammProgram, fx.pool));
+    expect(s.swapCount.toNumber()).to.equal(2);
+    expect(s.lpFeesA.toNumber()).to.equal(10_000); // 0.25% of 4M
+    expect(s.lpFeesB.toNumber()).to.equal(5_000); // 0.25% of 2M
+    // Each side counts its input plus what it paid out
+    expect(s.volumeA.gt(new anchor.BN(4_000_000))).to.equal(true);
+    expect(s.volumeB.gt(new anchor.BN(2_000_000))).to.equal(true);
+    expect(s.protocolFeesA.toNumber()).to.equal(0);
+    expect(s.lastActivitySlot.toString()).to.equal(account.lastActivitySlot.toString());
+  });
+});
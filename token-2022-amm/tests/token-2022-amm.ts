@@ -254,7 +254,7 @@ describe("Token-2022 AMM with Transfer Hooks", () => {
   it("Creates a liquidity pool", async () => {
     try {
       await ammProgram.methods
-        .createPool(new anchor.BN(1000000), new anchor.BN(1000000)) // 1M tokens each
+        .createPool(new anchor.BN(1000000), new anchor.BN(1000000), { constantProduct: {} }) // 1M tokens each
         .accounts({
           pool: poolPda,
           amm: ammPda,
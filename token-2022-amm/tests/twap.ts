@@ -0,0 +1,75 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { Token2022Amm } from "../target/types/token_2022_amm";
+import { TokenHook } from "../target/types/token_hook";
+import { expect } from "chai";
+import { observationsPda, PoolFixture, setupPool, swapAccounts, waitSlots } from "./helpers";
+
+const Q64 = new anchor.BN(1).shln(64);
+
+describe("TWAP price oracle", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const ammProgram = anchor.workspace.Token2022Amm as Program<Token2022Amm>;
+  const hookProgram = anchor.workspace.TokenHook as Program<TokenHook>;
+
+  let fx: PoolFixture;
+
+  const consult = (window: number) =>
+    ammProgram.methods
+      .consult(new anchor.BN(window))
+      .accounts({ pool: fx.pool })
+      .view();
+
+  before(async () => {
+    // 1:2 pool, so A trades at 2 B
+    fx = await setupPool(ammProgram, hookProgram, 1_000_000_000, 2_000_000_000);
+  });
+
+  it("Seeds an observation when the pool is created", async () => {
+    const observations = await ammProgram.account.observations.fetch(
+      observationsPda(ammProgram, fx.pool)
+    );
+    expect(observations.pool.toBase58()).to.equal(fx.pool.toBase58());
+    expect(observations.len).to.equal(1);
+  });
+
+  it("Returns the constant price while reserves are unchanged", async () => {
+    await waitSlots(provider, 3);
+    const twap = await consult(2);
+
+    expect(twap.priceA.div(Q64).toNumber()).to.equal(2);
+    expect(twap.priceB.mul(new anchor.BN(2)).div(Q64).toNumber()).to.be.within(0, 1);
+    expect(twap.endSlot.sub(twap.startSlot).toNumber()).to.be.at.least(2);
+  });
+
+  it("Accumulates on the first swap of a slot", async () => {
+    const before = await ammProgram.account.pool.fetch(fx.pool);
+    await ammProgram.methods
+      .swap(new anchor.BN(1_000_000), new anchor.BN(0))
+      .accountsPartial(swapAccounts(ammProgram, fx, true))
+      .rpc();
+
+    const after = await ammProgram.account.pool.fetch(fx.pool);
+    expect(after.lastUpdateSlot.gt(before.lastUpdateSlot)).to.equal(true);
+    expect(after.priceACumulative.gt(before.priceACumulative)).to.equal(true);
+
+    const observations = await ammProgram.account.observations.fetch(
+      observationsPda(ammProgram, fx.pool)
+    );
+    expect(observations.len).to.equal(2);
+    expect(observations.observations[observations.index].slot.toString()).to.equal(
+      after.lastUpdateSlot.toString()
+    );
+  });
+
+  it("Rejects windows older than the recorded history", async () => {
+    try {
+      await consult(1_000_000);
+      expect.fail("consult should fail without enough history");
+    } catch (err) {
+      expect(err.toString()).to.contain("TwapWindowUnavailable");
+    }
+  });
+});
@@ -0,0 +1,86 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { Token2022Amm } from "../target/types/token_2022_amm";
+import { TokenHook } from "../target/types/token_hook";
+import { expect } from "chai";
+import { PoolFixture, setupPool, swapAccounts } from "./helpers";
+
+const WEIGHT_ONE = 1_000_000_000;
+
+const weightedCurve = (weightA: number) => ({
+  weighted: {
+    weightA: new anchor.BN(weightA),
+    weightB: new anchor.BN(WEIGHT_ONE - weightA),
+  },
+});
+
+// floor(Ro * (1 - (Ri / (Ri + in_after_fee))^(w_in / w_out))) for an 80/20
+// pool holding 1e9 A and 4e9 B, computed with mpmath at 50 digits
+const REFERENCE_VECTORS = [
+  { aToB: true, amountIn: 10_000_000, amountOut: 155_698_012 },
+  { aToB: false, amountIn: 10_000_000, amountOut: 622_467 },
+  { aToB: true, amountIn: 1_000_000_000, amountOut: 3_748_746_083 },
+  { aToB: false, amountIn: 1_000_000_000, amountOut: 54_140_136 },
+];
+
+describe("Weighted pools", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const ammProgram = anchor.workspace.Token2022Amm as Program<Token2022Amm>;
+  const hookProgram = anchor.workspace.TokenHook as Program<TokenHook>;
+
+  let fx: PoolFixture;
+
+  const quote = (amountIn: number, aToB: boolean) =>
+    ammProgram.methods
+      .quoteSwap(new anchor.BN(amountIn), aToB ? fx.mintA : fx.mintB)
+      .accounts({ pool: fx.pool, amm: fx.amm })
+      .view();
+
+  before(async () => {
+    fx = await setupPool(ammProgram, hookProgram, 1_000_000_000, 4_000_000_000, weightedCurve(800_000_000));
+  });
+
+  it("Matches the reference vectors, rounding in the pool's favour", async () => {
+    for (const v of REFERENCE_VECTORS) {
+      const q = await quote(v.amountIn, v.aToB);
+      expect(q.amountOut.toNumber()).to.be.within(v.amountOut - 1, v.amountOut);
+    }
+  });
+
+  it("Prices A at w_a / w_b times the reserve ratio", async () => {
+    // Spot price (4e9 / 0.2) / (1e9 / 0.8) = 16 B per A, so a tiny trade
+    // executes at ~16 less the fee
+    const q = await quote(100_000, true);
+    expect(q.amountOut.toNumber()).to.be.within(1_595_000, 1_596_000);
+    expect(q.priceImpactBps.toNumber()).to.be.below(5);
+  });
+
+  it("Quotes an exact output that swap honours", async () => {
+    const wanted = new anchor.BN(2_000_000);
+    const q = await ammProgram.methods
+      .quoteExactOut(wanted, fx.mintB)
+      .accounts({ pool: fx.pool, amm: fx.amm })
+      .view();
+
+    await ammProgram.methods
+      .swap(q.amountIn, wanted)
+      .accountsPartial(swapAccounts(ammProgram, fx, false))
+      .rpc();
+  });
+
+  it("Rejects weights that do not sum to one or fall below 1%", async () => {
+    for (const curve of [
+      { weighted: { weightA: new anchor.BN(800_000_000), weightB: new anchor.BN(100_000_000) } },
+      weightedCurve(5_000_000),
+    ]) {
+      try {
+        await setupPool(ammProgram, hookProgram, 1_000_000, 1_000_000, curve);
+        expect.fail("invalid weights should be rejected");
+      } catch (err) {
+        expect(err.toString()).to.contain("InvalidCurveParameters");
+      }
+    }
+  });
+});